@@ -0,0 +1,352 @@
+//
+// ウィンドウ無しのベンチマーク
+//
+// cargo run --release --example bench -- --scenario missiles --count 10000 --frames 600
+// cargo run --release --example bench -- --scenario missiles --output base.json --output base.csv
+// cargo run --release --example bench -- --scenario missiles --count 10000 --batch-size 1024
+// cargo run --release --example bench -- --scenario missiles --count 10000 --threads 4
+// cargo run --release --example bench -- --compare base.json new.json --threshold 10
+//
+// 比較では --metrics（既定は mean,p95）の値が --threshold (%) より遅くなると終了コード 1 で終わります。
+//
+// シナリオ
+// - missiles:  円を描いて動くターゲットを count 発の追跡ミサイルが追う
+// - colliders: count 個の矩形が動き回り、総当たりで当たり判定をする
+//
+
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+use bevy::tasks::{ComputeTaskPool, TaskPoolBuilder};
+use bevy::transform::TransformPlugin;
+use bevy_examples::bench::{
+    run_frames, BenchAppExt, BenchMetadata, BenchPlugin, BenchResult, Comparison, Stats,
+};
+use bevy_examples::look_at::LookTarget;
+use bevy_examples::pause::gameplay_timestep;
+use bevy_examples::tracking::{
+    TargetSnapshotSystem, TrackingMissile, TrackingPlugin, TrackingSystem,
+};
+use bevy_examples::TIME_STEP;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const USAGE: &str = "usage: bench [--scenario missiles|colliders] [--count N] [--frames N] [--warmup N] [--seed N] [--batch-size N] [--threads N] [--output FILE.json|FILE.csv]...
+       bench --compare BASE.json NEW.json [--threshold PERCENT] [--metrics mean,p95]";
+
+/// 物体を配置する範囲
+const ARENA: f32 = 1000.0;
+
+struct Options {
+    scenario: String,
+    count: usize,
+    frames: usize,
+    warmup: usize,
+    seed: u64,
+    /// 追跡ミサイルを並列に処理する時の1タスクあたりの数
+    batch_size: usize,
+    /// `ComputeTaskPool` のスレッド数（指定しない場合は論理コア数から決める）
+    threads: Option<usize>,
+    outputs: Vec<String>,
+    /// 比較する2つの結果ファイル
+    compare: Option<(String, String)>,
+    threshold: f64,
+    metrics: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            scenario: "missiles".to_owned(),
+            count: 1000,
+            frames: 600,
+            warmup: 60,
+            seed: 0,
+            batch_size: TrackingPlugin::default().batch_size,
+            threads: None,
+            outputs: Vec::new(),
+            compare: None,
+            threshold: 10.0,
+            metrics: vec!["mean".to_owned(), "p95".to_owned()],
+        };
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--scenario" => options.scenario = value()?,
+                "--count" => options.count = parse_number(&arg, value()?)?,
+                "--frames" => options.frames = parse_number(&arg, value()?)?,
+                "--warmup" => options.warmup = parse_number(&arg, value()?)?,
+                "--seed" => options.seed = parse_number(&arg, value()?)?,
+                "--batch-size" => options.batch_size = parse_number(&arg, value()?)?,
+                "--threads" => options.threads = Some(parse_number(&arg, value()?)?),
+                "--output" => options.outputs.push(value()?),
+                "--compare" => options.compare = Some((value()?, value()?)),
+                "--threshold" => options.threshold = parse_number(&arg, value()?)?,
+                "--metrics" => {
+                    options.metrics = value()?.split(',').map(str::to_owned).collect();
+                    if let Some(metric) = options
+                        .metrics
+                        .iter()
+                        .find(|metric| !Stats::METRICS.contains(&metric.as_str()))
+                    {
+                        return Err(format!("unknown metric {}", metric));
+                    }
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", arg, value))
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some((base, new)) = &options.compare {
+        std::process::exit(compare(base, new, &options));
+    }
+
+    let mut app = App::new();
+    if let Some(threads) = options.threads {
+        // CorePlugin は既にあるタスクプールを置き換えない
+        app.insert_resource(ComputeTaskPool(
+            TaskPoolBuilder::new()
+                .num_threads(threads.max(1))
+                .thread_name("Compute Task Pool".to_owned())
+                .build(),
+        ));
+    }
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(BenchPlugin)
+        .insert_resource(StdRng::seed_from_u64(options.seed));
+
+    match options.scenario.as_str() {
+        "missiles" => missiles::build(&mut app, options.count, options.batch_size),
+        "colliders" => colliders::build(&mut app, options.count),
+        other => {
+            eprintln!("unknown scenario {}\n{}", other, USAGE);
+            std::process::exit(2);
+        }
+    }
+
+    let result = BenchResult {
+        scenario: options.scenario.clone(),
+        count: options.count,
+        warmup: options.warmup,
+        metadata: BenchMetadata {
+            compute_threads: app.world.resource::<ComputeTaskPool>().thread_num(),
+            ..BenchMetadata::collect()
+        },
+        report: run_frames(&mut app, options.frames, options.warmup),
+    };
+    println!(
+        "scenario: {}  count: {}  frames: {}  warmup: {}  threads: {}",
+        options.scenario,
+        options.count,
+        options.frames,
+        options.warmup,
+        result.metadata.compute_threads
+    );
+    print!("{}", result.report);
+
+    for output in &options.outputs {
+        if let Err(e) = result.save(output) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+        println!("saved {}", output);
+    }
+}
+
+///
+/// 2つの結果を比較して終了コードを返す
+///
+fn compare(base: &str, new: &str, options: &Options) -> i32 {
+    let (base, new) = match (BenchResult::load(base), BenchResult::load(new)) {
+        (Ok(base), Ok(new)) => (base, new),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if base.scenario != new.scenario || base.count != new.count {
+        eprintln!(
+            "warning: comparing different runs ({} x{} vs {} x{})",
+            base.scenario, base.count, new.scenario, new.count
+        );
+    }
+    let revision = |result: &BenchResult| {
+        let revision = result
+            .metadata
+            .git_revision
+            .clone()
+            .unwrap_or_else(|| "unknown".to_owned());
+        format!("{} ({} threads)", revision, result.metadata.compute_threads)
+    };
+    println!("base: {}  new: {}", revision(&base), revision(&new));
+
+    let metrics: Vec<&str> = options.metrics.iter().map(String::as_str).collect();
+    let comparison = match Comparison::new(&base.report, &new.report, &metrics, options.threshold) {
+        Ok(comparison) => comparison,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    print!("{}", comparison);
+
+    if comparison.has_regression() {
+        eprintln!("regression over {}% detected", options.threshold);
+        1
+    } else {
+        0
+    }
+}
+
+fn random_position(rng: &mut StdRng) -> Vec3 {
+    Vec3::new(
+        rng.gen_range(-ARENA..ARENA),
+        rng.gen_range(-ARENA..ARENA),
+        0.0,
+    )
+}
+
+///
+/// 追跡ミサイル
+///
+mod missiles {
+    use super::*;
+
+    #[derive(Component)]
+    struct Target;
+
+    #[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+    struct TargetSystem;
+
+    pub fn build(app: &mut App, count: usize, batch_size: usize) {
+        let mut rng = app.world.remove_resource::<StdRng>().unwrap();
+
+        let target = app
+            .world
+            .spawn()
+            .insert_bundle(TransformBundle::default())
+            .insert(Target)
+            .id();
+
+        for _ in 0..count {
+            app.world
+                .spawn()
+                .insert_bundle(TransformBundle::from_transform(Transform {
+                    translation: random_position(&mut rng),
+                    rotation: Quat::from_rotation_z(rng.gen_range(0.0..std::f32::consts::TAU)),
+                    ..Default::default()
+                }))
+                .insert(TrackingMissile::new(LookTarget::Entity(target)));
+        }
+
+        app.add_plugin(TrackingPlugin::default().with_batch_size(batch_size))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(gameplay_timestep)
+                    .with_system(target_movement.label(TargetSystem)),
+            )
+            .time_label("target_movement", TargetSystem, true)
+            .time_label("target_snapshot", TargetSnapshotSystem, true)
+            .time_label("tracking_missile_movement", TrackingSystem, true);
+    }
+
+    fn target_movement(mut time: Local<f32>, mut query: Query<&mut Transform, With<Target>>) {
+        *time += TIME_STEP;
+        for mut tf in query.iter_mut() {
+            tf.translation = Vec3::new(time.cos(), time.sin(), 0.0) * ARENA * 0.5;
+        }
+    }
+}
+
+///
+/// 総当たりの当たり判定
+///
+mod colliders {
+    use super::*;
+
+    #[derive(Component)]
+    struct Collider {
+        size: Vec2,
+        velocity: Vec2,
+    }
+
+    #[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+    enum ColliderSystem {
+        Movement,
+        Collision,
+    }
+
+    pub fn build(app: &mut App, count: usize) {
+        let mut rng = app.world.remove_resource::<StdRng>().unwrap();
+
+        for _ in 0..count {
+            let size = rng.gen_range(8.0..32.0);
+            app.world
+                .spawn()
+                .insert_bundle(TransformBundle::from_transform(
+                    Transform::from_translation(random_position(&mut rng)),
+                ))
+                .insert(Collider {
+                    size: Vec2::splat(size),
+                    velocity: Vec2::new(rng.gen_range(-200.0..200.0), rng.gen_range(-200.0..200.0)),
+                });
+        }
+
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(movement.label(ColliderSystem::Movement))
+                .with_system(
+                    collision
+                        .label(ColliderSystem::Collision)
+                        .after(ColliderSystem::Movement),
+                ),
+        )
+        .time_label("movement", ColliderSystem::Movement, true)
+        .time_label("collision", ColliderSystem::Collision, true);
+    }
+
+    fn movement(mut query: Query<(&mut Transform, &mut Collider)>) {
+        for (mut tf, mut collider) in query.iter_mut() {
+            tf.translation += (collider.velocity * TIME_STEP).extend(0.0);
+
+            // 範囲の端で跳ね返る
+            if tf.translation.x.abs() > ARENA {
+                collider.velocity.x = -collider.velocity.x;
+            }
+            if tf.translation.y.abs() > ARENA {
+                collider.velocity.y = -collider.velocity.y;
+            }
+        }
+    }
+
+    fn collision(query: Query<(&Transform, &Collider)>) {
+        let hits = query
+            .iter_combinations()
+            .filter(|[(a_tf, a), (b_tf, b)]| {
+                collide(a_tf.translation, a.size, b_tf.translation, b.size).is_some()
+            })
+            .count();
+        // 計算が最適化で消されないようにする
+        std::hint::black_box(hits);
+    }
+}
@@ -0,0 +1,112 @@
+// #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+//
+// 敵が弾幕パターンを発射する
+//
+// パターンは assets/patterns/enemy_a.emitter.ron に記述されています。
+//
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::settings::{Backends, WgpuSettings},
+};
+use bevy_examples::emitter::{Emitter, EmitterPlugin};
+use bevy_examples::look_at::LookTarget;
+use bevy_examples::pause::gameplay_timestep;
+use bevy_examples::TIME_STEP;
+
+const WINDOW_HEIGHT: f32 = 800.0;
+const WINDOW_WIDTH: f32 = 600.0;
+
+const PLAYER_SPEED: f32 = 160.0;
+
+fn main() {
+    App::new()
+        .insert_resource(WgpuSettings {
+            backends: Some(Backends::VULKAN),
+            ..Default::default()
+        })
+        .insert_resource(WindowDescriptor {
+            title: "BulletHell".to_owned(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(EmitterPlugin { max_bullets: 1500 })
+        .add_startup_system(setup)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(player_movement),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Player;
+
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let player = commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, -300.0, 0.0),
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/ship_C.png"),
+            ..Default::default()
+        })
+        .insert(Player)
+        .id();
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, 250.0, 0.0),
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/enemy_A.png"),
+            ..Default::default()
+        })
+        .insert(
+            Emitter::new(
+                assert_server.load("patterns/enemy_a.emitter.ron"),
+                assert_server.load("textures/うんちハニワ.png"),
+            )
+            .with_target(LookTarget::Entity(player))
+            .with_bullet_scale(Vec3::new(0.02, 0.02, 1.0)),
+        );
+}
+
+fn player_movement(
+    input_keyboard: Res<Input<KeyCode>>,
+    mut query: Query<&mut Transform, With<Player>>,
+) {
+    let x_dir = if input_keyboard.pressed(KeyCode::A) {
+        -1.
+    } else if input_keyboard.pressed(KeyCode::D) {
+        1.
+    } else {
+        0.
+    };
+
+    let y_dir = if input_keyboard.pressed(KeyCode::W) {
+        1.
+    } else if input_keyboard.pressed(KeyCode::S) {
+        -1.
+    } else {
+        0.
+    };
+
+    if let Ok(mut tf) = query.get_single_mut() {
+        tf.translation.x += x_dir * PLAYER_SPEED * TIME_STEP;
+        tf.translation.y += y_dir * PLAYER_SPEED * TIME_STEP;
+    }
+}
@@ -7,20 +7,32 @@
 // AMD固有のバグらしい。詳しくは https://github.com/bevyengine/bevy/issues/3606
 //
 
-use std::path::Path;
-
+use bevy::diagnostic::{DiagnosticId, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
 // use bevy::render::settings::{Backends, WgpuSettings};
-use bevy::render::texture::{CompressedImageFormats, ImageType};
 use bevy::sprite::collide_aabb::collide;
+use bevy_examples::diagnostics::{
+    diagnostic_id, CollisionEvent, DiagnosticsAppExt, FrameCounters, GameDiagnosticsPlugin,
+};
+use bevy_examples::loading::{LoadingAssets, LoadingPlugin};
+use bevy_examples::ui::diagnostics::DiagnosticsOverlayPlugin;
+
+const PLAYER_SPRITE: &str = "textures/ship_a.png";
+const ENEMY_SPRITE: &str = "textures/ship_a.png";
+
+/// 1フレームで当たり判定をした組の数
+const COLLISION_PAIRS: DiagnosticId =
+    DiagnosticId::from_u128(0x3f6a_91c2_4d7e_4b18_9e05_b2c8_7a14_d3e9);
+
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+enum AppState {
+    Loading,
+    Playing,
+}
 
-const SPRITE_DIR: &str = "assets/textures";
-const PLAYER_SPRITE: &str = "ship_a.png";
-const ENEMY_SPRITE: &str = "ship_a.png";
-
-struct SpriteInfos {
-    player: (Handle<Image>, Vec2),
-    enemy: (Handle<Image>, Vec2),
+struct SpriteHandles {
+    player: Handle<Image>,
+    enemy: Handle<Image>,
 }
 
 fn main() {
@@ -30,13 +42,25 @@ fn main() {
         //     ..Default::default()
         // })
         .add_plugins(DefaultPlugins)
+        // F3 で診断情報を表示する
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(GameDiagnosticsPlugin)
+        .add_plugin(DiagnosticsOverlayPlugin::default())
+        .add_component_count_diagnostic::<Enemy>(diagnostic_id("colliders"), "colliders")
+        .add_counter_diagnostic(COLLISION_PAIRS, "collision_pairs")
+        .add_state(AppState::Loading)
+        .add_plugin(LoadingPlugin::new(AppState::Loading, AppState::Playing))
         .add_startup_system(setup)
-        .add_startup_stage(
-            "spawn",
-            SystemStage::single(player_spawn).with_system(enemy_spawn),
+        .add_system_set(
+            SystemSet::on_enter(AppState::Playing)
+                .with_system(player_spawn)
+                .with_system(enemy_spawn),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(collision)
+                .with_system(player_movement),
         )
-        .add_system(collision)
-        .add_system(player_movement)
         .run();
 }
 
@@ -46,35 +70,25 @@ struct Enemy;
 #[derive(Component)]
 struct Player;
 
-fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<LoadingAssets>,
+) {
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(UiCameraBundle::default());
 
-    commands.insert_resource(SpriteInfos {
-        player: load_image(&mut images, PLAYER_SPRITE),
-        enemy: load_image(&mut images, ENEMY_SPRITE),
+    // 画像の大きさを使うので、読み込み画面で読み込みを待つ
+    commands.insert_resource(SpriteHandles {
+        player: loading.load(&asset_server, PLAYER_SPRITE),
+        enemy: loading.load(&asset_server, ENEMY_SPRITE),
     });
 }
 
-fn load_image(images: &mut ResMut<Assets<Image>>, path: &str) -> (Handle<Image>, Vec2) {
-    let path = Path::new(SPRITE_DIR).join(path);
-    let bytes = std::fs::read(&path).expect(&format!("Cannot find {}", path.display()));
-    let image = Image::from_buffer(
-        &bytes,
-        ImageType::MimeType("image/png"),
-        CompressedImageFormats::NONE,
-        false,
-    )
-    .unwrap();
-    let size = image.size();
-
-    let image_handle = images.add(image);
-    (image_handle, size)
-}
-
-fn enemy_spawn(mut commands: Commands, sprite_infos: Res<SpriteInfos>) {
+fn enemy_spawn(mut commands: Commands, sprite_handles: Res<SpriteHandles>) {
     commands
         .spawn_bundle(SpriteBundle {
-            texture: sprite_infos.enemy.0.clone(),
+            texture: sprite_handles.enemy.clone(),
             transform: Transform {
                 translation: Vec3::ZERO,
                 ..Default::default()
@@ -84,10 +98,10 @@ fn enemy_spawn(mut commands: Commands, sprite_infos: Res<SpriteInfos>) {
         .insert(Enemy);
 }
 
-fn player_spawn(mut commands: Commands, sprite_infos: Res<SpriteInfos>) {
+fn player_spawn(mut commands: Commands, sprite_handles: Res<SpriteHandles>) {
     commands
         .spawn_bundle(SpriteBundle {
-            texture: sprite_infos.player.0.clone(),
+            texture: sprite_handles.player.clone(),
             transform: Transform {
                 translation: Vec3::new(0., -200., 0.),
                 ..Default::default()
@@ -98,21 +112,38 @@ fn player_spawn(mut commands: Commands, sprite_infos: Res<SpriteInfos>) {
 }
 
 fn collision(
-    sprite_infos: Res<SpriteInfos>,
-    player_query: Query<&Transform, With<Player>>,
-    enemy_query: Query<&Transform, With<Enemy>>,
+    sprite_handles: Res<SpriteHandles>,
+    images: Res<Assets<Image>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    mut counters: ResMut<FrameCounters>,
+    mut collisions: EventWriter<CollisionEvent>,
 ) {
-    if let Ok(player_tf) = player_query.get_single() {
-        for enemy_tf in enemy_query.iter() {
+    // 読み込み画面で読み込み済みなので、画像の大きさを当たり判定に使う
+    let (player_size, enemy_size) = match (
+        images.get(&sprite_handles.player),
+        images.get(&sprite_handles.enemy),
+    ) {
+        (Some(player), Some(enemy)) => (player.size(), enemy.size()),
+        _ => return,
+    };
+
+    if let Ok((player, player_tf)) = player_query.get_single() {
+        for (enemy, enemy_tf) in enemy_query.iter() {
+            counters.add(COLLISION_PAIRS, 1.0);
             let collision = collide(
                 player_tf.translation,
-                sprite_infos.player.1,
+                player_size,
                 enemy_tf.translation,
-                sprite_infos.enemy.1,
+                enemy_size,
             );
 
             if let Some(_) = collision {
                 println!("collisioned");
+                collisions.send(CollisionEvent {
+                    a: player,
+                    b: enemy,
+                });
             }
         }
     }
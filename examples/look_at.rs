@@ -7,6 +7,8 @@
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy::render::settings::{Backends, WgpuSettings};
+use bevy_examples::cursor::{CursorPlugin, MainCamera};
+use bevy_examples::look_at::{z_angle, LookAt2d, LookAtPlugin, LookTarget};
 
 fn main() {
     App::new()
@@ -17,21 +19,16 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(LogDiagnosticsPlugin::default())
-        .insert_resource(MousePos(Vec2::ZERO))
+        .add_plugin(CursorPlugin)
+        .add_plugin(LookAtPlugin)
         .add_startup_system(setup)
-        .add_system(cursor_system)
-        .add_system(look_at)
+        .add_system(print_angle)
         .run();
 }
 
 #[derive(Component)]
 struct Player;
 
-#[derive(Component)]
-struct MainCamera;
-
-struct MousePos(Vec2);
-
 fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
     commands
         .spawn()
@@ -48,50 +45,16 @@ fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
             texture: assert_server.load("textures/うんちハニワ.png"),
             ..Default::default()
         })
-        .insert(Player);
-}
-
-fn cursor_system(
-    wnds: Res<Windows>,
-    mut mouse_pos: ResMut<MousePos>,
-    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-) {
-    if let Ok((camera, camera_transform)) = camera_query.get_single() {
-        if let Some(wnd) = wnds.get_primary() {
-            // カメラがウィンドウ内にあるか確認して、その位置を取得する
-
-            if let Some(screen_pos) = wnd.cursor_position() {
-                // ウィンドウサイズの取得
-                let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-
-                // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-                let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
-
-                // 投影とカメラ変換を元に戻すための行列
-                let ndc_to_world =
-                    camera_transform.compute_matrix() * camera.projection_matrix.inverse();
-
-                // ワールド座標に変換
-                let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-
-                // Vec3 -> Vec2に変換
-                let world_pos: Vec2 = world_pos.truncate();
-
-                mouse_pos.0 = world_pos;
-                println!("World coords: {}/{}", world_pos.x, world_pos.y);
-            }
-        }
-    }
+        .insert(Player)
+        .insert(
+            LookAt2d::new(LookTarget::Cursor)
+                .with_max_angular_speed(180_f32.to_radians())
+                .with_easing(8.0),
+        );
 }
 
-fn look_at(mut query: Query<&mut Transform, With<Player>>, mouse_pos: Res<MousePos>) {
-    if let Ok(mut tf) = query.get_single_mut() {
-        tf.look_at(Vec3::Z, Vec3::from((mouse_pos.0, 0.)));
-
-        let rotation = tf
-            .looking_at(Vec3::Z, Vec3::from((mouse_pos.0, 0.)))
-            .rotation;
-
-        println!("{}", rotation.x.atan2(rotation.y).to_degrees() * 2.0);
+fn print_angle(query: Query<&Transform, (With<Player>, Changed<Transform>)>) {
+    if let Ok(tf) = query.get_single() {
+        debug!("{}", z_angle(tf.rotation).to_degrees());
     }
 }
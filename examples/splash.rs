@@ -1,39 +1,154 @@
 // #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 //
-// スプラッシュ画面の表示とボタンアクションのログ
+// スプラッシュ画面の表示とメニュー（マウス・キーボード・ゲームパッド操作）
+//
+// F3 で診断情報を表示し、F4 で画面（状態）の切り替えの履歴をログに出します。
 //
 
+use bevy::asset::AssetServerSettings;
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 use bevy::prelude::*;
 use bevy::render::settings::{Backends, WgpuSettings};
+use bevy_examples::diagnostics::GameDiagnosticsPlugin;
+use bevy_examples::loading::{LoadingAssets, LoadingPlugin};
+use bevy_examples::localization::{Localization, LocalizationPlugin};
+use bevy_examples::pause::{Pause, PausePlugin};
+use bevy_examples::settings::{Settings, SettingsPlugin, SETTINGS_PATH};
+use bevy_examples::state_scoped::StateScopedPlugin;
+use bevy_examples::state_stack::{
+    StateChange, StateHistory, StateOperation, StateStackAppExt, StateStackPlugin,
+};
+use bevy_examples::tracking::TrackingPlugin;
+use bevy_examples::ui::diagnostics::DiagnosticsOverlayPlugin;
+use bevy_examples::ui::focus::FocusPlugin;
+use bevy_examples::ui::layout::{LayoutPlugin, ScreenLayout};
+use bevy_examples::ui::splash::{Slide, SplashPlugin};
+use bevy_examples::ui::transition::{TransitionEffect, TransitionPlugin};
+use bevy_examples::ui::widgets::WidgetsPlugin;
+use bevy_examples::ui::DEFAULT_FONT;
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
 enum GameState {
+    Loading,
     Splash,
     Menu,
-    // Game,
+    Settings,
+    Game,
 }
 
 fn main() {
+    // ウィンドウを作る前に設定を読み込む
+    let settings = Settings::load(SETTINGS_PATH);
+
     App::new()
         .insert_resource(WgpuSettings {
             backends: Some(Backends::VULKAN),
             ..Default::default()
         })
+        .insert_resource(settings.window_descriptor(WindowDescriptor::default()))
+        .insert_resource(settings)
+        // assets/ui/*.screen.ron の変更を反映する
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..Default::default()
+        })
         .add_plugins(DefaultPlugins)
+        .add_plugin(SettingsPlugin::new(SETTINGS_PATH))
+        .add_plugin(LocalizationPlugin::new(["ja", "en"]).with_fallback("en"))
+        .add_system(sync_locale)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(LogDiagnosticsPlugin::default())
         .add_startup_system(setup)
-        .add_state(GameState::Splash)
-        .add_plugin(benchmark::BenchMarkPlugin)
-        .add_plugin(splash::SplashPlugin)
+        .add_state(GameState::Loading)
+        .add_plugin(StateStackPlugin::new(GameState::Loading))
+        // 画面ごとに作ったエンティティは画面を抜ける時に削除する
+        .add_plugin(
+            StateScopedPlugin::new(GameState::Loading)
+                .with_auto_scope(GameState::Menu)
+                .with_auto_scope(GameState::Settings)
+                .with_auto_scope(GameState::Game),
+        )
+        .add_state_guard(settings_guard)
+        .add_system(log_state_history)
+        .add_plugin(LoadingPlugin::new(GameState::Loading, GameState::Splash))
+        .add_plugin(TransitionPlugin::new(GameState::Loading).with_effect(TransitionEffect::Fade))
+        .add_plugin(TrackingPlugin::default())
+        .add_plugin(FocusPlugin)
+        .add_plugin(WidgetsPlugin)
+        .add_plugin(LayoutPlugin)
+        // F3 で診断情報を表示する
+        .add_plugin(GameDiagnosticsPlugin)
+        .add_plugin(DiagnosticsOverlayPlugin::default())
+        .add_plugin(SplashPlugin::new(
+            GameState::Splash,
+            GameState::Menu,
+            vec![
+                Slide::text("splash.presents", DEFAULT_FONT, 2.0),
+                Slide::image("textures/うんちハニワ.png", 2.5),
+            ],
+        ))
         .add_plugin(menu::MenuPlugin)
+        .add_plugin(settings_menu::SettingsMenuPlugin)
+        .add_plugin(PausePlugin::manual())
+        .add_plugin(game::GamePlugin)
         .run();
 }
 
-fn setup(mut commands: Commands) {
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<LoadingAssets>,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
     commands.spawn_bundle(UiCameraBundle::default());
+
+    // 画面を表示する前に読み込んでおく
+    for path in [
+        "ui/menu.screen.ron",
+        "ui/settings.screen.ron",
+        "ui/pause.screen.ron",
+    ] {
+        let _: Handle<ScreenLayout> = loading.load(&asset_server, path);
+    }
+    for path in ["textures/うんちハニワ.png", "textures/ship_C.png"] {
+        let _: Handle<Image> = loading.load(&asset_server, path);
+    }
+}
+
+///
+/// 設定の言語を表示に反映する
+///
+fn sync_locale(settings: Res<Settings>, mut localization: ResMut<Localization>) {
+    if settings.is_changed() && localization.locale() != settings.language.code() {
+        localization.set_locale(settings.language.code());
+    }
+}
+
+///
+/// ゲーム画面の上に設定画面を開けるのは一時停止中だけ
+///
+fn settings_guard(change: &StateChange<GameState>, world: &World) -> Result<(), String> {
+    let paused = world
+        .get_resource::<Pause>()
+        .is_some_and(|pause| pause.is_paused());
+    match (&change.operation, &change.from) {
+        (StateOperation::Push(GameState::Settings), GameState::Game) if !paused => {
+            Err("the game must be paused to open settings".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+///
+/// F4キーで状態の切り替えの履歴をログに出す
+///
+fn log_state_history(keyboard_input: Res<Input<KeyCode>>, history: Res<StateHistory<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        for entry in history.iter() {
+            info!("{}", entry);
+        }
+    }
 }
 
 ///
@@ -46,276 +161,503 @@ fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands
 }
 
 ///
-/// スプラッシュ画面
+/// メニュー画面
 ///
-mod splash {
+mod menu {
+    use bevy::app::AppExit;
     use bevy::prelude::*;
+    use bevy_examples::state_stack::StateStack;
+    use bevy_examples::ui::focus::{ButtonActivated, FocusSystem};
+    use bevy_examples::ui::layout::{spawn_layout, LayoutAction};
+    use bevy_examples::ui::transition::Transition;
 
     use super::{despawn_screen, GameState};
 
-    pub struct SplashPlugin;
+    pub struct MenuPlugin;
 
-    impl Plugin for SplashPlugin {
+    impl Plugin for MenuPlugin {
         fn build(&self, app: &mut App) {
-            app.add_system_set(SystemSet::on_enter(GameState::Splash).with_system(splash_setup))
-                .add_system_set(SystemSet::on_update(GameState::Splash).with_system(countdown))
+            app.add_system_set(SystemSet::on_enter(GameState::Menu).with_system(menu_setup))
                 .add_system_set(
-                    SystemSet::on_exit(GameState::Splash)
-                        .with_system(despawn_screen::<OnSplashScreen>),
-                );
+                    SystemSet::on_update(GameState::Menu)
+                        .with_system(bevy::input::system::exit_on_esc_system)
+                        .with_system(menu_action.after(FocusSystem::Navigate)),
+                )
+                // 設定画面を重ねている間は隠し、戻った時に作り直す
+                .add_system_set(
+                    SystemSet::on_pause(GameState::Menu).with_system(despawn_screen::<MenuScreen>),
+                )
+                .add_system_set(SystemSet::on_resume(GameState::Menu).with_system(menu_setup));
         }
     }
 
     #[derive(Component)]
-    struct OnSplashScreen;
+    struct MenuScreen;
+
+    ///
+    /// クリック（または Enter / Aボタン）されたボタンのアクションを実行する
+    ///
+    fn menu_action(
+        mut activated: EventReader<ButtonActivated>,
+        action_query: Query<&LayoutAction>,
+        mut app_exit_events: EventWriter<AppExit>,
+        mut transition: ResMut<Transition<GameState>>,
+        mut stack: ResMut<StateStack<GameState>>,
+    ) {
+        for ButtonActivated(entity) in activated.iter() {
+            if let Ok(action) = action_query.get(*entity) {
+                match action.0.as_str() {
+                    "play" => {
+                        transition.request(GameState::Game);
+                    }
+                    "settings" => stack.push(GameState::Settings),
+                    "quit" => app_exit_events.send(AppExit),
+                    other => warn!("unknown menu action {:?}", other),
+                }
+            }
+        }
+    }
 
-    struct SplashTimer(Timer);
+    fn menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+        spawn_layout(&mut commands, asset_server.load("ui/menu.screen.ron")).insert(MenuScreen);
+    }
 
-    fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let icon: Handle<Image> = asset_server.load("textures/うんちハニワ.png");
+    #[cfg(test)]
+    mod tests {
+        use bevy::ecs::event::{Events, ManualEventReader};
+        use bevy::input::InputPlugin;
+        use bevy_examples::state_stack::StateStackPlugin;
+        use bevy_examples::ui::focus::FocusPlugin;
+        use bevy_examples::ui::transition::{TransitionEffect, TransitionPlugin};
+
+        use super::*;
+
+        /// 画面の読み込みを除いたメニューだけのアプリ
+        fn app() -> App {
+            let mut app = App::new();
+            app.add_plugins(MinimalPlugins)
+                .add_plugin(InputPlugin)
+                .add_state(GameState::Menu)
+                .add_plugin(StateStackPlugin::new(GameState::Menu))
+                .add_plugin(
+                    TransitionPlugin::new(GameState::Menu).with_effect(TransitionEffect::Cut),
+                )
+                .add_plugin(FocusPlugin)
+                .add_system_set(
+                    SystemSet::on_update(GameState::Menu)
+                        .with_system(menu_action.after(FocusSystem::Navigate)),
+                );
+            app
+        }
 
-        commands
-            .spawn_bundle(ImageBundle {
-                style: Style {
-                    margin: Rect::all(Val::Auto),
-                    size: Size::new(Val::Px(256.0), Val::Auto),
-                    ..Default::default()
-                },
-                image: UiImage(icon),
-                color: UiColor(Color::rgba(1.0, 1.0, 1.0, 1.0)),
-                ..Default::default()
-            })
-            .insert(OnSplashScreen);
+        fn click(app: &mut App, action: &str) {
+            app.world
+                .spawn()
+                .insert(Interaction::Clicked)
+                .insert(LayoutAction(action.to_string()));
+        }
 
-        // Splashを表示する時間を指定
-        commands.insert_resource(SplashTimer(Timer::from_seconds(2.0, false)));
-    }
+        #[test]
+        fn play_switches_to_game() {
+            let mut app = app();
+            click(&mut app, "play");
+            for _ in 0..4 {
+                app.update();
+            }
+            assert_eq!(
+                app.world.resource::<State<GameState>>().current(),
+                &GameState::Game
+            );
+        }
 
-    fn countdown(
-        mut game_state: ResMut<State<GameState>>,
-        time: Res<Time>,
-        mut timer: ResMut<SplashTimer>,
-    ) {
-        if timer.0.tick(time.delta()).finished() {
-            game_state.set(GameState::Menu).unwrap();
+        #[test]
+        fn quit_sends_app_exit() {
+            let mut app = app();
+            let mut reader = ManualEventReader::<AppExit>::default();
+            app.update();
+            assert_eq!(
+                reader.iter(app.world.resource::<Events<AppExit>>()).count(),
+                0
+            );
+
+            click(&mut app, "quit");
+            app.update();
+            assert_eq!(
+                reader.iter(app.world.resource::<Events<AppExit>>()).count(),
+                1
+            );
+            assert_eq!(
+                app.world.resource::<State<GameState>>().current(),
+                &GameState::Menu
+            );
         }
     }
 }
 
 ///
-/// メニュー画面
+/// 設定画面
 ///
-mod menu {
+mod settings_menu {
     use bevy::prelude::*;
+    use bevy_examples::settings::{Language, Settings, WindowModeSetting};
+    use bevy_examples::state_stack::StateStack;
+    use bevy_examples::ui::focus::{ButtonActivated, FocusSystem};
+    use bevy_examples::ui::layout::{spawn_layout, LayoutAction, LayoutId};
+    use bevy_examples::ui::transition::Transition;
+    use bevy_examples::ui::widgets::{
+        Dropdown, Slider, TextInput, Toggle, ValueChanged, WidgetSystem,
+    };
 
     use super::GameState;
 
-    pub struct MenuPlugin;
+    pub struct SettingsMenuPlugin;
 
-    impl Plugin for MenuPlugin {
+    impl Plugin for SettingsMenuPlugin {
         fn build(&self, app: &mut App) {
-            app.add_system_set(SystemSet::on_enter(GameState::Menu).with_system(menu_setup))
-                .add_system_set(
-                    SystemSet::on_update(GameState::Menu)
-                        .with_system(bevy::input::system::exit_on_esc_system)
-                        .with_system(button_system),
-                );
+            app.add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(settings_menu_setup),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(init_widgets)
+                    .with_system(apply_widgets.after(WidgetSystem))
+                    .with_system(back_to_menu.after(FocusSystem::Navigate)),
+            );
         }
     }
 
-    #[derive(Component)]
-    struct MenuScreen;
-
-    #[derive(Component)]
-    struct SelectedOption;
-
-    const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-    const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-    const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
-    const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
-    const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
-
-    #[derive(Component)]
-    enum MenuButtonAction {
-        Play,
-        Quit,
+    fn settings_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+        spawn_layout(&mut commands, asset_server.load("ui/settings.screen.ron"));
     }
 
-    fn button_system(
-        mut interaction_query: Query<
-            (&Interaction, &mut UiColor, Option<&SelectedOption>),
-            (Changed<Interaction>, With<Button>),
+    ///
+    /// レイアウトから作られたウィジェットに現在の設定を表示する
+    ///
+    /// ホットリロードで作り直された時も設定の値に戻ります。
+    ///
+    #[allow(clippy::type_complexity)]
+    fn init_widgets(
+        settings: Res<Settings>,
+        mut widgets: Query<
+            (
+                &LayoutId,
+                Option<&mut Slider>,
+                Option<&mut Toggle>,
+                Option<&mut Dropdown>,
+                Option<&mut TextInput>,
+            ),
+            Added<LayoutId>,
         >,
     ) {
-        for (i, (interaction, mut color, selected)) in interaction_query.iter_mut().enumerate() {
-            *color = match (*interaction, selected) {
-                (Interaction::Clicked, _) => {
-                    println!("(Clicked, _) {}", i);
-                    PRESSED_BUTTON.into()
-                }
-                (Interaction::Hovered, Some(_)) => {
-                    println!("(Hovered, Some(_)) {}", i);
-                    HOVERED_PRESSED_BUTTON.into()
+        for (id, slider, toggle, dropdown, text_input) in widgets.iter_mut() {
+            if let Some(mut slider) = slider {
+                slider.value = match id.0.as_str() {
+                    "master_volume" => settings.master_volume,
+                    "sfx_volume" => settings.sfx_volume,
+                    "music_volume" => settings.music_volume,
+                    "ui_scale" => settings.ui_scale,
+                    _ => continue,
+                };
+            } else if let Some(mut toggle) = toggle {
+                if id.0 == "vsync" {
+                    toggle.on = settings.vsync;
                 }
-                (Interaction::Hovered, None) => {
-                    println!("(Hovered, None) {}", i);
-                    HOVERED_BUTTON.into()
+            } else if let Some(mut dropdown) = dropdown {
+                let selected = match id.0.as_str() {
+                    "window_mode" => WindowModeSetting::ALL
+                        .iter()
+                        .position(|mode| *mode == settings.window_mode),
+                    "language" => Language::ALL
+                        .iter()
+                        .position(|language| *language == settings.language),
+                    _ => None,
+                };
+                if let Some(selected) = selected {
+                    dropdown.selected = selected.min(dropdown.options.len().saturating_sub(1));
                 }
-                (Interaction::None, Some(_)) => {
-                    println!("(None, Some(_)) {}", i);
-                    PRESSED_BUTTON.into()
-                }
-                (Interaction::None, None) => {
-                    println!("(None, None) {}", i);
-                    NORMAL_BUTTON.into()
+            } else if let Some(mut text_input) = text_input {
+                if id.0 == "player_name" {
+                    text_input.set_value(settings.player_name.clone());
                 }
             }
         }
     }
 
-    fn menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        let font = asset_server.load("fonts/NotoSansJP-Medium.otf");
+    ///
+    /// ウィジェットの値を設定に反映する
+    ///
+    fn apply_widgets(
+        mut settings: ResMut<Settings>,
+        mut sliders: EventReader<ValueChanged<f32>>,
+        mut toggles: EventReader<ValueChanged<bool>>,
+        mut dropdowns: EventReader<ValueChanged<usize>>,
+        mut text_inputs: EventReader<ValueChanged<String>>,
+        ids: Query<&LayoutId>,
+    ) {
+        let id_of = |entity: &Entity| ids.get(*entity).map_or("", |id| id.0.as_str());
+
+        for ValueChanged { entity, value } in sliders.iter() {
+            match id_of(entity) {
+                // 音量は保存するだけ（この例では音を鳴らさない）
+                "master_volume" => settings.master_volume = *value,
+                "sfx_volume" => settings.sfx_volume = *value,
+                "music_volume" => settings.music_volume = *value,
+                "ui_scale" => settings.ui_scale = *value,
+                _ => {}
+            }
+        }
 
-        let button_style = Style {
-            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
-            margin: Rect::all(Val::Px(20.0)),
-            justify_content: JustifyContent::Center,
-            align_items: AlignItems::Center,
-            ..Default::default()
-        };
+        for ValueChanged { entity, value } in toggles.iter() {
+            if id_of(entity) == "vsync" {
+                settings.vsync = *value;
+            }
+        }
 
-        let button_text_style = TextStyle {
-            font: font.clone(),
-            font_size: 40.0,
-            color: TEXT_COLOR,
-        };
+        for ValueChanged { entity, value } in dropdowns.iter() {
+            match id_of(entity) {
+                "window_mode" => {
+                    if let Some(mode) = WindowModeSetting::ALL.get(*value) {
+                        settings.window_mode = *mode;
+                    }
+                }
+                "language" => {
+                    if let Some(language) = Language::ALL.get(*value) {
+                        settings.language = *language;
+                    }
+                }
+                _ => {}
+            }
+        }
 
-        commands
-            .spawn_bundle(NodeBundle {
-                style: Style {
-                    margin: Rect::all(Val::Auto),
-                    flex_direction: FlexDirection::ColumnReverse,
-                    align_items: AlignItems::Center,
-                    ..Default::default()
-                },
-                color: Color::CRIMSON.into(),
-                ..Default::default()
-            })
-            .insert(MenuScreen)
-            .with_children(|parent| {
-                parent.spawn_bundle(TextBundle {
-                    style: Style {
-                        margin: Rect::all(Val::Px(50.0)),
-                        ..Default::default()
-                    },
-                    text: Text::with_section(
-                        "メニュ画面",
-                        TextStyle {
-                            font: font.clone(),
-                            font_size: 80.0,
-                            color: TEXT_COLOR.into(),
-                        },
-                        Default::default(),
-                    ),
-                    ..Default::default()
-                });
+        for ValueChanged { entity, value } in text_inputs.iter() {
+            if id_of(entity) == "player_name" {
+                settings.player_name = value.clone();
+            }
+        }
+    }
 
-                parent
-                    .spawn_bundle(ButtonBundle {
-                        style: button_style.clone(),
-                        color: NORMAL_BUTTON.into(),
-                        ..Default::default()
-                    })
-                    .insert(MenuButtonAction::Play)
-                    .with_children(|parent| {
-                        parent.spawn_bundle(TextBundle {
-                            text: Text::with_section(
-                                "New Game",
-                                button_text_style.clone(),
-                                Default::default(),
-                            ),
-                            ..Default::default()
-                        });
-                    });
-
-                parent
-                    .spawn_bundle(ButtonBundle {
-                        style: button_style,
-                        color: NORMAL_BUTTON.into(),
-                        ..Default::default()
-                    })
-                    .insert(MenuButtonAction::Quit)
-                    .with_children(|parent| {
-                        parent.spawn_bundle(TextBundle {
-                            text: Text::with_section("Quit", button_text_style, Default::default()),
-                            ..Default::default()
-                        });
-                    });
-            });
+    ///
+    /// 戻るボタンか Escキーで前の画面に戻る
+    ///
+    fn back_to_menu(
+        mut activated: EventReader<ButtonActivated>,
+        actions: Query<&LayoutAction>,
+        mut keyboard_input: ResMut<Input<KeyCode>>,
+        game_state: Res<State<GameState>>,
+        mut stack: ResMut<StateStack<GameState>>,
+        mut transition: ResMut<Transition<GameState>>,
+    ) {
+        let back_pressed = activated.iter().any(|ButtonActivated(entity)| {
+            matches!(actions.get(*entity), Ok(action) if action.0 == "back")
+        });
+
+        if back_pressed || keyboard_input.just_pressed(KeyCode::Escape) {
+            // メニュー画面の exit_on_esc_system で終了しないように入力を消費する
+            keyboard_input.reset(KeyCode::Escape);
+            if game_state.inactives().is_empty() {
+                transition.request(GameState::Menu);
+            } else {
+                // 重ねる前の画面（メニュー画面かゲーム画面）に戻る
+                stack.pop();
+            }
+        }
     }
 }
 
 ///
-/// ### BenchMark
-///
-/// Usage
-///
-/// ```
-/// App()
-///     .add_plugins(DefaultPlugins)
-///     .add_plugin(benchmark::BenchMarkPlugin)
-///     .run();
+/// ゲーム画面（追跡ミサイル）
 ///
-///
-mod benchmark {
-    use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+mod game {
     use bevy::prelude::*;
+    use bevy_examples::localization::LocaleArgs;
+    use bevy_examples::look_at::LookTarget;
+    use bevy_examples::pause::Pause;
+    use bevy_examples::run_criteria::gameplay_timestep_in;
+    use bevy_examples::settings::Settings;
+    use bevy_examples::state_stack::StateStack;
+    use bevy_examples::tracking::TrackingMissile;
+    use bevy_examples::ui::focus::{ButtonActivated, FocusSystem};
+    use bevy_examples::ui::layout::{spawn_layout, LayoutAction};
+    use bevy_examples::ui::transition::Transition;
+    use bevy_examples::ui::widgets::{label, Widget, WidgetTheme};
+    use bevy_examples::TIME_STEP;
 
-    pub struct BenchMarkPlugin;
+    use super::{despawn_screen, GameState};
 
-    impl Plugin for BenchMarkPlugin {
+    const PLAYER_SPEED: f32 = 120.0;
+
+    pub struct GamePlugin;
+
+    impl Plugin for GamePlugin {
         fn build(&self, app: &mut App) {
-            app.add_startup_system(bench_setup)
-                .add_system(counter_system);
+            app.add_system_set(SystemSet::on_enter(GameState::Game).with_system(game_setup))
+                .add_system_set(
+                    SystemSet::on_update(GameState::Game)
+                        .with_system(toggle_pause)
+                        .with_system(pause_overlay.after(toggle_pause))
+                        .with_system(pause_action.after(FocusSystem::Navigate)),
+                )
+                .add_system_set(
+                    SystemSet::new()
+                        .with_run_criteria(gameplay_timestep_in(GameState::Game))
+                        .with_system(player_movement),
+                )
+                // 一時停止メニューから設定画面を開いている間は隠す
+                .add_system_set(
+                    SystemSet::on_pause(GameState::Game).with_system(despawn_screen::<PauseScreen>),
+                )
+                .add_system_set(SystemSet::on_exit(GameState::Game).with_system(resume));
         }
     }
 
     #[derive(Component)]
-    struct StatsText;
+    struct PauseScreen;
 
-    fn bench_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-        commands
-            .spawn_bundle(TextBundle {
-                text: Text::with_section(
-                    "Average FPS",
-                    TextStyle {
-                        font: asset_server.load("fonts/NotoSansJP-Medium.otf"),
-                        font_size: 40.0,
-                        color: Color::WHITE,
+    #[derive(Component)]
+    struct Player;
+
+    fn game_setup(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        theme: Res<WidgetTheme>,
+        settings: Res<Settings>,
+    ) {
+        let player = commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(250.0, -250.0, 0.0),
+                    scale: Vec3::new(0.5, 0.5, 1.0),
+                    ..Default::default()
+                },
+                texture: asset_server.load("textures/ship_C.png"),
+                ..Default::default()
+            })
+            .insert(Player)
+            .id();
+
+        let missiles = [Vec3::new(-200.0, 0.0, 0.0), Vec3::new(200.0, 200.0, 0.0)];
+        for translation in missiles {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    transform: Transform {
+                        translation,
+                        scale: Vec3::new(0.0625, 0.0625, 1.0),
+                        ..Default::default()
                     },
-                    Default::default(),
-                ),
+                    texture: asset_server.load("textures/うんちハニワ.png"),
+                    ..Default::default()
+                })
+                .insert(TrackingMissile::new(LookTarget::Entity(player)));
+        }
+
+        // 画面左下に状況と操作方法を表示する
+        commands
+            .spawn_bundle(NodeBundle {
                 style: Style {
                     position_type: PositionType::Absolute,
                     position: Rect {
-                        top: Val::Px(5.0),
-                        left: Val::Px(5.0),
+                        left: Val::Px(10.0),
+                        bottom: Val::Px(10.0),
                         ..Default::default()
                     },
+                    flex_direction: FlexDirection::ColumnReverse,
                     ..Default::default()
                 },
+                color: Color::NONE.into(),
                 ..Default::default()
             })
-            .insert(StatsText);
+            .with_children(|parent| {
+                label("game.status").spawn(parent, &theme).insert(
+                    LocaleArgs::default()
+                        .with("name", &settings.player_name)
+                        .with("count", missiles.len()),
+                );
+                label("game.hint").spawn(parent, &theme);
+            });
     }
 
-    fn counter_system(diagnostics: Res<Diagnostics>, mut query: Query<&mut Text, With<StatsText>>) {
-        let mut text = query.single_mut();
+    fn player_movement(
+        input_keyboard: Res<Input<KeyCode>>,
+        mut query: Query<&mut Transform, With<Player>>,
+    ) {
+        let x_dir = if input_keyboard.pressed(KeyCode::A) {
+            -1.
+        } else if input_keyboard.pressed(KeyCode::D) {
+            1.
+        } else {
+            0.
+        };
+
+        let y_dir = if input_keyboard.pressed(KeyCode::W) {
+            1.
+        } else if input_keyboard.pressed(KeyCode::S) {
+            -1.
+        } else {
+            0.
+        };
+
+        if let Ok(mut tf) = query.get_single_mut() {
+            tf.translation.x += x_dir * PLAYER_SPEED * TIME_STEP;
+            tf.translation.y += y_dir * PLAYER_SPEED * TIME_STEP;
+        }
+    }
+
+    ///
+    /// Escキーで一時停止を切り替える
+    ///
+    fn toggle_pause(mut keyboard_input: ResMut<Input<KeyCode>>, mut pause: ResMut<Pause>) {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            // メニュー画面の exit_on_esc_system で終了しないように入力を消費する
+            keyboard_input.reset(KeyCode::Escape);
+            pause.toggle();
+        }
+    }
 
-        if let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) {
-            if let Some(average) = fps.average() {
-                text.sections[0].value = format!("{:.2}", average);
+    ///
+    /// 一時停止中は一時停止メニューを表示する
+    ///
+    fn pause_overlay(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        pause: Res<Pause>,
+        screens: Query<Entity, With<PauseScreen>>,
+    ) {
+        if pause.is_paused() && screens.is_empty() {
+            spawn_layout(&mut commands, asset_server.load("ui/pause.screen.ron"))
+                .insert(PauseScreen);
+        } else if !pause.is_paused() {
+            for entity in screens.iter() {
+                commands.entity(entity).despawn_recursive();
             }
         }
     }
+
+    ///
+    /// 一時停止メニューのボタンのアクションを実行する
+    ///
+    fn pause_action(
+        mut activated: EventReader<ButtonActivated>,
+        actions: Query<&LayoutAction>,
+        mut pause: ResMut<Pause>,
+        mut stack: ResMut<StateStack<GameState>>,
+        mut transition: ResMut<Transition<GameState>>,
+    ) {
+        for ButtonActivated(entity) in activated.iter() {
+            if let Ok(action) = actions.get(*entity) {
+                match action.0.as_str() {
+                    "resume" => pause.set(false),
+                    // ゲーム画面を残したまま設定画面を開く
+                    "settings" => stack.push(GameState::Settings),
+                    "menu" => {
+                        transition.request(GameState::Menu);
+                    }
+                    other => warn!("unknown pause action {:?}", other),
+                }
+            }
+        }
+    }
+
+    fn resume(mut pause: ResMut<Pause>) {
+        pause.set(false);
+    }
 }
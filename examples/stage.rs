@@ -5,23 +5,119 @@
 //
 // 現在: Failed to acquire next swap chain texture!のバグが発生している。
 //
+// 各システムの実行順と時間は SystemTracePlugin で記録します（--features trace が必要）。
+// スケジュールの構造は起動時に stage.dot に出力します。
+// F5 を押すと直前のフレームを traces/frame-*.json に保存します（chrome://tracing で開けます）。
+//
+// --headless を付けるとウィンドウ無しで数フレーム動かし、最初の2フレームの実行順を表示して
+// traces/stage-frame-*.json に保存します。
+// cargo run --example stage --features trace -- --headless
+//
 
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    log::LogPlugin,
     prelude::*,
     render::settings::{Backends, WgpuSettings},
 };
+use bevy_examples::system_trace::{
+    save_schedule_dot, SystemTrace, SystemTracePlugin, TraceFrame, TraceKind,
+};
+
+/// スケジュールを出力するファイル
+const DOT_PATH: &str = "stage.dot";
+
+/// ウィンドウ無しで動かすフレーム数
+const HEADLESS_FRAMES: u64 = 3;
 
 fn main() {
-    App::new()
-        .insert_resource(WgpuSettings {
-            backends: Some(Backends::VULKAN),
-            ..Default::default()
-        })
-        .add_plugins(DefaultPlugins)
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(LogDiagnosticsPlugin::default())
-        .add_startup_system(startup_system)
+    if std::env::args().any(|arg| arg == "--headless") {
+        std::process::exit(run_headless());
+    }
+
+    let mut app = App::new();
+    app.insert_resource(WgpuSettings {
+        backends: Some(Backends::VULKAN),
+        ..Default::default()
+    })
+    // ログは SystemTracePlugin が出力する
+    .add_plugin(SystemTracePlugin::default())
+    .add_plugins_with(DefaultPlugins, |plugins| plugins.disable::<LogPlugin>())
+    .add_plugin(FrameTimeDiagnosticsPlugin::default())
+    .add_plugin(LogDiagnosticsPlugin::default());
+    add_stages(&mut app);
+    if let Err(e) = save_schedule_dot(&app.schedule, DOT_PATH) {
+        warn!("{}", e);
+    }
+    app.run();
+}
+
+///
+/// ウィンドウ無しで動かし、記録した実行順を表示する
+///
+fn run_headless() -> i32 {
+    let mut app = App::new();
+    app.add_plugin(SystemTracePlugin::default().with_key(None))
+        .add_plugins(MinimalPlugins);
+    add_stages(&mut app);
+    if let Err(e) = save_schedule_dot(&app.schedule, DOT_PATH) {
+        eprintln!("{}", e);
+        return 1;
+    }
+    println!("saved the schedule to {}", DOT_PATH);
+
+    for _ in 0..HEADLESS_FRAMES {
+        app.update();
+    }
+
+    let frames = app.world.resource::<SystemTrace>().frames();
+    if frames.is_empty() {
+        eprintln!("no frame has been traced; run with `--features trace`");
+        return 1;
+    }
+    // 最初のフレームは起動時のステージを含む
+    for frame in frames.iter().take(2) {
+        print_frame(frame);
+        let path = format!("traces/stage-frame-{}.json", frame.index);
+        let saved = std::fs::create_dir_all("traces")
+            .map_err(anyhow::Error::from)
+            .and_then(|_| frame.save_chrome_trace(&path));
+        match saved {
+            Ok(()) => println!("saved the trace to {}", path),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+///
+/// ステージとシステムを実行順に、入れ子を字下げして表示する
+///
+fn print_frame(frame: &TraceFrame) {
+    println!("frame {} ({:.3} ms)", frame.index, frame.duration / 1000.0);
+    let mut enclosing: Vec<f64> = Vec::new();
+    for event in frame
+        .events
+        .iter()
+        .filter(|event| event.kind != TraceKind::Commands)
+    {
+        while matches!(enclosing.last(), Some(&end) if end <= event.start) {
+            enclosing.pop();
+        }
+        let indent = "  ".repeat(enclosing.len() + 1);
+        match event.kind {
+            TraceKind::Stage => println!("{}[{}]", indent, event.name),
+            _ => println!("{}{} ({:.1} µs)", indent, event.name, event.duration),
+        }
+        enclosing.push(event.start + event.duration);
+    }
+}
+
+fn add_stages(app: &mut App) {
+    app.add_startup_system(startup_system)
         .add_startup_stage(
             "startup_stage",
             SystemStage::single(normal_startup_stage_system),
@@ -39,38 +135,28 @@ fn main() {
         .add_stage("stage", SystemStage::single(normal_stage_system))
         .add_stage_before("stage", "before", SystemStage::single(before_stage_system))
         .add_stage_after("stage", "after", SystemStage::single(after_stage_system))
-        .add_system(normal_system)
-        .run();
+        // beforeとafterはLabelでバインドされる
+        .add_system(normal_system.label("normal"))
+        .add_system(before_normal_system.before("normal"))
+        .add_system(after_normal_system.after("normal"));
 }
 
-fn startup_system() {
-    println!("startup system run");
-}
+fn startup_system() {}
 
-fn normal_system() {
-    println!("normal system run")
-}
+fn normal_system() {}
 
-fn normal_startup_stage_system() {
-    println!("normal startup stage system run");
-}
+fn before_normal_system() {}
 
-fn before_startup_stage_system() {
-    println!("before startup statge system run");
-}
+fn after_normal_system() {}
 
-fn after_startup_stage_system() {
-    println!("after startup statge system run");
-}
+fn normal_startup_stage_system() {}
 
-fn normal_stage_system() {
-    println!("normal stage system run");
-}
+fn before_startup_stage_system() {}
 
-fn before_stage_system() {
-    println!("before statge system run");
-}
+fn after_startup_stage_system() {}
 
-fn after_stage_system() {
-    println!("after statge system run");
-}
+fn normal_stage_system() {}
+
+fn before_stage_system() {}
+
+fn after_stage_system() {}
@@ -3,15 +3,25 @@
 //
 // Stateによる実行管理
 //
-// 一部バグが発生しているので正常な動作が行われていないところがあります。
+// --headless を付けるとウィンドウ無しで数秒動かし、b_system が状態 B の時だけ
+// 実行されるかと、`StateScoped` のエンティティが状態を抜けた時に削除されるかを
+// 確認します（失敗すると終了コード 1 で終わります）。
+// cargo run --example state -- --headless
 //
 
+use std::time::{Duration, Instant};
+
 use bevy::{
-    core::FixedTimestep,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    ecs::event::Events,
     prelude::*,
     render::settings::{Backends, WgpuSettings},
 };
+use bevy_examples::run_criteria::{and_timestep, in_state};
+use bevy_examples::state_scoped::{OrphanedEntity, StateScoped, StateScopedPlugin};
+
+/// ウィンドウ無しで動かす時間
+const HEADLESS_DURATION: Duration = Duration::from_millis(2500);
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 enum GameState {
@@ -20,40 +30,174 @@ enum GameState {
 }
 
 fn main() {
-    App::new()
-        .insert_resource(WgpuSettings {
-            backends: Some(Backends::VULKAN),
-            ..Default::default()
-        })
-        .add_state(GameState::A)
-        .add_plugins(DefaultPlugins)
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(LogDiagnosticsPlugin::default())
-        // beforeとafterはLabelでバインドされる
-        .add_system_set(
-            SystemSet::on_enter(GameState::A)
-                .label("A")
-                .with_system(a_system),
-        )
-        .add_system_set(
-            SystemSet::on_enter(GameState::A)
-                .before("A")
-                .with_system(a_before_system),
-        )
-        .add_system_set(
-            SystemSet::on_enter(GameState::A)
-                .after("A")
-                .with_system(a_after_system),
-        )
-        .add_system_set(
-            // BUG: with_run_criteriaを付けるとStateの状態に関係無くタイムステップごとに動作する
-            SystemSet::on_exit(GameState::B)
-                .with_run_criteria(FixedTimestep::step(1.0))
-                .with_system(b_system),
-        )
-        .run();
+    if std::env::args().any(|arg| arg == "--headless") {
+        std::process::exit(run_headless());
+    }
+
+    let mut app = App::new();
+    app.insert_resource(WgpuSettings {
+        backends: Some(Backends::VULKAN),
+        ..Default::default()
+    })
+    .add_state(GameState::A)
+    .add_plugins(DefaultPlugins)
+    .add_plugin(FrameTimeDiagnosticsPlugin::default())
+    .add_plugin(LogDiagnosticsPlugin::default());
+    add_a_systems(&mut app);
+    add_b_systems(&mut app);
+    app.run();
+}
+
+fn run_headless() -> i32 {
+    let b_system_ok = check_b_system();
+    let state_scoped_ok = check_state_scoped();
+    if b_system_ok && state_scoped_ok {
+        0
+    } else {
+        1
+    }
+}
+
+///
+/// ウィンドウ無しで動かし、b_system が状態 B の時だけ実行されるかを確認する
+///
+/// 状態 A のまま留まるように A の遷移は追加せず、途中で B に切り替えます。
+///
+fn check_b_system() -> bool {
+    let mut app = App::new();
+    app.add_state(GameState::A).add_plugins(MinimalPlugins);
+    add_b_systems(&mut app);
+
+    let run_for = |app: &mut App, duration: Duration| {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            app.update();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    };
+
+    // 状態 A の間は実行されない
+    run_for(&mut app, HEADLESS_DURATION);
+    let runs_in_a = app.world.resource::<BRuns>().0.len();
+    println!("b_system ran {} times in state A", runs_in_a);
+
+    // 状態 B になると1秒後に実行され、A に戻す
+    app.world
+        .resource_mut::<State<GameState>>()
+        .set(GameState::B)
+        .unwrap();
+    run_for(&mut app, HEADLESS_DURATION);
+    let runs = &app.world.resource::<BRuns>().0;
+    println!("b_system ran in {:?}", runs);
+
+    let ok = runs_in_a == 0 && runs.as_slice() == [GameState::B];
+    if !ok {
+        eprintln!("b_system must run only in state B");
+    }
+    ok
+}
+
+///
+/// 状態を重ねたり切り替えたりして、`StateScoped` のエンティティが
+/// 状態がスタックから無くなった時だけ子ごと削除されるかを確認する
+///
+fn check_state_scoped() -> bool {
+    let mut app = App::new();
+    app.add_state(GameState::A)
+        .add_plugins(MinimalPlugins)
+        .add_plugin(StateScopedPlugin::new(GameState::A));
+    app.update();
+
+    let change_state = |app: &mut App, change: &dyn Fn(&mut State<GameState>)| {
+        change(&mut app.world.resource_mut::<State<GameState>>());
+        app.update();
+    };
+    let alive = |app: &App, entity: Entity| app.world.get_entity(entity).is_some();
+    let mut reader = app
+        .world
+        .resource::<Events<OrphanedEntity<GameState>>>()
+        .get_reader();
+    let mut orphans = |app: &App| {
+        let events = app.world.resource::<Events<OrphanedEntity<GameState>>>();
+        reader
+            .iter(events)
+            .map(|orphan| (orphan.entity, orphan.state.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    // 状態 A で作る
+    let a = app.world.spawn().insert(StateScoped(GameState::A)).id();
+    let a_child = app.world.spawn().id();
+    app.world.entity_mut(a).push_children(&[a_child]);
+    let a_orphan = app.world.spawn().id();
+    app.update();
+
+    // B を重ねている間は A のエンティティは残る
+    change_state(&mut app, &|state| state.push(GameState::B).unwrap());
+    let b = app.world.spawn().insert(StateScoped(GameState::B)).id();
+    let b_orphan = app.world.spawn().id();
+    app.update();
+    let pushed = alive(&app, a) && alive(&app, a_child) && alive(&app, b);
+
+    // B を外すと B のエンティティだけ削除され、印の無いものは警告される
+    change_state(&mut app, &|state| state.pop().unwrap());
+    let popped = alive(&app, a) && !alive(&app, b) && alive(&app, b_orphan);
+    let popped_orphans = orphans(&app);
+
+    // A を抜けると A のエンティティは子ごと削除される
+    change_state(&mut app, &|state| state.set(GameState::B).unwrap());
+    let exited = !alive(&app, a) && !alive(&app, a_child) && alive(&app, a_orphan);
+    let exited_orphans = orphans(&app);
+
+    println!(
+        "state_scoped: pushed {}, popped {} {:?}, exited {} {:?}",
+        pushed, popped, popped_orphans, exited, exited_orphans
+    );
+    let ok = pushed
+        && popped
+        && exited
+        && popped_orphans == [(b_orphan, GameState::B)]
+        && exited_orphans == [(a_orphan, GameState::A)];
+    if !ok {
+        eprintln!("StateScoped entities must be despawned only when their state leaves the stack");
+    }
+    ok
+}
+
+fn add_a_systems(app: &mut App) {
+    // beforeとafterはLabelでバインドされる
+    app.add_system_set(
+        SystemSet::on_enter(GameState::A)
+            .label("A")
+            .with_system(a_system),
+    )
+    .add_system_set(
+        SystemSet::on_enter(GameState::A)
+            .before("A")
+            .with_system(a_before_system),
+    )
+    .add_system_set(
+        SystemSet::on_enter(GameState::A)
+            .after("A")
+            .with_system(a_after_system),
+    );
 }
 
+fn add_b_systems(app: &mut App) {
+    app.init_resource::<BRuns>().add_system_set(
+        // SystemSet::on_update(GameState::B).with_run_criteria(...) とすると
+        // 状態のランクライテリアが置き換えられて状態に関係無く動作するので、
+        // 状態の判定をタイムステップのランクライテリアにパイプする
+        SystemSet::new()
+            .with_run_criteria(in_state(GameState::B).chain(and_timestep(1.0)))
+            .with_system(b_system),
+    );
+}
+
+/// b_system が実行された時の状態
+#[derive(Default)]
+struct BRuns(Vec<GameState>);
+
 fn a_system() {
     println!("A run");
 }
@@ -67,7 +211,8 @@ fn a_after_system(mut state: ResMut<State<GameState>>) {
     state.set(GameState::B).unwrap();
 }
 
-fn b_system(mut state: ResMut<State<GameState>>) {
+fn b_system(mut state: ResMut<State<GameState>>, mut runs: ResMut<BRuns>) {
     println!("B run");
+    runs.0.push(state.current().clone());
     state.set(GameState::A).unwrap();
 }
@@ -0,0 +1,153 @@
+// #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+//
+// タッチ操作（仮想ジョイスティックで移動、タップで発射）
+//
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::settings::{Backends, WgpuSettings},
+};
+use bevy_examples::cursor::{CursorPlugin, MainCamera};
+use bevy_examples::look_at::{look_angle, LookAt2d, LookAtPlugin, LookTarget};
+use bevy_examples::pause::gameplay_timestep;
+use bevy_examples::touch::{spawn_joystick, TapEvent, TouchControlPlugin, VirtualJoystick};
+
+const WINDOW_HEIGHT: f32 = 800.0;
+const WINDOW_WIDTH: f32 = 600.0;
+
+const TIME_STEP: f32 = 1.0 / 60.0;
+
+const PLAYER_SPEED: f32 = 160.0;
+const BULLET_SPEED: f32 = 480.0;
+
+fn main() {
+    App::new()
+        .insert_resource(WgpuSettings {
+            backends: Some(Backends::VULKAN),
+            ..Default::default()
+        })
+        .insert_resource(WindowDescriptor {
+            title: "TouchControl".to_owned(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(CursorPlugin)
+        .add_plugin(TouchControlPlugin)
+        .add_plugin(LookAtPlugin)
+        .add_startup_system(setup)
+        .add_system(tap_to_fire)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(player_movement)
+                .with_system(bullet_movement),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Player;
+
+#[derive(Component)]
+struct Bullet;
+
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/ship_C.png"),
+            ..Default::default()
+        })
+        .insert(Player)
+        .insert(LookAt2d::new(LookTarget::Cursor));
+
+    spawn_joystick(&mut commands, 80.0, 30.0);
+}
+
+fn player_movement(
+    input_keyboard: Res<Input<KeyCode>>,
+    joystick_query: Query<&VirtualJoystick>,
+    mut query: Query<&mut Transform, With<Player>>,
+) {
+    let mut direction = Vec2::ZERO;
+
+    if input_keyboard.pressed(KeyCode::A) {
+        direction.x -= 1.;
+    }
+    if input_keyboard.pressed(KeyCode::D) {
+        direction.x += 1.;
+    }
+    if input_keyboard.pressed(KeyCode::W) {
+        direction.y += 1.;
+    }
+    if input_keyboard.pressed(KeyCode::S) {
+        direction.y -= 1.;
+    }
+
+    // ジョイスティックの入力を加算
+    for joystick in joystick_query.iter() {
+        direction += joystick.axis;
+    }
+
+    if let Ok(mut tf) = query.get_single_mut() {
+        let delta = direction.clamp_length_max(1.0) * PLAYER_SPEED * TIME_STEP;
+        tf.translation += delta.extend(0.);
+    }
+}
+
+fn tap_to_fire(
+    mut commands: Commands,
+    assert_server: Res<AssetServer>,
+    mut taps: EventReader<TapEvent>,
+    query: Query<&Transform, With<Player>>,
+) {
+    let player_tf = match query.get_single() {
+        Ok(tf) => tf,
+        Err(_) => return,
+    };
+
+    for tap in taps.iter() {
+        let angle = look_angle(player_tf.translation.truncate(), tap.world_pos, Vec2::Y);
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: player_tf.translation,
+                    rotation: Quat::from_rotation_z(angle),
+                    scale: Vec3::new(0.03, 0.03, 1.0),
+                },
+                texture: assert_server.load("textures/うんちハニワ.png"),
+                ..Default::default()
+            })
+            .insert(Bullet);
+    }
+}
+
+fn bullet_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform), With<Bullet>>,
+) {
+    for (entity, mut tf) in query.iter_mut() {
+        let movement_direction = tf.rotation * Vec3::Y;
+        tf.translation += movement_direction * BULLET_SPEED * TIME_STEP;
+
+        // 画面外に出たら削除
+        if tf.translation.x.abs() > WINDOW_WIDTH || tf.translation.y.abs() > WINDOW_HEIGHT {
+            commands.entity(entity).despawn();
+        }
+    }
+}
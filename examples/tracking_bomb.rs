@@ -3,13 +3,27 @@
 //
 // プレイヤーを追跡して、近づくと爆発する
 //
+// 引数でシナリオを指定すると、大量のボムが複数のターゲットを追います。
+// cargo run --release --example tracking_bomb -- --scenario assets/scenarios/stress.scenario.ron
+// cargo run --release --example tracking_bomb -- --seed 7 --targets 8 --bombs 3000
+//
+
+use std::sync::Mutex;
 
 use bevy::{
-    core::FixedTimestep,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
     render::settings::{Backends, WgpuSettings},
+    tasks::ComputeTaskPool,
+};
+use bevy_examples::diagnostics::{
+    diagnostic_id, DiagnosticsAppExt, ExplosionEvent, GameDiagnosticsPlugin,
 };
+use bevy_examples::look_at::LookTarget;
+use bevy_examples::pause::{gameplay_timestep, GameTime, PausePlugin};
+use bevy_examples::scenario::{Scenario, ScenarioPlugin};
+use bevy_examples::tracking::{move_forward, steer_towards, TargetSnapshot};
+use bevy_examples::ui::diagnostics::DiagnosticsOverlayPlugin;
 
 const WINDOW_HEIGHT: f32 = 800.0;
 const WINDOW_WIDTH: f32 = 1200.0;
@@ -18,32 +32,55 @@ const TIME_STEP: f32 = 1.0 / 60.0;
 
 const PLAYER_SPEED: f32 = 120.0;
 
+/// 並列に処理する時の1タスクあたりのボムの数
+const BATCH_SIZE: usize = 256;
+
 fn main() {
-    App::new()
-        .insert_resource(WgpuSettings {
-            backends: Some(Backends::VULKAN),
-            ..Default::default()
-        })
-        .insert_resource(WindowDescriptor {
-            title: "SimpleTracking".to_owned(),
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
-            // resizable: false,
-            ..Default::default()
-        })
-        .add_plugins(DefaultPlugins)
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(LogDiagnosticsPlugin::default())
-        .add_startup_system(setup)
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(player_movement)
-                .with_system(tracking_missile_movement),
-        )
-        .add_system(bomb_spawn)
-        .add_system(bom_animation)
-        .run();
+    let scenario = match Scenario::from_args(std::env::args().skip(1)) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut app = App::new();
+    if let Some(scenario) = scenario {
+        app.insert_resource(scenario);
+    }
+
+    app.insert_resource(WgpuSettings {
+        backends: Some(Backends::VULKAN),
+        ..Default::default()
+    })
+    .insert_resource(WindowDescriptor {
+        title: "SimpleTracking".to_owned(),
+        width: WINDOW_WIDTH,
+        height: WINDOW_HEIGHT,
+        // resizable: false,
+        ..Default::default()
+    })
+    .add_plugins(DefaultPlugins)
+    .add_plugin(FrameTimeDiagnosticsPlugin::default())
+    .add_plugin(LogDiagnosticsPlugin::default())
+    // F3 で診断情報を表示する
+    .add_plugin(GameDiagnosticsPlugin)
+    .add_plugin(DiagnosticsOverlayPlugin::default())
+    .add_component_count_diagnostic::<TrackingBomb>(diagnostic_id("bombs"), "bombs")
+    .add_plugin(ScenarioPlugin)
+    // P キーで一時停止
+    .add_plugin(PausePlugin::default())
+    .add_event::<BombDetonated>()
+    .add_startup_system(setup)
+    .add_system_set(
+        SystemSet::new()
+            .with_run_criteria(gameplay_timestep)
+            .with_system(player_movement)
+            .with_system(tracking_missile_movement),
+    )
+    .add_system(bomb_spawn)
+    .add_system(bom_animation)
+    .run();
 }
 
 #[derive(Component)]
@@ -51,21 +88,30 @@ struct TrackingBomb {
     /// 回転速度 0 ~ 90° (0 ~ π/2)
     rotation_speed: f32,
     speed: f32,
+    target: Entity,
     is_tracking: bool,
+    is_detonated: bool,
 }
 
 impl TrackingBomb {
-    fn new(rotation_speed: f32, speed: f32) -> Self {
+    fn new(rotation_speed: f32, speed: f32, target: Entity) -> Self {
         Self {
             rotation_speed,
             speed,
+            target,
             is_tracking: true,
+            is_detonated: false,
         }
     }
 }
 
-#[derive(Component)]
-struct BumbToSpawn(Vec3);
+///
+/// 止まったボムが爆発する時のイベント
+///
+struct BombDetonated {
+    entity: Entity,
+    translation: Vec3,
+}
 
 #[derive(Component)]
 struct Bomb;
@@ -76,9 +122,27 @@ struct BombTimer(Timer);
 #[derive(Component)]
 struct Player;
 
-fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>, scenario: Option<Res<Scenario>>) {
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
 
+    let player = commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(250.0, -250.0, 0.0),
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/ship_A.png"),
+            ..Default::default()
+        })
+        .insert(Player)
+        .id();
+
+    if let Some(scenario) = scenario {
+        spawn_scenario(&mut commands, &assert_server, &scenario);
+        return;
+    }
+
     commands
         .spawn_bundle(SpriteBundle {
             transform: Transform {
@@ -89,19 +153,37 @@ fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
             texture: assert_server.load("textures/うんちハニワ.png"),
             ..Default::default()
         })
-        .insert(TrackingBomb::new(30_f32.to_radians(), 180.0));
+        .insert(TrackingBomb::new(30_f32.to_radians(), 180.0, player));
+}
 
-    commands
-        .spawn_bundle(SpriteBundle {
-            transform: Transform {
-                translation: Vec3::new(250.0, -250.0, 0.0),
-                scale: Vec3::new(0.5, 0.5, 1.0),
-                ..Default::default()
-            },
+///
+/// シナリオのターゲットとボムを配置する
+///
+fn spawn_scenario(commands: &mut Commands, assert_server: &AssetServer, scenario: &Scenario) {
+    let spawns = scenario.generate();
+    let targets = spawns.spawn_targets(
+        commands,
+        SpriteBundle {
+            transform: Transform::from_scale(Vec3::new(0.25, 0.25, 1.0)),
             texture: assert_server.load("textures/ship_A.png"),
             ..Default::default()
-        })
-        .insert(Player);
+        },
+    );
+
+    let texture = assert_server.load("textures/うんちハニワ.png");
+    for bomb in &spawns.bombs {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: bomb.transform().with_scale(Vec3::new(0.03, 0.03, 1.0)),
+                texture: texture.clone(),
+                ..Default::default()
+            })
+            .insert(TrackingBomb::new(
+                bomb.turn_rate,
+                bomb.speed,
+                targets[bomb.target],
+            ));
+    }
 }
 
 fn player_movement(
@@ -131,98 +213,84 @@ fn player_movement(
 }
 
 fn tracking_missile_movement(
-    mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &mut Sprite, &mut TrackingBomb), Without<Player>>,
-    player_query: Query<&Transform, With<Player>>,
+    pool: Res<ComputeTaskPool>,
+    mut snapshot: Local<TargetSnapshot>,
+    mut query: Query<(Entity, &mut Transform, &mut Sprite, &mut TrackingBomb)>,
+    targets: Query<&GlobalTransform>,
+    mut detonations: EventWriter<BombDetonated>,
 ) {
-    let player_transform = player_query.single();
-    let player_translation = player_transform.translation.truncate();
-
-    for (entity, mut tf, mut sprite, mut tracking_missile) in query.iter_mut() {
+    snapshot.rebuild(
+        query
+            .iter()
+            .map(|(_, _, _, tracking_missile)| LookTarget::Entity(tracking_missile.target)),
+        None,
+        &targets,
+    );
+
+    // 並列に処理している間はイベントを送れないので、爆発するボムを集めてから送る
+    let detonated = Mutex::new(Vec::new());
+
+    let update = |(entity, mut tf, mut sprite, mut tracking_missile): (
+        Entity,
+        Mut<Transform>,
+        Mut<Sprite>,
+        Mut<TrackingBomb>,
+    )| {
         if tracking_missile.is_tracking {
-            let missile_translation = tf.translation.truncate();
-
-            // 接近距離の指定
-            let approach_distance = 100.0;
-            if missile_translation.distance(player_translation) <= approach_distance {
-                tracking_missile.is_tracking = false;
-            }
-
-            // 進んでいる座標方向
-            let missile_forward = (tf.rotation * Vec3::Y).truncate();
-
-            // ミサイルからプレイヤーへのベクトル
-            let to_player = (player_translation - missile_translation).normalize();
-
-            // 敵の前方ベクトルとプレーヤーへの方向の間の内積を取得
-            let forward_dot_player = missile_forward.dot(to_player);
-
-            // 内積が約1.0の場合、敵はすでにプレーヤーに直面しているため、早期に終了できます。
-            if (forward_dot_player - 1.0).abs() < f32::EPSILON {
-                continue;
+            // ターゲットが消えた場合は直進する
+            if let Some(target) = snapshot.position(LookTarget::Entity(tracking_missile.target)) {
+                // 接近距離の指定
+                let approach_distance = 100.0;
+                if tf.translation.truncate().distance(target) <= approach_distance {
+                    tracking_missile.is_tracking = false;
+                }
+
+                steer_towards(&mut tf, target, tracking_missile.rotation_speed * TIME_STEP);
             }
 
-            // ミサイルの正しいベクトルを2Dで取得（すでに単位長）
-            let missile_right = (tf.rotation * Vec3::X).truncate();
-
-            // ミサイルの右ベクトルの内積とプレイヤーの船への方向を取得します。
-            // 内積が負の場合は反時計回りに回転する必要があり、正の場合は時計回りに回転する必要があります。
-            // ドット積が0.0の場合でも、 `copysign`は1.0を返すことに注意してください
-            // （プレーヤーが敵の真後ろにいるため、右のベクトルに垂直であるため）。
-            let right_dot_player = missile_right.dot(to_player);
-
-            // 右のドットプレーヤーから回転のサインを決定します。
-            // 2D bevy座標系は、画面の外を指している+ Zを中心に回転するため、ここで符号を無効にする必要があります。
-            // 右手の法則により、+ Zを中心とした正の回転は反時計回りで、負の回転は時計回りです。
-            let rotation_sign = -f32::copysign(1.0, right_dot_player);
-
-            // ターゲットをオーバーシュートしないように回転を制限します。
-            // ここで内積を角度に変換して、回転角を固定できるようにする必要があります。
-            let max_angle = forward_dot_player.clamp(-1.0, 1.0).acos();
-
-            // 制限付きの回転角を計算する
-            let rotation_angle =
-                rotation_sign * (tracking_missile.rotation_speed * TIME_STEP).min(max_angle);
-
-            // クォータニオンを現在の敵の向きの方向からプレーヤーの向きの方向に回転させます
-            let rotation_delta = Quat::from_rotation_z(rotation_angle);
-
-            // ミサイルを回転させてプレイヤーと向き合う
-            tf.rotation *= rotation_delta;
-
-            let movement_direction = tf.rotation * Vec3::Y;
-            let movement_distance = tracking_missile.speed * TIME_STEP;
-            let translation_delta = movement_direction * movement_distance;
-
-            tf.translation += translation_delta;
+            move_forward(&mut tf, tracking_missile.speed * TIME_STEP);
         } else if tracking_missile.speed > 0. {
             tracking_missile.speed -= 2.5;
 
             sprite.color = Color::RED;
 
-            let movement_direction = tf.rotation * Vec3::Y;
-            let movement_distance = tracking_missile.speed * TIME_STEP;
-            let translation_delta = movement_direction * movement_distance;
-
-            tf.translation += translation_delta;
-        } else {
-            commands.entity(entity).despawn();
-            commands.spawn().insert(BumbToSpawn(tf.translation));
+            move_forward(&mut tf, tracking_missile.speed * TIME_STEP);
+        } else if !tracking_missile.is_detonated {
+            tracking_missile.is_detonated = true;
+            detonated.lock().unwrap().push(BombDetonated {
+                entity,
+                translation: tf.translation,
+            });
         }
+    };
+
+    // スレッドが1つしか無い場合はタスクを分ける分だけ遅くなる
+    if pool.thread_num() > 1 {
+        query.par_for_each_mut(&pool, BATCH_SIZE, update);
+    } else {
+        query.for_each_mut(update);
     }
+
+    detonations.send_batch(detonated.into_inner().unwrap().into_iter());
 }
 
 fn bomb_spawn(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    spawn_query: Query<(Entity, &BumbToSpawn)>,
+    mut detonations: EventReader<BombDetonated>,
+    mut explosions: EventWriter<ExplosionEvent>,
 ) {
-    for (entity, bom_to_spawn) in spawn_query.iter() {
+    for detonation in detonations.iter() {
+        commands.entity(detonation.entity).despawn();
+
+        explosions.send(ExplosionEvent {
+            position: detonation.translation.truncate(),
+        });
         commands
             .spawn_bundle(SpriteBundle {
                 texture: asset_server.load("textures/icon.png"),
                 transform: Transform {
-                    translation: bom_to_spawn.0,
+                    translation: detonation.translation,
                     scale: Vec3::new(0.5, 0.5, 1.0),
                     ..Default::default()
                 },
@@ -230,18 +298,17 @@ fn bomb_spawn(
             })
             .insert(Bomb)
             .insert(BombTimer(Timer::from_seconds(1.5, false)));
-
-        commands.entity(entity).despawn();
     }
 }
 
 fn bom_animation(
     mut commands: Commands,
     mut query: Query<(Entity, &mut Sprite, &mut BombTimer), With<Bomb>>,
-    time: Res<Time>,
+    game_time: Res<GameTime>,
 ) {
     for (entity, mut sprite, mut bomb_timer) in query.iter_mut() {
-        bomb_timer.0.tick(time.delta());
+        // 一時停止中は進めない
+        bomb_timer.0.tick(game_time.delta());
 
         sprite.color.set_a(bomb_timer.0.percent_left());
 
@@ -3,13 +3,22 @@
 //
 // プレイヤーを追跡する弾丸、
 //
+// 引数でシナリオを指定すると、大量のミサイルが複数のターゲットを追います。
+// cargo run --release --example tracking_missile -- --scenario assets/scenarios/stress.scenario.ron
+// cargo run --release --example tracking_missile -- --seed 7 --targets 8 --missiles 5000
+//
 
 use bevy::{
-    core::FixedTimestep,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
     render::settings::{Backends, WgpuSettings},
 };
+use bevy_examples::diagnostics::GameDiagnosticsPlugin;
+use bevy_examples::look_at::LookTarget;
+use bevy_examples::pause::{gameplay_timestep, PausePlugin};
+use bevy_examples::scenario::{Scenario, ScenarioPlugin};
+use bevy_examples::tracking::{TrackingMissile, TrackingPlugin};
+use bevy_examples::ui::diagnostics::DiagnosticsOverlayPlugin;
 
 const WINDOW_HEIGHT: f32 = 600.0;
 const WINDOW_WIDTH: f32 = 600.0;
@@ -20,72 +29,117 @@ const PLAYER_SPEED: f32 = 120.0;
 const MISSILE_SPEED: f32 = 180.0;
 
 fn main() {
-    App::new()
-        .insert_resource(WgpuSettings {
-            backends: Some(Backends::VULKAN),
-            ..Default::default()
-        })
-        .insert_resource(WindowDescriptor {
-            title: "SimpleTracking".to_owned(),
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
-            // resizable: false,
-            ..Default::default()
-        })
-        .add_plugins(DefaultPlugins)
-        .add_plugin(FrameTimeDiagnosticsPlugin::default())
-        .add_plugin(LogDiagnosticsPlugin::default())
-        .add_startup_system(setup)
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(player_movement)
-                .with_system(tracking_missile_movement),
-        )
-        .run();
-}
-
-#[derive(Component)]
-struct TrackingMissile {
-    rotation_speed: f32,
-}
-impl Default for TrackingMissile {
-    fn default() -> Self {
-        Self {
-            rotation_speed: 90_f32.to_radians(),
+    let scenario = match Scenario::from_args(std::env::args().skip(1)) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(2);
         }
+    };
+
+    let mut app = App::new();
+    if let Some(scenario) = scenario {
+        app.insert_resource(scenario);
     }
+
+    app.insert_resource(WgpuSettings {
+        backends: Some(Backends::VULKAN),
+        ..Default::default()
+    })
+    .insert_resource(WindowDescriptor {
+        title: "SimpleTracking".to_owned(),
+        width: WINDOW_WIDTH,
+        height: WINDOW_HEIGHT,
+        // resizable: false,
+        ..Default::default()
+    })
+    .add_plugins(DefaultPlugins)
+    .add_plugin(FrameTimeDiagnosticsPlugin::default())
+    .add_plugin(LogDiagnosticsPlugin::default())
+    // F3 で診断情報を表示する
+    .add_plugin(GameDiagnosticsPlugin)
+    .add_plugin(DiagnosticsOverlayPlugin::default())
+    .add_plugin(TrackingPlugin::default())
+    .add_plugin(ScenarioPlugin)
+    // P キーで一時停止
+    .add_plugin(PausePlugin::default())
+    .add_startup_system(setup)
+    .add_system_set(
+        SystemSet::new()
+            .with_run_criteria(gameplay_timestep)
+            .with_system(player_movement),
+    )
+    .run();
 }
 
 #[derive(Component)]
 struct Player;
 
-fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>, scenario: Option<Res<Scenario>>) {
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
 
-    commands
+    let player = commands
         .spawn_bundle(SpriteBundle {
             transform: Transform {
-                translation: Vec3::new(-200.0, 0.0, 0.0),
-                scale: Vec3::new(0.0625, 0.0625, 1.0),
+                translation: Vec3::new(250.0, -250.0, 0.0),
+                scale: Vec3::new(0.5, 0.5, 1.0),
                 ..Default::default()
             },
-            texture: assert_server.load("textures/うんちハニワ.png"),
+            texture: assert_server.load("textures/Ship_C.png"),
             ..Default::default()
         })
-        .insert(TrackingMissile::default());
+        .insert(Player)
+        .id();
+
+    if let Some(scenario) = scenario {
+        spawn_scenario(&mut commands, &assert_server, &scenario);
+        return;
+    }
 
     commands
         .spawn_bundle(SpriteBundle {
             transform: Transform {
-                translation: Vec3::new(250.0, -250.0, 0.0),
-                scale: Vec3::new(0.5, 0.5, 1.0),
+                translation: Vec3::new(-200.0, 0.0, 0.0),
+                scale: Vec3::new(0.0625, 0.0625, 1.0),
                 ..Default::default()
             },
-            texture: assert_server.load("textures/Ship_C.png"),
+            texture: assert_server.load("textures/うんちハニワ.png"),
             ..Default::default()
         })
-        .insert(Player);
+        .insert(TrackingMissile {
+            speed: MISSILE_SPEED,
+            ..TrackingMissile::new(LookTarget::Entity(player))
+        });
+}
+
+///
+/// シナリオのターゲットとミサイルを配置する
+///
+fn spawn_scenario(commands: &mut Commands, assert_server: &AssetServer, scenario: &Scenario) {
+    let spawns = scenario.generate();
+    let targets = spawns.spawn_targets(
+        commands,
+        SpriteBundle {
+            transform: Transform::from_scale(Vec3::new(0.25, 0.25, 1.0)),
+            texture: assert_server.load("textures/ship_C.png"),
+            ..Default::default()
+        },
+    );
+
+    let texture = assert_server.load("textures/うんちハニワ.png");
+    for missile in &spawns.missiles {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: missile.transform().with_scale(Vec3::new(0.03, 0.03, 1.0)),
+                texture: texture.clone(),
+                ..Default::default()
+            })
+            .insert(TrackingMissile {
+                rotation_speed: missile.turn_rate,
+                speed: missile.speed,
+                target: LookTarget::Entity(targets[missile.target]),
+            });
+    }
 }
 
 fn player_movement(
@@ -113,62 +167,3 @@ fn player_movement(
         tf.translation.y += y_dir * PLAYER_SPEED * TIME_STEP;
     }
 }
-
-fn tracking_missile_movement(
-    mut query: Query<(&mut Transform, &mut TrackingMissile), Without<Player>>,
-    player_query: Query<&Transform, With<Player>>,
-) {
-    let player_transform = player_query.single();
-    let player_translation = player_transform.translation.truncate();
-
-    for (mut tf, tracking_missile) in query.iter_mut() {
-        let missile_translation = tf.translation.truncate();
-        // 進んでいる座標方向
-        let missile_forward = (tf.rotation * Vec3::Y).truncate();
-
-        // ミサイルからプレイヤーへのベクトル
-        let to_player = (player_translation - missile_translation).normalize();
-
-        // 敵の前方ベクトルとプレーヤーへの方向の間の内積を取得
-        let forward_dot_player = missile_forward.dot(to_player);
-
-        // 内積が約1.0の場合、敵はすでにプレーヤーに直面しているため、早期に終了できます。
-        if (forward_dot_player - 1.0).abs() < f32::EPSILON {
-            continue;
-        }
-
-        // ミサイルの正しいベクトルを2Dで取得（すでに単位長）
-        let missile_right = (tf.rotation * Vec3::X).truncate();
-
-        // ミサイルの右ベクトルの内積とプレイヤーの船への方向を取得します。
-        // 内積が負の場合は反時計回りに回転する必要があり、正の場合は時計回りに回転する必要があります。
-        // ドット積が0.0の場合でも、 `copysign`は1.0を返すことに注意してください
-        // （プレーヤーが敵の真後ろにいるため、右のベクトルに垂直であるため）。
-        let right_dot_player = missile_right.dot(to_player);
-
-        // 右のドットプレーヤーから回転のサインを決定します。
-        // 2D bevy座標系は、画面の外を指している+ Zを中心に回転するため、ここで符号を無効にする必要があります。
-        // 右手の法則により、+ Zを中心とした正の回転は反時計回りで、負の回転は時計回りです。
-        let rotation_sign = -f32::copysign(1.0, right_dot_player);
-
-        // ターゲットをオーバーシュートしないように回転を制限します。
-        // ここで内積を角度に変換して、回転角を固定できるようにする必要があります。
-        let max_angle = forward_dot_player.clamp(-1.0, 1.0).acos();
-
-        // 制限付きの回転角を計算する
-        let rotation_angle =
-            rotation_sign * (tracking_missile.rotation_speed * TIME_STEP).min(max_angle);
-
-        // クォータニオンを現在の敵の向きの方向からプレーヤーの向きの方向に回転させます
-        let rotation_delta = Quat::from_rotation_z(rotation_angle);
-
-        // ミサイルを回転させてプレイヤーと向き合う
-        tf.rotation *= rotation_delta;
-
-        let movement_direction = tf.rotation * Vec3::Y;
-        let movement_distance = MISSILE_SPEED * TIME_STEP;
-        let translation_delta = movement_direction * movement_distance;
-
-        tf.translation += translation_delta;
-    }
-}
@@ -0,0 +1,196 @@
+// #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+//
+// 移動する船体に取り付けた砲塔をカーソルに向ける
+//
+// 照準が合っている砲塔だけが左クリックで発射できます。
+//
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::settings::{Backends, WgpuSettings},
+};
+use bevy_examples::cursor::{CursorPlugin, MainCamera};
+use bevy_examples::look_at::{LookAtPlugin, LookTarget};
+use bevy_examples::pause::gameplay_timestep;
+use bevy_examples::turret::{OnTarget, Turret, TurretPlugin};
+
+const WINDOW_HEIGHT: f32 = 800.0;
+const WINDOW_WIDTH: f32 = 800.0;
+
+const TIME_STEP: f32 = 1.0 / 60.0;
+
+const HULL_SPEED: f32 = 120.0;
+const HULL_ROTATION_SPEED: f32 = 90.0;
+const BULLET_SPEED: f32 = 480.0;
+
+fn main() {
+    App::new()
+        .insert_resource(WgpuSettings {
+            backends: Some(Backends::VULKAN),
+            ..Default::default()
+        })
+        .insert_resource(WindowDescriptor {
+            title: "Turret".to_owned(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(CursorPlugin)
+        .add_plugin(LookAtPlugin)
+        .add_plugin(TurretPlugin)
+        .add_startup_system(setup)
+        .add_system(turret_color)
+        .add_system(turret_fire)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(hull_movement)
+                .with_system(bullet_movement),
+        )
+        .run();
+}
+
+#[derive(Component)]
+struct Hull;
+
+#[derive(Component)]
+struct Bullet;
+
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+
+    let turret_texture: Handle<Image> = assert_server.load("textures/うんちハニワ.png");
+    let turret_sprite = |translation: Vec3| SpriteBundle {
+        transform: Transform {
+            translation,
+            scale: Vec3::new(0.1, 0.1, 1.0),
+            ..Default::default()
+        },
+        texture: turret_texture.clone(),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/ship_C.png"),
+            ..Default::default()
+        })
+        .insert(Hull)
+        .with_children(|parent| {
+            // 前方の砲塔は前方 240° の範囲を旋回
+            parent
+                .spawn_bundle(turret_sprite(Vec3::new(0.0, 60.0, 1.0)))
+                .insert_bundle(Turret::bundle(
+                    LookTarget::Cursor,
+                    (-120_f32.to_radians(), 120_f32.to_radians()),
+                    120_f32.to_radians(),
+                ));
+
+            // 左右の砲塔は側面のみ
+            parent
+                .spawn_bundle(turret_sprite(Vec3::new(-50.0, -30.0, 1.0)))
+                .insert_bundle(Turret::bundle(
+                    LookTarget::Cursor,
+                    (0.0, 150_f32.to_radians()),
+                    60_f32.to_radians(),
+                ));
+            parent
+                .spawn_bundle(turret_sprite(Vec3::new(50.0, -30.0, 1.0)))
+                .insert_bundle(Turret::bundle(
+                    LookTarget::Cursor,
+                    (-150_f32.to_radians(), 0.0),
+                    60_f32.to_radians(),
+                ));
+        });
+}
+
+fn hull_movement(
+    input_keyboard: Res<Input<KeyCode>>,
+    mut query: Query<&mut Transform, With<Hull>>,
+) {
+    let rotation_dir = if input_keyboard.pressed(KeyCode::A) {
+        1.
+    } else if input_keyboard.pressed(KeyCode::D) {
+        -1.
+    } else {
+        0.
+    };
+
+    let move_dir = if input_keyboard.pressed(KeyCode::W) {
+        1.
+    } else if input_keyboard.pressed(KeyCode::S) {
+        -1.
+    } else {
+        0.
+    };
+
+    if let Ok(mut tf) = query.get_single_mut() {
+        tf.rotate(Quat::from_rotation_z(
+            rotation_dir * HULL_ROTATION_SPEED.to_radians() * TIME_STEP,
+        ));
+
+        let movement_direction = tf.rotation * Vec3::Y;
+        tf.translation += movement_direction * move_dir * HULL_SPEED * TIME_STEP;
+    }
+}
+
+fn turret_color(mut query: Query<(&mut Sprite, Option<&OnTarget>), With<Turret>>) {
+    for (mut sprite, on_target) in query.iter_mut() {
+        sprite.color = if on_target.is_some() {
+            Color::WHITE
+        } else {
+            Color::GRAY
+        };
+    }
+}
+
+fn turret_fire(
+    mut commands: Commands,
+    assert_server: Res<AssetServer>,
+    mouse_input: Res<Input<MouseButton>>,
+    query: Query<&GlobalTransform, (With<Turret>, With<OnTarget>)>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    for global_tf in query.iter() {
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: global_tf.translation,
+                    rotation: global_tf.rotation,
+                    scale: Vec3::new(0.03, 0.03, 1.0),
+                },
+                texture: assert_server.load("textures/うんちハニワ.png"),
+                ..Default::default()
+            })
+            .insert(Bullet);
+    }
+}
+
+fn bullet_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform), With<Bullet>>,
+) {
+    for (entity, mut tf) in query.iter_mut() {
+        let movement_direction = tf.rotation * Vec3::Y;
+        tf.translation += movement_direction * BULLET_SPEED * TIME_STEP;
+
+        // 画面外に出たら削除
+        if tf.translation.x.abs() > WINDOW_WIDTH || tf.translation.y.abs() > WINDOW_HEIGHT {
+            commands.entity(entity).despawn();
+        }
+    }
+}
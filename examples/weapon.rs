@@ -0,0 +1,153 @@
+// #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+//
+// 武器による弾の発射
+//
+// 左クリック（またはタップ）で扇状に弾を連射、右クリックでカーソルを追跡するミサイルを
+// 3連射します。ミサイルは6発撃つとリロードします。
+//
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::settings::{Backends, WgpuSettings},
+};
+use bevy_examples::cursor::{CursorPlugin, MainCamera};
+use bevy_examples::look_at::{LookAt2d, LookAtPlugin, LookTarget};
+use bevy_examples::pause::gameplay_timestep;
+use bevy_examples::touch::{TapEvent, TouchControlPlugin};
+use bevy_examples::tracking::TrackingPlugin;
+use bevy_examples::weapon::{Ammo, ProjectilePrefab, Weapon, WeaponPlugin, WeaponSystem};
+use bevy_examples::TIME_STEP;
+
+const WINDOW_HEIGHT: f32 = 800.0;
+const WINDOW_WIDTH: f32 = 800.0;
+
+const PLAYER_SPEED: f32 = 160.0;
+
+fn main() {
+    App::new()
+        .insert_resource(WgpuSettings {
+            backends: Some(Backends::VULKAN),
+            ..Default::default()
+        })
+        .insert_resource(WindowDescriptor {
+            title: "Weapon".to_owned(),
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(CursorPlugin)
+        .add_plugin(TouchControlPlugin)
+        .add_plugin(LookAtPlugin)
+        .add_plugin(TrackingPlugin::default())
+        .add_plugin(WeaponPlugin)
+        .add_startup_system(setup)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(player_movement),
+        )
+        .add_system(weapon_input.before(WeaponSystem))
+        .run();
+}
+
+#[derive(Component)]
+struct Player;
+
+#[derive(Component)]
+enum WeaponSlot {
+    Gun,
+    MissileLauncher,
+}
+
+fn setup(mut commands: Commands, assert_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+
+    let projectile_texture: Handle<Image> = assert_server.load("textures/うんちハニワ.png");
+
+    let gun = Weapon::new(
+        ProjectilePrefab::bullet(projectile_texture.clone(), 480.0)
+            .with_scale(Vec3::new(0.03, 0.03, 1.0)),
+        8.0,
+    )
+    .with_spread(3, 20_f32.to_radians())
+    .with_muzzle_offset(Vec2::new(0.0, 40.0));
+
+    let missile_launcher = Weapon::new(
+        ProjectilePrefab::tracking_missile(projectile_texture, 240.0, 180_f32.to_radians())
+            .with_scale(Vec3::new(0.05, 0.05, 1.0)),
+        1.0,
+    )
+    .with_burst(3, 0.1)
+    .with_ammo(Ammo::new(6, 2.0))
+    .with_target(LookTarget::Cursor);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                scale: Vec3::new(0.5, 0.5, 1.0),
+                ..Default::default()
+            },
+            texture: assert_server.load("textures/ship_C.png"),
+            ..Default::default()
+        })
+        .insert(Player)
+        .insert(LookAt2d::new(LookTarget::Cursor))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle((Transform::default(), GlobalTransform::default()))
+                .insert(gun)
+                .insert(WeaponSlot::Gun);
+            parent
+                .spawn_bundle((Transform::default(), GlobalTransform::default()))
+                .insert(missile_launcher)
+                .insert(WeaponSlot::MissileLauncher);
+        });
+}
+
+fn player_movement(
+    input_keyboard: Res<Input<KeyCode>>,
+    mut query: Query<&mut Transform, With<Player>>,
+) {
+    let x_dir = if input_keyboard.pressed(KeyCode::A) {
+        -1.
+    } else if input_keyboard.pressed(KeyCode::D) {
+        1.
+    } else {
+        0.
+    };
+
+    let y_dir = if input_keyboard.pressed(KeyCode::W) {
+        1.
+    } else if input_keyboard.pressed(KeyCode::S) {
+        -1.
+    } else {
+        0.
+    };
+
+    if let Ok(mut tf) = query.get_single_mut() {
+        tf.translation.x += x_dir * PLAYER_SPEED * TIME_STEP;
+        tf.translation.y += y_dir * PLAYER_SPEED * TIME_STEP;
+    }
+}
+
+fn weapon_input(
+    mouse_input: Res<Input<MouseButton>>,
+    mut taps: EventReader<TapEvent>,
+    mut query: Query<(&mut Weapon, &WeaponSlot)>,
+) {
+    let tapped = taps.iter().count() > 0;
+
+    for (mut weapon, slot) in query.iter_mut() {
+        weapon.trigger = match slot {
+            WeaponSlot::Gun => mouse_input.pressed(MouseButton::Left) || tapped,
+            WeaponSlot::MissileLauncher => mouse_input.pressed(MouseButton::Right),
+        };
+    }
+}
@@ -0,0 +1,787 @@
+//
+// ベンチマーク
+//
+// ウィンドウを開かずにシナリオを決まったフレーム数だけ実行し、
+// フレーム全体とラベルを付けたシステムごとの時間を集計します。
+// 結果は環境の情報と一緒に JSON / CSV に保存でき、保存した2つの結果を比較できます。
+//
+// ```
+// let mut app = App::new();
+// app.add_plugins(MinimalPlugins)
+//     .add_plugin(BenchPlugin)
+//     .add_plugin(TrackingPlugin::default())
+//     .time_label("tracking", TrackingSystem, true);
+// println!("{}", run_frames(&mut app, 600, 60));
+// ```
+//
+
+use std::{
+    fmt, fs,
+    path::Path,
+    process::Command,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    core::CoreSystem,
+    ecs::schedule::{SingleThreadedExecutor, SystemLabel},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::pause::{gameplay_timestep, GameTime};
+use crate::TIME_STEP;
+
+///
+/// ベンチマーク用の設定を追加するプラグイン
+///
+/// - ゲーム内時間を毎フレーム `TIME_STEP` だけ進める（1フレームで固定タイムステップ1回）
+/// - システムの時間が重ならないよう `Update` ステージを1スレッドで実行する
+///
+pub struct BenchPlugin;
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BenchTimings>()
+            .init_resource::<GameTime>()
+            .add_system_to_stage(CoreStage::First, fixed_clock_system.after(CoreSystem::Time))
+            .schedule
+            .stage(CoreStage::Update, |stage: &mut SystemStage| {
+                stage.set_executor(Box::new(SingleThreadedExecutor));
+                stage
+            });
+    }
+}
+
+fn fixed_clock_system(mut game_time: ResMut<GameTime>) {
+    game_time.advance(TIME_STEP, false);
+}
+
+///
+/// 集計値（ミリ秒）
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Stats {
+    ///
+    /// サンプルから集計する（サンプルが無い場合は `None`）
+    ///
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // 最近傍順位法
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Some(Self {
+            min: sorted[0],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    /// 集計値の名前
+    pub const METRICS: [&'static str; 6] = ["min", "mean", "p50", "p95", "p99", "max"];
+
+    /// 名前で集計値を取得する
+    pub fn get(&self, metric: &str) -> Option<f64> {
+        match metric {
+            "min" => Some(self.min),
+            "mean" => Some(self.mean),
+            "p50" => Some(self.p50),
+            "p95" => Some(self.p95),
+            "p99" => Some(self.p99),
+            "max" => Some(self.max),
+            _ => None,
+        }
+    }
+}
+
+///
+/// システムごとの計測時間
+///
+#[derive(Debug, Default)]
+pub struct BenchTimings {
+    /// (名前, フレームごとの時間)
+    systems: Vec<(&'static str, Vec<f64>)>,
+    running: HashMap<&'static str, Instant>,
+    /// 今のフレームで計測した時間の合計
+    frame: HashMap<&'static str, f64>,
+}
+
+impl BenchTimings {
+    /// 結果に表示する順番を決めるため、計るシステムを登録する
+    pub fn register(&mut self, name: &'static str) {
+        if !self.systems.iter().any(|(system, _)| *system == name) {
+            self.systems.push((name, Vec::new()));
+        }
+    }
+
+    pub fn start(&mut self, name: &'static str) {
+        self.running.insert(name, Instant::now());
+    }
+
+    pub fn stop(&mut self, name: &'static str) {
+        if let Some(start) = self.running.remove(name) {
+            *self.frame.entry(name).or_default() += start.elapsed().as_secs_f64() * 1000.0;
+        }
+    }
+
+    ///
+    /// フレームの計測を終える
+    ///
+    /// `record` が `false` の場合（ウォームアップ中）は記録しません。
+    ///
+    pub fn finish_frame(&mut self, record: bool) {
+        for (name, time) in self.frame.drain() {
+            if !record {
+                continue;
+            }
+            match self.systems.iter_mut().find(|(system, _)| *system == name) {
+                Some((_, samples)) => samples.push(time),
+                None => self.systems.push((name, vec![time])),
+            }
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = (&'static str, &[f64])> {
+        self.systems
+            .iter()
+            .map(|(name, samples)| (*name, samples.as_slice()))
+    }
+}
+
+///
+/// システムの時間を計る
+///
+pub trait BenchAppExt {
+    ///
+    /// `label` を付けた `Update` ステージのシステムの時間を `name` として計る
+    ///
+    /// `fixed` が `true` の場合は `gameplay_timestep` で動くシステムとして計ります。
+    ///
+    fn time_label(
+        &mut self,
+        name: &'static str,
+        label: impl SystemLabel + Clone,
+        fixed: bool,
+    ) -> &mut Self;
+}
+
+impl BenchAppExt for App {
+    fn time_label(
+        &mut self,
+        name: &'static str,
+        label: impl SystemLabel + Clone,
+        fixed: bool,
+    ) -> &mut Self {
+        let start = move |mut timings: ResMut<BenchTimings>| timings.start(name);
+        let stop = move |mut timings: ResMut<BenchTimings>| timings.stop(name);
+
+        let mut timings = self
+            .world
+            .get_resource_or_insert_with(BenchTimings::default);
+        let previous = timings.systems.last().map(|(name, _)| *name);
+        timings.register(name);
+
+        let mut start = start.label(BenchMarker::Start(name)).before(label.clone());
+        // 計測の区間が重ならないよう、先に登録したシステムの計測が終わってから始める
+        if let Some(previous) = previous {
+            start = start.after(BenchMarker::Stop(previous));
+        }
+        let mut set = SystemSet::new()
+            .with_system(start)
+            .with_system(stop.label(BenchMarker::Stop(name)).after(label));
+        if fixed {
+            set = set.with_run_criteria(gameplay_timestep);
+        }
+        self.add_system_set(set)
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+enum BenchMarker {
+    Start(&'static str),
+    Stop(&'static str),
+}
+
+///
+/// システムの時間の集計値
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemStats {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+///
+/// ベンチマークの結果
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// 計測したフレーム数
+    pub frames: usize,
+    /// フレーム全体の時間
+    pub frame: Stats,
+    /// システムごとの時間
+    pub systems: Vec<SystemStats>,
+}
+
+impl BenchReport {
+    /// フレーム全体とシステムごとの (名前, 集計値)
+    pub fn rows(&self) -> impl Iterator<Item = (&str, &Stats)> {
+        std::iter::once(("frame", &self.frame)).chain(
+            self.systems
+                .iter()
+                .map(|system| (system.name.as_str(), &system.stats)),
+        )
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<28}{:>10}{:>10}{:>10}{:>10}{:>10}{:>10}   (ms, {} frames)",
+            "", "min", "mean", "p50", "p95", "p99", "max", self.frames
+        )?;
+        for (name, stats) in self.rows() {
+            writeln!(
+                f,
+                "{:<28}{:>10.4}{:>10.4}{:>10.4}{:>10.4}{:>10.4}{:>10.4}",
+                name, stats.min, stats.mean, stats.p50, stats.p95, stats.p99, stats.max
+            )?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// `warmup` フレーム実行した後、`frames` フレームの時間を計る
+///
+pub fn run_frames(app: &mut App, frames: usize, warmup: usize) -> BenchReport {
+    let mut frame_samples = Vec::with_capacity(frames);
+
+    for frame in 0..warmup + frames {
+        let record = frame >= warmup;
+        let start = Instant::now();
+        app.update();
+        if record {
+            frame_samples.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+        app.world
+            .get_resource_or_insert_with(BenchTimings::default)
+            .finish_frame(record);
+    }
+
+    let timings = app.world.get_resource_or_insert_with(BenchTimings::default);
+    BenchReport {
+        frames,
+        frame: Stats::from_samples(&frame_samples).unwrap_or_default(),
+        systems: timings
+            .samples()
+            .filter_map(|(name, samples)| {
+                Stats::from_samples(samples).map(|stats| SystemStats {
+                    name: name.to_owned(),
+                    stats,
+                })
+            })
+            .collect(),
+    }
+}
+
+///
+/// 計測した環境
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchMetadata {
+    /// 計測した時刻（UNIX時間、秒）
+    pub timestamp: u64,
+    /// git のリビジョン（変更がある場合は末尾に `-dirty`）
+    pub git_revision: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub cpu: Option<String>,
+    /// 論理コア数
+    pub threads: usize,
+    /// 計測に使った `ComputeTaskPool` のスレッド数
+    #[serde(default)]
+    pub compute_threads: usize,
+    /// `debug` か `release`
+    pub profile: String,
+}
+
+impl BenchMetadata {
+    ///
+    /// 現在の環境の情報を集める
+    ///
+    pub fn collect() -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            git_revision: git_revision(),
+            os: std::env::consts::OS.to_owned(),
+            arch: std::env::consts::ARCH.to_owned(),
+            cpu: cpu_name(),
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            compute_threads: 0,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_owned(),
+        }
+    }
+}
+
+fn git_revision() -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+
+    let revision = git(&["rev-parse", "--short", "HEAD"])?;
+    let dirty = matches!(
+        git(&["status", "--porcelain", "--untracked-files=no"]),
+        Some(status) if !status.is_empty()
+    );
+    Some(if dirty {
+        format!("{}-dirty", revision)
+    } else {
+        revision
+    })
+}
+
+fn cpu_name() -> Option<String> {
+    // Linux 以外では取得しない
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split(':').nth(1))
+        .map(|name| name.trim().to_owned())
+}
+
+///
+/// ファイルに保存するベンチマークの結果
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub scenario: String,
+    pub count: usize,
+    pub warmup: usize,
+    pub metadata: BenchMetadata,
+    pub report: BenchReport,
+}
+
+impl BenchResult {
+    ///
+    /// CSV にする
+    ///
+    /// 1行が1つのシステム（またはフレーム全体）です。
+    ///
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("scenario,count,frames,git_revision,timestamp,name");
+        for metric in Stats::METRICS {
+            csv.push(',');
+            csv.push_str(metric);
+        }
+        csv.push('\n');
+
+        let revision = self.metadata.git_revision.as_deref().unwrap_or("");
+        for (name, stats) in self.report.rows() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}",
+                self.scenario,
+                self.count,
+                self.report.frames,
+                revision,
+                self.metadata.timestamp,
+                name
+            ));
+            for metric in Stats::METRICS {
+                csv.push_str(&format!(",{}", stats.get(metric).unwrap()));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    ///
+    /// ファイルに保存する
+    ///
+    /// 拡張子が `.csv` の場合は CSV、それ以外は JSON で保存します。
+    ///
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => self.to_csv(),
+            _ => serde_json::to_string_pretty(self)?,
+        };
+        fs::write(path, contents)
+            .map_err(|e| anyhow::anyhow!("failed to write {}: {}", path.display(), e))
+    }
+
+    ///
+    /// JSON で保存した結果を読み込む
+    ///
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("invalid bench result {}: {}", path.display(), e))
+    }
+}
+
+///
+/// 2つの結果の1つの値の差
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delta {
+    pub name: String,
+    pub metric: &'static str,
+    pub base: f64,
+    pub new: f64,
+    /// 変化率（%、遅くなった場合は正）
+    pub change: f64,
+    /// 閾値を超えて遅くなったか
+    pub regressed: bool,
+}
+
+///
+/// 2つの結果の比較
+///
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    pub deltas: Vec<Delta>,
+    /// 片方にしか無いシステム
+    pub missing: Vec<String>,
+}
+
+impl Comparison {
+    ///
+    /// `base` と `new` の `metrics` を比べる
+    ///
+    /// `threshold` (%) より大きく遅くなった値を劣化とします。
+    /// `metrics` に `Stats::METRICS` に無い名前がある場合はエラーです。
+    ///
+    pub fn new(
+        base: &BenchReport,
+        new: &BenchReport,
+        metrics: &[&str],
+        threshold: f64,
+    ) -> anyhow::Result<Self> {
+        if let Some(metric) = metrics
+            .iter()
+            .find(|metric| !Stats::METRICS.contains(metric))
+        {
+            anyhow::bail!("unknown metric {}", metric);
+        }
+
+        let mut comparison = Self::default();
+
+        for (name, base_stats) in base.rows() {
+            let new_stats = match new.rows().find(|(new_name, _)| *new_name == name) {
+                Some((_, stats)) => stats,
+                None => {
+                    comparison.missing.push(name.to_owned());
+                    continue;
+                }
+            };
+
+            for metric in Stats::METRICS {
+                if !metrics.contains(&metric) {
+                    continue;
+                }
+                let base_value = base_stats.get(metric).unwrap();
+                let new_value = new_stats.get(metric).unwrap();
+                let change = if base_value > 0.0 {
+                    (new_value - base_value) / base_value * 100.0
+                } else {
+                    0.0
+                };
+                comparison.deltas.push(Delta {
+                    name: name.to_owned(),
+                    metric,
+                    base: base_value,
+                    new: new_value,
+                    change,
+                    regressed: change > threshold,
+                });
+            }
+        }
+
+        for (name, _) in new.rows() {
+            if base.rows().all(|(base_name, _)| base_name != name) {
+                comparison.missing.push(name.to_owned());
+            }
+        }
+        Ok(comparison)
+    }
+
+    pub fn has_regression(&self) -> bool {
+        self.deltas.iter().any(|delta| delta.regressed)
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<28}{:>8}{:>12}{:>12}{:>10}",
+            "", "metric", "base", "new", "change"
+        )?;
+        for delta in &self.deltas {
+            writeln!(
+                f,
+                "{:<28}{:>8}{:>12.4}{:>12.4}{:>+9.1}%{}",
+                delta.name,
+                delta.metric,
+                delta.base,
+                delta.new,
+                delta.change,
+                if delta.regressed { "  REGRESSION" } else { "" }
+            )?;
+        }
+        for name in &self.missing {
+            writeln!(f, "{:<28}  (only in one result)", name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(mean: f64, p95: f64) -> Stats {
+        Stats {
+            mean,
+            p95,
+            ..Default::default()
+        }
+    }
+
+    fn report(frame: Stats, systems: &[(&str, Stats)]) -> BenchReport {
+        BenchReport {
+            frames: 10,
+            frame,
+            systems: systems
+                .iter()
+                .map(|(name, stats)| SystemStats {
+                    name: name.to_string(),
+                    stats: *stats,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn stats_percentiles() {
+        let samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        let stats = Stats::from_samples(&samples).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                min: 1.0,
+                mean: 50.5,
+                p50: 50.0,
+                p95: 95.0,
+                p99: 99.0,
+                max: 100.0,
+            }
+        );
+
+        // 少ない場合は最近傍の順位
+        let stats = Stats::from_samples(&[3.0, 1.0, 2.0]).unwrap();
+        assert_eq!((stats.p50, stats.p95, stats.p99), (2.0, 3.0, 3.0));
+        let stats = Stats::from_samples(&[4.0]).unwrap();
+        assert_eq!((stats.min, stats.p50, stats.max), (4.0, 4.0, 4.0));
+
+        assert_eq!(Stats::from_samples(&[]), None);
+    }
+
+    #[test]
+    fn stats_by_name() {
+        let stats = Stats::from_samples(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        let values: Vec<_> = Stats::METRICS
+            .iter()
+            .map(|metric| stats.get(metric).unwrap())
+            .collect();
+        assert_eq!(values, [1.0, 2.5, 2.0, 4.0, 4.0, 4.0]);
+        assert_eq!(stats.get("median"), None);
+    }
+
+    #[test]
+    fn comparison_thresholds() {
+        let base = report(stats(10.0, 20.0), &[("movement", stats(2.0, 4.0))]);
+        let new = report(stats(11.0, 20.0), &[("movement", stats(2.5, 3.0))]);
+        let comparison = Comparison::new(&base, &new, &["mean", "p95"], 10.0).unwrap();
+
+        let deltas: Vec<_> = comparison
+            .deltas
+            .iter()
+            .map(|delta| {
+                (
+                    delta.name.as_str(),
+                    delta.metric,
+                    delta.change,
+                    delta.regressed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            deltas,
+            [
+                // ちょうど閾値は劣化にしない
+                ("frame", "mean", 10.0, false),
+                ("frame", "p95", 0.0, false),
+                ("movement", "mean", 25.0, true),
+                ("movement", "p95", -25.0, false),
+            ]
+        );
+        assert!(comparison.has_regression());
+        assert!(comparison.missing.is_empty());
+
+        // 閾値を上げると劣化ではない
+        let comparison = Comparison::new(&base, &new, &["mean", "p95"], 30.0).unwrap();
+        assert!(!comparison.has_regression());
+    }
+
+    #[test]
+    fn comparison_selects_metrics() {
+        let base = report(stats(10.0, 20.0), &[]);
+        let new = report(stats(10.0, 40.0), &[]);
+
+        let comparison = Comparison::new(&base, &new, &["mean"], 10.0).unwrap();
+        assert_eq!(comparison.deltas.len(), 1);
+        assert!(!comparison.has_regression());
+
+        let comparison = Comparison::new(&base, &new, &[], 10.0).unwrap();
+        assert!(comparison.deltas.is_empty());
+    }
+
+    #[test]
+    fn comparison_rejects_unknown_metrics() {
+        let base = report(stats(10.0, 20.0), &[]);
+        let error = Comparison::new(&base, &base, &["mean", "p90"], 10.0).unwrap_err();
+        assert_eq!(error.to_string(), "unknown metric p90");
+    }
+
+    #[test]
+    fn comparison_reports_missing_systems() {
+        let base = report(
+            stats(10.0, 20.0),
+            &[
+                ("movement", stats(2.0, 4.0)),
+                ("collision", stats(0.0, 0.0)),
+            ],
+        );
+        let new = report(
+            stats(10.0, 20.0),
+            &[
+                ("collision", stats(1.0, 1.0)),
+                ("snapshot", stats(1.0, 1.0)),
+            ],
+        );
+        let comparison = Comparison::new(&base, &new, &["mean"], 10.0).unwrap();
+
+        assert_eq!(comparison.missing, ["movement", "snapshot"]);
+        let names: Vec<_> = comparison.deltas.iter().map(|delta| &delta.name).collect();
+        assert_eq!(names, ["frame", "collision"]);
+        // 元が 0 の場合は変化なしとする
+        assert_eq!(comparison.deltas[1].change, 0.0);
+        assert!(!comparison.has_regression());
+
+        let text = comparison.to_string();
+        assert!(text.contains("movement"), "{}", text);
+        assert!(text.contains("(only in one result)"), "{}", text);
+    }
+
+    #[test]
+    fn result_to_csv() {
+        let result = BenchResult {
+            scenario: "missiles".to_owned(),
+            count: 100,
+            warmup: 5,
+            metadata: BenchMetadata {
+                timestamp: 1700000000,
+                git_revision: Some("abc1234".to_owned()),
+                ..Default::default()
+            },
+            report: report(
+                Stats::from_samples(&[1.0, 3.0]).unwrap(),
+                &[("movement", Stats::from_samples(&[0.5]).unwrap())],
+            ),
+        };
+        assert_eq!(
+            result.to_csv(),
+            "scenario,count,frames,git_revision,timestamp,name,min,mean,p50,p95,p99,max\n\
+             missiles,100,10,abc1234,1700000000,frame,1,2,1,3,3,3\n\
+             missiles,100,10,abc1234,1700000000,movement,0.5,0.5,0.5,0.5,0.5,0.5\n"
+        );
+
+        // リビジョンが無い場合は空
+        let result = BenchResult {
+            metadata: BenchMetadata::default(),
+            ..result
+        };
+        assert!(result.to_csv().contains("missiles,100,10,,0,frame,"));
+    }
+
+    #[test]
+    fn result_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("bench-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let result = BenchResult {
+            scenario: "colliders".to_owned(),
+            count: 10,
+            metadata: BenchMetadata {
+                compute_threads: 4,
+                ..Default::default()
+            },
+            report: report(stats(1.0, 2.0), &[("collision", stats(0.5, 1.0))]),
+            ..Default::default()
+        };
+
+        let json = dir.join("result.json");
+        result.save(&json).unwrap();
+        let loaded = BenchResult::load(&json).unwrap();
+        assert_eq!(loaded.to_csv(), result.to_csv());
+        assert_eq!(loaded.metadata, result.metadata);
+
+        let csv = dir.join("result.csv");
+        result.save(&csv).unwrap();
+        assert_eq!(fs::read_to_string(&csv).unwrap(), result.to_csv());
+        // CSV は読み込めない
+        assert!(BenchResult::load(&csv).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+//
+// マウスカーソルとタッチ位置をワールド座標として入手
+//
+// マウスとタッチのどちらの入力も同じ `MousePos` に反映されるので、
+// 視線追従などのシステムは入力デバイスを意識する必要がありません。
+//
+
+use bevy::input::touch::Touches;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+///
+/// カーソル・タッチ位置のワールド座標変換を行うプラグイン
+///
+pub struct CursorPlugin;
+
+impl Plugin for CursorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MousePos(Vec2::ZERO))
+            .init_resource::<TouchPositions>()
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                SystemSet::new()
+                    .after(InputSystem)
+                    .with_system(touch_tracking_system.label(CursorSystem::Touch))
+                    .with_system(
+                        cursor_system
+                            .label(CursorSystem::Cursor)
+                            .after(CursorSystem::Touch),
+                    ),
+            );
+    }
+}
+
+///
+/// `CursorPlugin` が追加するシステムのラベル
+///
+/// タッチを占有するシステム（仮想ジョイスティックなど）は `Touch` の後、
+/// `Cursor` の前に実行してください。
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CursorSystem {
+    /// 指ごとのタッチ位置を更新する
+    Touch,
+    /// `MousePos` を更新する
+    Cursor,
+}
+
+///
+/// ワールド座標の計算に使うカメラ
+///
+#[derive(Component)]
+pub struct MainCamera;
+
+///
+/// カーソル（またはタッチ）のワールド座標
+///
+pub struct MousePos(pub Vec2);
+
+///
+/// 指ひとつ分のタッチ情報
+///
+#[derive(Debug, Clone, Copy)]
+pub struct TrackedTouch {
+    /// ウィンドウ座標
+    pub screen: Vec2,
+    /// ワールド座標
+    pub world: Vec2,
+    /// タッチを開始したウィンドウ座標
+    pub start_screen: Vec2,
+    /// タッチを開始した時刻（起動からの秒数）
+    pub start_time: f64,
+    /// ジョイスティックなどに占有されている場合は `true`
+    pub claimed: bool,
+    /// このフレームで指が離れた場合は `true`
+    pub released: bool,
+}
+
+///
+/// 指ID毎のタッチ位置
+///
+/// 離された指はそのフレームの間だけ `released` として残ります。
+///
+#[derive(Debug, Default)]
+pub struct TouchPositions {
+    touches: HashMap<u64, TrackedTouch>,
+    latest: Option<u64>,
+}
+
+impl TouchPositions {
+    pub fn get(&self, id: u64) -> Option<&TrackedTouch> {
+        self.touches.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &TrackedTouch)> + '_ {
+        self.touches.iter().map(|(id, touch)| (*id, touch))
+    }
+
+    /// 他のシステムが使用中のタッチとして登録する
+    pub fn claim(&mut self, id: u64) {
+        if let Some(touch) = self.touches.get_mut(&id) {
+            touch.claimed = true;
+        }
+    }
+
+    /// 占有されていないタッチのうち、最後に触れたもの
+    pub fn pointer(&self) -> Option<(u64, &TrackedTouch)> {
+        let latest = self.latest.and_then(|id| {
+            self.touches
+                .get(&id)
+                .filter(|touch| !touch.claimed)
+                .map(|touch| (id, touch))
+        });
+        // 最後に触れた指が占有されている場合は、他の指から選ぶ
+        latest.or_else(|| {
+            self.iter()
+                .filter(|(_, touch)| !touch.claimed)
+                .max_by(|(_, a), (_, b)| a.start_time.total_cmp(&b.start_time))
+        })
+    }
+}
+
+///
+/// ウィンドウ座標をワールド座標に変換する
+///
+pub fn screen_to_world(
+    screen_pos: Vec2,
+    window_size: Vec2,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Vec2 {
+    // [0..window_size]（画面位置） -> [-1..1]（gpu座標）に変換します
+    let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
+
+    // 投影とカメラ変換を元に戻すための行列
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+
+    // ワールド座標に変換して Vec3 -> Vec2に変換
+    ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
+}
+
+fn touch_tracking_system(
+    wnds: Res<Windows>,
+    time: Res<Time>,
+    touches: Res<Touches>,
+    mut positions: ResMut<TouchPositions>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let to_world = |screen: Vec2| -> Vec2 {
+        match (camera_query.get_single(), wnds.get_primary()) {
+            (Ok((camera, camera_transform)), Some(wnd)) => {
+                let window_size = Vec2::new(wnd.width(), wnd.height());
+                screen_to_world(screen, window_size, camera, camera_transform)
+            }
+            _ => screen,
+        }
+    };
+
+    // 前のフレームで離された指を削除
+    positions.touches.retain(|_, touch| !touch.released);
+
+    for touch in touches.iter_just_pressed() {
+        positions.touches.insert(
+            touch.id(),
+            TrackedTouch {
+                screen: touch.position(),
+                world: to_world(touch.position()),
+                start_screen: touch.start_position(),
+                start_time: time.seconds_since_startup(),
+                claimed: false,
+                released: false,
+            },
+        );
+        positions.latest = Some(touch.id());
+    }
+
+    for touch in touches.iter() {
+        if let Some(tracked) = positions.touches.get_mut(&touch.id()) {
+            tracked.screen = touch.position();
+            tracked.world = to_world(touch.position());
+        }
+    }
+
+    for touch in touches
+        .iter_just_released()
+        .chain(touches.iter_just_cancelled())
+    {
+        if let Some(tracked) = positions.touches.get_mut(&touch.id()) {
+            tracked.screen = touch.position();
+            tracked.world = to_world(touch.position());
+            tracked.released = true;
+        }
+    }
+
+    if let Some(latest) = positions.latest {
+        if !positions.touches.contains_key(&latest) {
+            positions.latest = None;
+        }
+    }
+}
+
+fn cursor_system(
+    wnds: Res<Windows>,
+    positions: Res<TouchPositions>,
+    mut mouse_pos: ResMut<MousePos>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    // タッチ中はタッチ位置を優先する
+    if let Some((_, touch)) = positions.pointer() {
+        mouse_pos.0 = touch.world;
+        return;
+    }
+
+    if let Ok((camera, camera_transform)) = camera_query.get_single() {
+        if let Some(wnd) = wnds.get_primary() {
+            // カメラがウィンドウ内にあるか確認して、その位置を取得する
+            if let Some(screen_pos) = wnd.cursor_position() {
+                // ウィンドウサイズの取得
+                let window_size = Vec2::new(wnd.width(), wnd.height());
+
+                mouse_pos.0 = screen_to_world(screen_pos, window_size, camera, camera_transform);
+            }
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//
+// ゲームの診断情報
+//
+// エンティティ数・アーキタイプ数・1フレームで実行した固定タイムステップの回数、
+// `ExplosionEvent` から数えた1秒あたりの爆発数、`CollisionEvent` から数えた1フレームの衝突数と、
+// アプリが登録したコンポーネントの数やフレームごとの回数を `Diagnostics` に記録します。
+// 記録した値は `LogDiagnosticsPlugin` や診断オーバーレイで表示できます。
+//
+// 追跡ミサイルの数と旋回角度は `TrackingPlugin` が登録します。
+//
+// ```
+// app.add_plugin(GameDiagnosticsPlugin)
+//     .add_component_count_diagnostic::<Bomb>(diagnostic_id("bombs"), "bombs")
+//     .add_counter_diagnostic(COLLISION_PAIRS, "collision_pairs");
+//
+// fn collision(mut counters: ResMut<FrameCounters>) {
+//     counters.add(COLLISION_PAIRS, 1.0);
+// }
+// ```
+//
+
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    ecs::archetype::Archetypes,
+    prelude::*,
+    utils::{AHasher, HashMap},
+};
+
+use crate::pause::gameplay_timestep;
+
+///
+/// 診断情報を記録するプラグイン
+///
+pub struct GameDiagnosticsPlugin;
+
+impl GameDiagnosticsPlugin {
+    pub const ENTITY_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5d1c_7e2a_9b34_4f60_8a1e_3c7d_2b9f_6e01);
+    pub const ARCHETYPE_COUNT: DiagnosticId =
+        DiagnosticId::from_u128(0x5d1c_7e2a_9b34_4f60_8a1e_3c7d_2b9f_6e02);
+    /// 1フレームで実行した固定タイムステップの回数（遅れを取り戻すと 2 以上になる）
+    pub const FIXED_STEPS: DiagnosticId =
+        DiagnosticId::from_u128(0x5d1c_7e2a_9b34_4f60_8a1e_3c7d_2b9f_6e03);
+    /// 直近1秒間の `ExplosionEvent` の数
+    pub const EXPLOSIONS: DiagnosticId =
+        DiagnosticId::from_u128(0x5d1c_7e2a_9b34_4f60_8a1e_3c7d_2b9f_6e04);
+    /// 1フレームの `CollisionEvent` の数
+    pub const COLLISIONS: DiagnosticId =
+        DiagnosticId::from_u128(0x5d1c_7e2a_9b34_4f60_8a1e_3c7d_2b9f_6e05);
+}
+
+impl Plugin for GameDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_diagnostic(Diagnostic::new(Self::ENTITY_COUNT, "entity_count", 20))
+            .add_diagnostic(Diagnostic::new(
+                Self::ARCHETYPE_COUNT,
+                "archetype_count",
+                20,
+            ))
+            .add_diagnostic(Diagnostic::new(Self::EXPLOSIONS, "explosions", 20).with_suffix("/s"))
+            .add_counter_diagnostic(Self::FIXED_STEPS, "fixed_steps")
+            .add_counter_diagnostic(Self::COLLISIONS, "collisions")
+            .add_event::<ExplosionEvent>()
+            .add_event::<CollisionEvent>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(gameplay_timestep)
+                    .with_system(fixed_step_counter_system),
+            )
+            .add_system_to_stage(CoreStage::Last, world_diagnostic_system)
+            .add_system_to_stage(CoreStage::Last, explosion_rate_system)
+            .add_system_to_stage(
+                CoreStage::Last,
+                collision_count_system.before(frame_counter_system),
+            );
+    }
+}
+
+///
+/// 爆発が起きたことを知らせるイベント
+///
+#[derive(Debug, Clone, Copy)]
+pub struct ExplosionEvent {
+    pub position: Vec2,
+}
+
+///
+/// 2つのエンティティが衝突したことを知らせるイベント
+///
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+///
+/// 直近 `window` 秒間に起きた回数
+///
+#[derive(Debug, Clone)]
+pub struct EventRate {
+    window: f64,
+    times: VecDeque<f64>,
+}
+
+impl Default for EventRate {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl EventRate {
+    pub fn new(window: f64) -> Self {
+        Self {
+            window,
+            times: VecDeque::new(),
+        }
+    }
+
+    /// `now` 秒に `count` 回起きたことを記録する
+    pub fn record(&mut self, now: f64, count: usize) {
+        self.times.extend(std::iter::repeat_n(now, count));
+    }
+
+    /// `now` 秒までの `window` 秒間に起きた回数
+    pub fn count(&mut self, now: f64) -> usize {
+        while matches!(self.times.front(), Some(&time) if time <= now - self.window) {
+            self.times.pop_front();
+        }
+        self.times.len()
+    }
+}
+
+///
+/// 名前から決まる `DiagnosticId`
+///
+/// アプリ側で定数の ID を用意しなくても診断情報を登録できます。
+///
+pub fn diagnostic_id(name: &str) -> DiagnosticId {
+    let hash = |seed: u64| {
+        let mut hasher = AHasher::default();
+        seed.hash(&mut hasher);
+        name.hash(&mut hasher);
+        hasher.finish() as u128
+    };
+    DiagnosticId::from_u128(hash(0) << 64 | hash(1))
+}
+
+///
+/// フレームごとに数える値
+///
+/// `add` した値はフレームの最後に `Diagnostics` に記録され、0 に戻ります。
+///
+#[derive(Debug, Default)]
+pub struct FrameCounters {
+    counts: HashMap<DiagnosticId, f64>,
+}
+
+impl FrameCounters {
+    pub fn add(&mut self, id: DiagnosticId, value: f64) {
+        *self.counts.entry(id).or_default() += value;
+    }
+
+    /// 今のフレームで数えた値
+    pub fn get(&self, id: DiagnosticId) -> f64 {
+        self.counts.get(&id).copied().unwrap_or_default()
+    }
+}
+
+///
+/// 診断情報を登録する
+///
+pub trait DiagnosticsAppExt {
+    /// `Diagnostics` に診断情報を追加する
+    fn add_diagnostic(&mut self, diagnostic: Diagnostic) -> &mut Self;
+
+    /// `FrameCounters` で数える、フレームごとの回数を登録する
+    fn add_counter_diagnostic(&mut self, id: DiagnosticId, name: &'static str) -> &mut Self;
+
+    /// コンポーネント `C` を持つエンティティの数を登録する
+    fn add_component_count_diagnostic<C: Component>(
+        &mut self,
+        id: DiagnosticId,
+        name: &'static str,
+    ) -> &mut Self;
+}
+
+impl DiagnosticsAppExt for App {
+    fn add_diagnostic(&mut self, diagnostic: Diagnostic) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(Diagnostics::default)
+            .add(diagnostic);
+        self
+    }
+
+    fn add_counter_diagnostic(&mut self, id: DiagnosticId, name: &'static str) -> &mut Self {
+        // 同じ ID を何度登録しても記録するシステムは1つだけ
+        let first = !self.world.contains_resource::<FrameCounters>();
+        self.world
+            .get_resource_or_insert_with(FrameCounters::default)
+            .counts
+            .insert(id, 0.0);
+        if first {
+            self.add_system_to_stage(CoreStage::Last, frame_counter_system);
+        }
+        self.add_diagnostic(Diagnostic::new(id, name, 20))
+    }
+
+    fn add_component_count_diagnostic<C: Component>(
+        &mut self,
+        id: DiagnosticId,
+        name: &'static str,
+    ) -> &mut Self {
+        let count = move |mut diagnostics: ResMut<Diagnostics>, query: Query<(), With<C>>| {
+            diagnostics.add_measurement(id, query.iter().count() as f64);
+        };
+        self.add_diagnostic(Diagnostic::new(id, name, 20))
+            .add_system_to_stage(CoreStage::Last, count)
+    }
+}
+
+fn frame_counter_system(mut counters: ResMut<FrameCounters>, mut diagnostics: ResMut<Diagnostics>) {
+    for (id, count) in counters.counts.iter_mut() {
+        diagnostics.add_measurement(*id, *count);
+        *count = 0.0;
+    }
+}
+
+fn explosion_rate_system(
+    time: Res<Time>,
+    mut explosions: EventReader<ExplosionEvent>,
+    mut rate: Local<EventRate>,
+    mut diagnostics: ResMut<Diagnostics>,
+) {
+    let now = time.seconds_since_startup();
+    rate.record(now, explosions.iter().count());
+    diagnostics.add_measurement(GameDiagnosticsPlugin::EXPLOSIONS, rate.count(now) as f64);
+}
+
+fn collision_count_system(
+    mut collisions: EventReader<CollisionEvent>,
+    mut counters: ResMut<FrameCounters>,
+) {
+    counters.add(
+        GameDiagnosticsPlugin::COLLISIONS,
+        collisions.iter().count() as f64,
+    );
+}
+
+fn fixed_step_counter_system(mut counters: ResMut<FrameCounters>) {
+    counters.add(GameDiagnosticsPlugin::FIXED_STEPS, 1.0);
+}
+
+fn world_diagnostic_system(archetypes: &Archetypes, mut diagnostics: ResMut<Diagnostics>) {
+    let entities: usize = archetypes.iter().map(|archetype| archetype.len()).sum();
+    diagnostics.add_measurement(GameDiagnosticsPlugin::ENTITY_COUNT, entities as f64);
+    diagnostics.add_measurement(
+        GameDiagnosticsPlugin::ARCHETYPE_COUNT,
+        archetypes.len() as f64,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+
+    use super::*;
+    use crate::pause::{GameTime, Pause};
+    use crate::TIME_STEP;
+
+    #[derive(Component)]
+    struct Marker;
+
+    const MARKERS: &str = "markers";
+    const SHOTS: &str = "shots";
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Pause>()
+            .init_resource::<GameTime>()
+            .add_plugin(GameDiagnosticsPlugin)
+            .add_component_count_diagnostic::<Marker>(diagnostic_id(MARKERS), MARKERS)
+            .add_counter_diagnostic(diagnostic_id(SHOTS), SHOTS);
+        app
+    }
+
+    /// ゲーム内時間を `delta` 秒進めて1フレーム実行する
+    fn tick(app: &mut App, delta: f32) {
+        app.world.resource_mut::<GameTime>().advance(delta, false);
+        app.update();
+    }
+
+    fn value(app: &App, id: DiagnosticId) -> Option<f64> {
+        app.world.resource::<Diagnostics>().get(id)?.value()
+    }
+
+    #[test]
+    fn records_component_counts() {
+        let mut app = app();
+        for _ in 0..3 {
+            app.world.spawn().insert(Marker);
+        }
+        tick(&mut app, 0.0);
+        assert_eq!(value(&app, diagnostic_id(MARKERS)), Some(3.0));
+        assert!(value(&app, GameDiagnosticsPlugin::ENTITY_COUNT).unwrap() >= 3.0);
+
+        let marker = app
+            .world
+            .query_filtered::<Entity, With<Marker>>()
+            .iter(&app.world)
+            .next()
+            .unwrap();
+        app.world.despawn(marker);
+        tick(&mut app, 0.0);
+        assert_eq!(value(&app, diagnostic_id(MARKERS)), Some(2.0));
+    }
+
+    #[test]
+    fn counters_reset_every_frame() {
+        let mut app = app();
+        app.world
+            .resource_mut::<FrameCounters>()
+            .add(diagnostic_id(SHOTS), 2.0);
+        for _ in 0..2 {
+            app.world
+                .resource_mut::<Events<CollisionEvent>>()
+                .send(CollisionEvent {
+                    a: Entity::from_raw(0),
+                    b: Entity::from_raw(1),
+                });
+        }
+        // 固定タイムステップ 2 回分
+        tick(&mut app, 2.5 * TIME_STEP);
+        assert_eq!(value(&app, diagnostic_id(SHOTS)), Some(2.0));
+        assert_eq!(value(&app, GameDiagnosticsPlugin::COLLISIONS), Some(2.0));
+        assert_eq!(value(&app, GameDiagnosticsPlugin::FIXED_STEPS), Some(2.0));
+
+        tick(&mut app, 0.0);
+        assert_eq!(value(&app, diagnostic_id(SHOTS)), Some(0.0));
+        assert_eq!(value(&app, GameDiagnosticsPlugin::COLLISIONS), Some(0.0));
+        assert_eq!(value(&app, GameDiagnosticsPlugin::FIXED_STEPS), Some(0.0));
+    }
+}
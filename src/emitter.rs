@@ -0,0 +1,670 @@
+//
+// 弾幕（バレットヘル）のパターン発射
+//
+// パターンは `*.emitter.ron` ファイルに記述して AssetServer から読み込みます。
+// 角度は度数法で、0° が +Y 方向、正の値が反時計回りです。
+//
+// ```ron
+// (
+//     steps: [
+//         (pattern: Radial(count: 24, speed: 150.0), duration: 2.0, interval: 0.5),
+//         (pattern: Spiral(arms: 3, speed: 120.0, step: 12.0), duration: 4.0, interval: 0.1),
+//     ],
+// )
+// ```
+//
+
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::cursor::MousePos;
+use crate::look_at::{look_angle, target_position, wrap_angle, LookTarget};
+use crate::pause::gameplay_timestep;
+use crate::TIME_STEP;
+
+/// 弾が消える画面外の距離
+const BULLET_BOUNDS: f32 = 2000.0;
+
+///
+/// 弾幕の発射と弾の移動を行うプラグイン
+///
+pub struct EmitterPlugin {
+    /// 同時に存在できる弾の最大数
+    pub max_bullets: usize,
+}
+
+impl Default for EmitterPlugin {
+    fn default() -> Self {
+        Self { max_bullets: 2000 }
+    }
+}
+
+impl Plugin for EmitterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EmitterScript>()
+            .init_asset_loader::<EmitterScriptLoader>()
+            .insert_resource(BulletPool::new(self.max_bullets))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(gameplay_timestep)
+                    .with_system(emitter_system.label(EmitterSystem))
+                    .with_system(enemy_bullet_system.after(EmitterSystem)),
+            );
+    }
+}
+
+///
+/// `emitter_system` のラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmitterSystem;
+
+///
+/// 発射パターン
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum Pattern {
+    /// 全方位に等間隔で撃つ
+    Radial {
+        count: u32,
+        speed: f32,
+        #[serde(default)]
+        offset: f32,
+    },
+    /// 発射の度に `step` だけ角度をずらして撃つ
+    Spiral {
+        arms: u32,
+        speed: f32,
+        #[serde(default)]
+        start: f32,
+        step: f32,
+    },
+    /// ターゲットに向けて扇状に撃つ
+    Aimed { count: u32, spread: f32, speed: f32 },
+    /// 正弦波で揺れながら進む弾を撃つ
+    Wave {
+        direction: f32,
+        speed: f32,
+        amplitude: f32,
+        frequency: f32,
+    },
+}
+
+///
+/// 1発分の弾の情報
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    /// 発射角 (rad)
+    pub angle: f32,
+    pub speed: f32,
+    /// 揺れ幅と周波数
+    pub wave: Option<(f32, f32)>,
+}
+
+impl Pattern {
+    ///
+    /// `shot_index` 回目の発射で撃つ弾
+    ///
+    /// `aim` はターゲットへの角度 (rad) です。
+    ///
+    pub fn shots(&self, shot_index: u32, aim: f32) -> Vec<Shot> {
+        match *self {
+            Pattern::Radial {
+                count,
+                speed,
+                offset,
+            } => ring(count, offset.to_radians())
+                .map(|angle| Shot {
+                    angle,
+                    speed,
+                    wave: None,
+                })
+                .collect(),
+            Pattern::Spiral {
+                arms,
+                speed,
+                start,
+                step,
+            } => {
+                let base = (start + step * shot_index as f32).to_radians();
+                ring(arms, base)
+                    .map(|angle| Shot {
+                        angle,
+                        speed,
+                        wave: None,
+                    })
+                    .collect()
+            }
+            Pattern::Aimed {
+                count,
+                spread,
+                speed,
+            } => {
+                let count = count.max(1);
+                let spread = spread.to_radians();
+                (0..count)
+                    .map(|i| {
+                        let offset = if count == 1 {
+                            0.0
+                        } else {
+                            -spread / 2.0 + spread * i as f32 / (count - 1) as f32
+                        };
+                        Shot {
+                            angle: wrap_angle(aim + offset),
+                            speed,
+                            wave: None,
+                        }
+                    })
+                    .collect()
+            }
+            Pattern::Wave {
+                direction,
+                speed,
+                amplitude,
+                frequency,
+            } => vec![Shot {
+                angle: wrap_angle(direction.to_radians()),
+                speed,
+                wave: Some((amplitude, frequency)),
+            }],
+        }
+    }
+}
+
+/// `base` から `count` 等分した角度
+fn ring(count: u32, base: f32) -> impl Iterator<Item = f32> {
+    let count = count.max(1);
+    (0..count).map(move |i| wrap_angle(base + TAU * i as f32 / count as f32))
+}
+
+///
+/// パターンを撃つ時間と間隔
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PatternStep {
+    pub pattern: Pattern,
+    /// このパターンを続ける時間（秒）
+    pub duration: f32,
+    /// 発射間隔（秒）
+    pub interval: f32,
+}
+
+///
+/// 弾幕の台本（パターンの並び）
+///
+#[derive(Debug, Clone, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "5b7c8f3e-2d4a-4c1e-9f6b-8a3d2e1c0b9a"]
+pub struct EmitterScript {
+    pub steps: Vec<PatternStep>,
+    /// 最後まで撃ったら最初に戻る
+    #[serde(default = "default_repeat")]
+    pub repeat: bool,
+}
+
+fn default_repeat() -> bool {
+    true
+}
+
+#[derive(Default)]
+pub struct EmitterScriptLoader;
+
+impl AssetLoader for EmitterScriptLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let script: EmitterScript = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(script));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["emitter.ron"]
+    }
+}
+
+///
+/// 弾幕を発射するコンポーネント
+///
+#[derive(Component, Debug, Clone)]
+pub struct Emitter {
+    pub script: Handle<EmitterScript>,
+    /// `Aimed` パターンのターゲット
+    pub target: LookTarget,
+    pub bullet_texture: Handle<Image>,
+    pub bullet_scale: Vec3,
+    /// 弾が消えるまでの時間（秒）
+    pub bullet_lifetime: f32,
+    step: usize,
+    step_time: f32,
+    fire_timer: f32,
+    shot_index: u32,
+}
+
+impl Emitter {
+    pub fn new(script: Handle<EmitterScript>, bullet_texture: Handle<Image>) -> Self {
+        Self {
+            script,
+            target: LookTarget::Cursor,
+            bullet_texture,
+            bullet_scale: Vec3::ONE,
+            bullet_lifetime: 10.0,
+            step: 0,
+            step_time: 0.0,
+            fire_timer: 0.0,
+            shot_index: 0,
+        }
+    }
+
+    pub fn with_target(mut self, target: LookTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_bullet_scale(mut self, bullet_scale: Vec3) -> Self {
+        self.bullet_scale = bullet_scale;
+        self
+    }
+
+    /// 現在のパターンの番号
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    ///
+    /// 時間を `delta` 秒進めて、このステップで撃つ弾を返す
+    ///
+    /// 時間が進まない場合（一時停止中など）は何も撃ちません。
+    ///
+    pub fn tick(&mut self, script: &EmitterScript, delta: f32, aim: f32) -> Vec<Shot> {
+        let mut shots = Vec::new();
+
+        let step = match script.steps.get(self.step) {
+            Some(step) => step,
+            None => return shots,
+        };
+        // 間隔も delta も 0 だとタイマーが進まず抜けられない
+        if delta <= 0.0 {
+            return shots;
+        }
+
+        while self.fire_timer <= 0.0 {
+            shots.extend(step.pattern.shots(self.shot_index, aim));
+            self.shot_index += 1;
+            // 間隔が 0 の場合は1ステップに1回だけ撃つ
+            self.fire_timer += step.interval.max(delta);
+        }
+        self.fire_timer -= delta;
+
+        self.step_time += delta;
+        if self.step_time >= step.duration {
+            self.step += 1;
+            if self.step >= script.steps.len() && script.repeat {
+                self.step = 0;
+            }
+            self.step_time = 0.0;
+            self.fire_timer = 0.0;
+            self.shot_index = 0;
+        }
+
+        shots
+    }
+}
+
+///
+/// 弾幕の弾
+///
+#[derive(Component, Debug, Clone)]
+pub struct EnemyBullet {
+    pub origin: Vec2,
+    /// 進行方向（単位ベクトル）
+    pub direction: Vec2,
+    pub speed: f32,
+    pub wave: Option<(f32, f32)>,
+    pub age: f32,
+    pub lifetime: f32,
+    active: bool,
+}
+
+impl EnemyBullet {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// 発射からの時間に対応する位置
+    pub fn position(&self) -> Vec2 {
+        let mut position = self.origin + self.direction * self.speed * self.age;
+        if let Some((amplitude, frequency)) = self.wave {
+            let side = self.direction.perp();
+            position += side * amplitude * (TAU * frequency * self.age).sin();
+        }
+        position
+    }
+}
+
+///
+/// 弾のエンティティを使い回すためのプール
+///
+/// 使われていない弾（`is_active` が `false`）のエンティティを再利用します。
+/// 弾の数は毎回数え直すので、他のシステムで弾を削除しても構いません。
+///
+#[derive(Debug)]
+pub struct BulletPool {
+    pub capacity: usize,
+    active: usize,
+}
+
+impl BulletPool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            active: 0,
+        }
+    }
+
+    /// 画面上に存在している弾の数
+    pub fn active(&self) -> usize {
+        self.active
+    }
+}
+
+fn emitter_system(
+    mut commands: Commands,
+    scripts: Res<Assets<EmitterScript>>,
+    mouse_pos: Option<Res<MousePos>>,
+    mut pool: ResMut<BulletPool>,
+    mut query: Query<(&mut Emitter, &GlobalTransform)>,
+    targets: Query<&GlobalTransform>,
+    bullets: Query<(Entity, &EnemyBullet)>,
+) {
+    let mut free = Vec::new();
+    pool.active = 0;
+    for (entity, bullet) in bullets.iter() {
+        if bullet.active {
+            pool.active += 1;
+        } else {
+            free.push(entity);
+        }
+    }
+
+    for (mut emitter, global_tf) in query.iter_mut() {
+        let script = match scripts.get(&emitter.script) {
+            Some(script) => script,
+            None => continue,
+        };
+
+        let origin = global_tf.translation.truncate();
+        let aim = target_position(emitter.target, mouse_pos.as_deref(), &targets)
+            .map_or(std::f32::consts::PI, |target| {
+                look_angle(origin, target, Vec2::Y)
+            });
+
+        for shot in emitter.tick(script, TIME_STEP, aim) {
+            // 上限を超える弾は撃たない
+            if pool.active >= pool.capacity {
+                break;
+            }
+
+            let direction = Vec2::new(-shot.angle.sin(), shot.angle.cos());
+            let bullet = EnemyBullet {
+                origin,
+                direction,
+                speed: shot.speed,
+                wave: shot.wave,
+                age: 0.0,
+                lifetime: emitter.bullet_lifetime,
+                active: true,
+            };
+            let sprite = SpriteBundle {
+                transform: Transform {
+                    translation: origin.extend(global_tf.translation.z + 1.0),
+                    rotation: Quat::from_rotation_z(shot.angle),
+                    scale: emitter.bullet_scale,
+                },
+                texture: emitter.bullet_texture.clone(),
+                ..Default::default()
+            };
+
+            match free.pop() {
+                Some(entity) => {
+                    commands.entity(entity).insert_bundle(sprite).insert(bullet);
+                }
+                None => {
+                    commands.spawn_bundle(sprite).insert(bullet);
+                }
+            }
+            pool.active += 1;
+        }
+    }
+}
+
+fn enemy_bullet_system(
+    mut pool: ResMut<BulletPool>,
+    mut query: Query<(&mut Transform, &mut Visibility, &mut EnemyBullet)>,
+) {
+    for (mut tf, mut visibility, mut bullet) in query.iter_mut() {
+        if !bullet.active {
+            continue;
+        }
+
+        bullet.age += TIME_STEP;
+        let position = bullet.position();
+        tf.translation.x = position.x;
+        tf.translation.y = position.y;
+
+        // 寿命か画面外でプールに戻す
+        if bullet.age >= bullet.lifetime || position.length() > BULLET_BOUNDS {
+            bullet.active = false;
+            visibility.is_visible = false;
+            pool.active = pool.active.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetPlugin;
+
+    use super::*;
+    use crate::pause::GameTime;
+
+    fn spiral_script(interval: f32) -> EmitterScript {
+        EmitterScript {
+            steps: vec![PatternStep {
+                pattern: Pattern::Spiral {
+                    arms: 2,
+                    speed: 100.0,
+                    start: 0.0,
+                    step: 15.0,
+                },
+                duration: 10.0,
+                interval,
+            }],
+            repeat: true,
+        }
+    }
+
+    fn degrees(shots: &[Shot]) -> Vec<f32> {
+        shots
+            .iter()
+            .map(|shot| (shot.angle.to_degrees() * 1000.0).round() / 1000.0)
+            .collect()
+    }
+
+    #[test]
+    fn spiral_angles_after_ticks() {
+        let script = spiral_script(0.25);
+        let mut emitter = Emitter::new(Handle::default(), Handle::default());
+
+        // 0.125 秒ずつ 6回進めると 0.25 秒ごとに 3回撃つ
+        let shots_per_tick: Vec<Vec<Shot>> =
+            (0..6).map(|_| emitter.tick(&script, 0.125, 0.0)).collect();
+        let counts: Vec<usize> = shots_per_tick.iter().map(Vec::len).collect();
+        assert_eq!(counts, vec![2, 0, 2, 0, 2, 0]);
+
+        let shots: Vec<Shot> = shots_per_tick.into_iter().flatten().collect();
+        assert_eq!(
+            degrees(&shots),
+            vec![0.0, 180.0, 15.0, -165.0, 30.0, -150.0]
+        );
+    }
+
+    #[test]
+    fn zero_interval_fires_once_per_tick() {
+        let script = spiral_script(0.0);
+        let mut emitter = Emitter::new(Handle::default(), Handle::default());
+
+        let shots: Vec<Shot> = (0..3)
+            .flat_map(|_| emitter.tick(&script, 0.125, 0.0))
+            .collect();
+        assert_eq!(
+            degrees(&shots),
+            vec![0.0, 180.0, 15.0, -165.0, 30.0, -150.0]
+        );
+    }
+
+    #[test]
+    fn zero_delta_does_not_fire() {
+        let script = spiral_script(0.0);
+        let mut emitter = Emitter::new(Handle::default(), Handle::default());
+
+        assert!(emitter.tick(&script, 0.0, 0.0).is_empty());
+        assert!(emitter.tick(&script, -0.125, 0.0).is_empty());
+        assert_eq!(emitter.step(), 0);
+        // 再開すると最初の弾から撃つ
+        assert_eq!(
+            degrees(&emitter.tick(&script, 0.125, 0.0)),
+            vec![0.0, 180.0]
+        );
+    }
+
+    #[test]
+    fn moves_to_next_step_after_duration() {
+        let script = EmitterScript {
+            steps: vec![
+                PatternStep {
+                    pattern: Pattern::Radial {
+                        count: 4,
+                        speed: 100.0,
+                        offset: 0.0,
+                    },
+                    duration: 0.25,
+                    interval: 1.0,
+                },
+                spiral_script(1.0).steps.remove(0),
+            ],
+            repeat: false,
+        };
+        let mut emitter = Emitter::new(Handle::default(), Handle::default());
+
+        let counts: Vec<usize> = (0..4)
+            .map(|_| emitter.tick(&script, 0.125, 0.0).len())
+            .collect();
+        assert_eq!(counts, vec![4, 0, 2, 0]);
+        assert_eq!(emitter.step(), 1);
+    }
+
+    fn app(max_bullets: usize) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .init_resource::<GameTime>()
+            .add_plugin(EmitterPlugin { max_bullets });
+        app
+    }
+
+    /// ゲーム内時間を1ステップ分進めて1フレーム実行する
+    fn tick(app: &mut App) {
+        app.world
+            .resource_mut::<GameTime>()
+            .advance(TIME_STEP, false);
+        app.update();
+    }
+
+    fn spawn_emitter(app: &mut App, pattern: Pattern) -> Entity {
+        let script = app
+            .world
+            .resource_mut::<Assets<EmitterScript>>()
+            .add(EmitterScript {
+                steps: vec![PatternStep {
+                    pattern,
+                    duration: 100.0,
+                    interval: 1.0,
+                }],
+                repeat: true,
+            });
+        app.world
+            .spawn()
+            .insert(Emitter::new(script, Handle::default()))
+            .insert(GlobalTransform::identity())
+            .id()
+    }
+
+    fn active_bullets(app: &mut App) -> Vec<Entity> {
+        app.world
+            .query::<(Entity, &EnemyBullet)>()
+            .iter(&app.world)
+            .filter(|(_, bullet)| bullet.is_active())
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    #[test]
+    fn aims_at_cursor_by_default() {
+        let mut app = app(10);
+        app.insert_resource(MousePos(Vec2::new(100.0, 0.0)));
+        spawn_emitter(
+            &mut app,
+            Pattern::Aimed {
+                count: 1,
+                spread: 0.0,
+                speed: 100.0,
+            },
+        );
+        tick(&mut app);
+
+        let bullets = active_bullets(&mut app);
+        assert_eq!(bullets.len(), 1);
+        let bullet = app.world.get::<EnemyBullet>(bullets[0]).unwrap();
+        assert!(bullet.direction.distance(Vec2::X) < 1e-5);
+    }
+
+    #[test]
+    fn caps_bullets_and_recounts_after_external_despawn() {
+        let mut app = app(4);
+        spawn_emitter(
+            &mut app,
+            Pattern::Radial {
+                count: 6,
+                speed: 100.0,
+                offset: 0.0,
+            },
+        );
+        tick(&mut app);
+        let bullets = active_bullets(&mut app);
+        assert_eq!(bullets.len(), 4);
+        assert_eq!(app.world.resource::<BulletPool>().active(), 4);
+
+        // プールを通さずに削除された弾の分も撃てる
+        for entity in bullets {
+            app.world.despawn(entity);
+        }
+        // 発射間隔は 1 秒
+        for _ in 0..90 {
+            tick(&mut app);
+        }
+        assert_eq!(active_bullets(&mut app).len(), 4);
+        assert_eq!(app.world.resource::<BulletPool>().active(), 4);
+    }
+}
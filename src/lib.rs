@@ -0,0 +1,22 @@
+pub mod bench;
+pub mod cursor;
+pub mod diagnostics;
+pub mod emitter;
+pub mod loading;
+pub mod localization;
+pub mod look_at;
+pub mod pause;
+pub mod run_criteria;
+pub mod scenario;
+pub mod settings;
+pub mod state_scoped;
+pub mod state_stack;
+pub mod system_trace;
+pub mod touch;
+pub mod tracking;
+pub mod turret;
+pub mod ui;
+pub mod weapon;
+
+/// ゲームプレイを更新する固定タイムステップ（秒）
+pub const TIME_STEP: f32 = 1.0 / 60.0;
@@ -0,0 +1,379 @@
+//
+// 読み込み画面
+//
+// 各プラグインが `LoadingAssets` に登録したアセットが全て読み込まれるまで
+// 進捗バーを表示し、読み込みが終わってから次の状態に移ります。
+// 読み込みに失敗したアセットはパスを報告してから先に進みます
+// （`with_stop_on_failure(true)` の場合は止まります）。
+//
+// ```
+// app.add_state(AppState::Loading)
+//     .add_plugin(LoadingPlugin::new(AppState::Loading, AppState::Playing))
+//     .add_startup_system(register);
+//
+// fn register(mut loading: ResMut<LoadingAssets>, asset_server: Res<AssetServer>) {
+//     let ship: Handle<Image> = loading.load(&asset_server, "textures/ship_a.png");
+// }
+// ```
+//
+
+use bevy::{
+    asset::{Asset, LoadState},
+    ecs::schedule::StateData,
+    prelude::*,
+};
+
+use crate::ui::{transition::Transition, DEFAULT_FONT};
+
+///
+/// 読み込み画面を表示するプラグイン
+///
+/// `state` の間に読み込みを待ち、全て読み込まれると `next` に移ります。
+/// `TransitionPlugin` を追加している場合は切り替え効果を使って移ります。
+///
+pub struct LoadingPlugin<S> {
+    pub state: S,
+    pub next: S,
+    /// 読み込みに失敗したアセットを表示するフォント
+    pub font: String,
+    /// 読み込みに失敗したアセットがある場合は次の状態に移らない
+    pub stop_on_failure: bool,
+}
+
+impl<S: StateData> LoadingPlugin<S> {
+    pub fn new(state: S, next: S) -> Self {
+        Self {
+            state,
+            next,
+            font: DEFAULT_FONT.to_owned(),
+            stop_on_failure: false,
+        }
+    }
+
+    pub fn with_font(mut self, font: impl Into<String>) -> Self {
+        self.font = font.into();
+        self
+    }
+
+    pub fn with_stop_on_failure(mut self, stop_on_failure: bool) -> Self {
+        self.stop_on_failure = stop_on_failure;
+        self
+    }
+}
+
+impl<S: StateData> Plugin for LoadingPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingAssets>()
+            .insert_resource(LoadingConfig {
+                next: self.next.clone(),
+                font: self.font.clone(),
+                stop_on_failure: self.stop_on_failure,
+            })
+            .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(loading_setup::<S>))
+            .add_system_set(
+                SystemSet::on_update(self.state.clone()).with_system(loading_system::<S>),
+            )
+            .add_system_set(SystemSet::on_exit(self.state.clone()).with_system(loading_cleanup));
+    }
+}
+
+///
+/// 読み込みを待つアセット
+///
+/// プラグインの `build` やスタートアップシステムで登録します。
+/// 登録したアセットはハンドルを保持するので、読み込み後に解放されることはありません。
+///
+#[derive(Default)]
+pub struct LoadingAssets {
+    assets: Vec<(String, HandleUntyped)>,
+    /// 既に報告した失敗の数
+    reported: usize,
+}
+
+impl LoadingAssets {
+    ///
+    /// アセットを読み込み、読み込みを待つ対象に加える
+    ///
+    pub fn load<T: Asset>(&mut self, asset_server: &AssetServer, path: &str) -> Handle<T> {
+        let handle = asset_server.load(path);
+        self.add(path, handle.clone_untyped());
+        handle
+    }
+
+    ///
+    /// 読み込み中のハンドルを待つ対象に加える
+    ///
+    pub fn add(&mut self, path: impl Into<String>, handle: HandleUntyped) {
+        self.assets.push((path.into(), handle));
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// 現在の読み込みの進み具合
+    pub fn progress(&self, asset_server: &AssetServer) -> LoadingProgress {
+        LoadingProgress::new(
+            self.assets
+                .iter()
+                .map(|(path, handle)| (path.as_str(), asset_server.get_load_state(handle))),
+        )
+    }
+}
+
+///
+/// 読み込みの進み具合
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadingProgress {
+    pub total: usize,
+    pub loaded: usize,
+    /// 読み込みに失敗したアセットのパス
+    pub failed: Vec<String>,
+}
+
+impl LoadingProgress {
+    pub fn new<'a>(states: impl IntoIterator<Item = (&'a str, LoadState)>) -> Self {
+        let mut progress = Self::default();
+        for (path, state) in states {
+            progress.total += 1;
+            match state {
+                LoadState::Loaded => progress.loaded += 1,
+                LoadState::Failed => progress.failed.push(path.to_owned()),
+                _ => {}
+            }
+        }
+        progress
+    }
+
+    /// 読み込まれた割合 (0.0 ~ 1.0)
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded as f32 / self.total as f32
+        }
+    }
+
+    /// 全て読み込まれたか
+    pub fn is_complete(&self) -> bool {
+        self.loaded == self.total
+    }
+
+    /// 失敗したものも含めて、全ての読み込みが終わったか
+    pub fn is_settled(&self) -> bool {
+        self.loaded + self.failed.len() == self.total
+    }
+}
+
+struct LoadingConfig<S> {
+    next: S,
+    font: String,
+    stop_on_failure: bool,
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingErrors;
+
+fn loading_setup<S: StateData>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<LoadingConfig<S>>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::BLACK.into(),
+            ..Default::default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            // 進捗バーの枠
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(60.0), Val::Px(16.0)),
+                        ..Default::default()
+                    },
+                    color: Color::DARK_GRAY.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..Default::default()
+                            },
+                            color: Color::WHITE.into(),
+                            ..Default::default()
+                        })
+                        .insert(LoadingBar);
+                });
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(20.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(config.font.as_str()),
+                            font_size: 20.0,
+                            color: Color::CRIMSON,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(LoadingErrors);
+        });
+}
+
+fn loading_cleanup(mut commands: Commands, screens: Query<Entity, With<LoadingScreen>>) {
+    for entity in screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn loading_system<S: StateData>(
+    asset_server: Res<AssetServer>,
+    config: Res<LoadingConfig<S>>,
+    mut loading: ResMut<LoadingAssets>,
+    mut state: ResMut<State<S>>,
+    transition: Option<ResMut<Transition<S>>>,
+    mut bars: Query<&mut Style, With<LoadingBar>>,
+    mut errors: Query<&mut Text, With<LoadingErrors>>,
+) {
+    let progress = loading.progress(&asset_server);
+
+    for mut style in bars.iter_mut() {
+        style.size.width = Val::Percent(progress.fraction() * 100.0);
+    }
+
+    // 失敗したアセットは読み込み直さないので、パスを表示して知らせる
+    if progress.failed.len() > loading.reported {
+        for path in &progress.failed[loading.reported..] {
+            error!("failed to load asset {:?}", path);
+        }
+        loading.reported = progress.failed.len();
+
+        for mut text in errors.iter_mut() {
+            text.sections[0].value = format!("failed to load:\n{}", progress.failed.join("\n"));
+        }
+    }
+
+    let finished = if config.stop_on_failure {
+        progress.is_complete()
+    } else {
+        progress.is_settled()
+    };
+    if finished {
+        match transition {
+            Some(mut transition) => {
+                transition.request(config.next.clone());
+            }
+            None => {
+                // 既に遷移中の場合は何もしない
+                let _ = state.set(config.next.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::asset::AssetPlugin;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum TestState {
+        Loading,
+        Playing,
+    }
+
+    #[test]
+    fn progress_counts_failures_as_settled() {
+        let progress =
+            LoadingProgress::new([("a.png", LoadState::Loaded), ("b.png", LoadState::Failed)]);
+        assert!(!progress.is_complete());
+        assert!(progress.is_settled());
+        assert_eq!(progress.fraction(), 0.5);
+
+        let progress =
+            LoadingProgress::new([("a.png", LoadState::Loaded), ("b.png", LoadState::Loading)]);
+        assert!(!progress.is_settled());
+    }
+
+    /// 読み込めないアセットを登録して、読み込みが終わるまでフレームを進める
+    fn run_with_missing_asset(plugin: LoadingPlugin<TestState>) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_state(TestState::Loading)
+            .add_plugin(plugin);
+
+        let asset_server = app.world.resource::<AssetServer>().clone();
+        let mut loading = app.world.resource_mut::<LoadingAssets>();
+        let _: Handle<Image> = loading.load(&asset_server, "textures/missing.png");
+
+        for _ in 0..200 {
+            app.update();
+            let progress = app
+                .world
+                .resource::<LoadingAssets>()
+                .progress(&asset_server);
+            if progress.is_settled() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        // 状態の切り替えは次のフレームで行われる
+        app.update();
+        app
+    }
+
+    #[test]
+    fn failed_asset_is_reported_and_skipped() {
+        let app =
+            run_with_missing_asset(LoadingPlugin::new(TestState::Loading, TestState::Playing));
+        assert_eq!(app.world.resource::<LoadingAssets>().reported, 1);
+        assert_eq!(
+            app.world.resource::<State<TestState>>().current(),
+            &TestState::Playing
+        );
+    }
+
+    #[test]
+    fn stop_on_failure_stays_in_loading() {
+        let app = run_with_missing_asset(
+            LoadingPlugin::new(TestState::Loading, TestState::Playing).with_stop_on_failure(true),
+        );
+        assert_eq!(app.world.resource::<LoadingAssets>().reported, 1);
+        assert_eq!(
+            app.world.resource::<State<TestState>>().current(),
+            &TestState::Loading
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//
+// UIの文字列の多言語対応
+//
+// 言語ごとの文字列表 `assets/locales/<言語コード>.locale.ron` を読み込み、
+// `LocalizedText` を付けた `Text` を現在の言語で表示します。
+//
+// ```ron
+// {
+//     "menu.play": "ゲーム開始",
+//     "game.status": "{name} を追うミサイル: {count} 発",
+//     // 英語などは count の値で文字列を選べる
+//     "game.missiles": (one: "{count} missile", other: "{count} missiles"),
+// }
+// ```
+//
+// 見つからないキーは言語の代替（`en-US` → `en` → `fallback`）を順にたどり、
+// どこにも無い場合はキーをそのまま表示して警告を1回だけ出します。
+//
+
+use std::sync::Mutex;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    ui::UiSystem,
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+use serde::Deserialize;
+
+use crate::loading::LoadingAssets;
+
+///
+/// 文字列表の読み込みと `LocalizedText` の更新を行うプラグイン
+///
+/// 文字列表を AssetServer で読み込むので `DefaultPlugins` の後に追加してください。
+///
+pub struct LocalizationPlugin {
+    /// 読み込む言語コード
+    pub locales: Vec<String>,
+    /// 最初に使う言語
+    pub locale: String,
+    /// キーが見つからない時に使う言語
+    pub fallback: String,
+}
+
+impl LocalizationPlugin {
+    pub fn new(locales: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let locales: Vec<String> = locales.into_iter().map(Into::into).collect();
+        let locale = locales.first().cloned().unwrap_or_default();
+        Self {
+            fallback: locale.clone(),
+            locale,
+            locales,
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: impl Into<String>) -> Self {
+        self.fallback = fallback.into();
+        self
+    }
+}
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<StringTable>()
+            .init_asset_loader::<StringTableLoader>();
+
+        let asset_server = app.world.get_resource::<AssetServer>().unwrap().clone();
+        // 読み込み画面で文字列表の読み込みを待つ
+        let mut loading = app
+            .world
+            .get_resource_or_insert_with(LoadingAssets::default);
+        let tables = self
+            .locales
+            .iter()
+            .map(|locale| {
+                let path = format!("locales/{}.locale.ron", locale);
+                (locale.clone(), loading.load(&asset_server, &path))
+            })
+            .collect();
+
+        let mut localization = Localization {
+            locale: String::new(),
+            fallback: self.fallback.clone(),
+            tables,
+            resolved: Vec::new(),
+            ready: false,
+            dirty: true,
+            missing: Mutex::default(),
+        };
+        localization.set_locale(&self.locale);
+
+        app.insert_resource(localization)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                resolve_tables_system.label(LocalizationSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                localized_text_system.before(UiSystem::Flex),
+            );
+    }
+}
+
+///
+/// 文字列表を切り替えるシステムのラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocalizationSystem;
+
+///
+/// 文字列表の項目
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Entry {
+    Text(String),
+    /// `count` 引数の値で選ぶ文字列
+    Plural {
+        #[serde(default)]
+        zero: Option<String>,
+        #[serde(default)]
+        one: Option<String>,
+        other: String,
+    },
+}
+
+///
+/// 1つの言語の文字列表
+///
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "9a4e2c71-3b8d-4f05-a6e1-7d2c5b9f8e13"]
+#[serde(transparent)]
+pub struct StringTable(pub HashMap<String, Entry>);
+
+#[derive(Default)]
+pub struct StringTableLoader;
+
+impl AssetLoader for StringTableLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let table: StringTable = ron::de::from_bytes(bytes).map_err(|e| {
+                anyhow::anyhow!(
+                    "invalid string table {}: {}",
+                    load_context.path().display(),
+                    e
+                )
+            })?;
+            load_context.set_default_asset(LoadedAsset::new(table));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale.ron"]
+    }
+}
+
+///
+/// 複数形の種類
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Other,
+}
+
+///
+/// `locale` で数 `n` がどの複数形になるか
+///
+/// 日本語・中国語・韓国語は単複の区別をしません。
+///
+pub fn plural_category(locale: &str, n: f64) -> PluralCategory {
+    let language = locale.split(['-', '_']).next().unwrap_or(locale);
+    if n == 0.0 {
+        return PluralCategory::Zero;
+    }
+    match language {
+        "ja" | "zh" | "ko" => PluralCategory::Other,
+        _ if n.abs() == 1.0 => PluralCategory::One,
+        _ => PluralCategory::Other,
+    }
+}
+
+impl Entry {
+    fn select(&self, locale: &str, args: &[(String, String)]) -> &str {
+        match self {
+            Entry::Text(text) => text,
+            Entry::Plural { zero, one, other } => {
+                let count = args
+                    .iter()
+                    .find(|(name, _)| name == "count")
+                    .and_then(|(_, value)| value.parse::<f64>().ok());
+
+                let selected = match count.map(|count| plural_category(locale, count)) {
+                    Some(PluralCategory::Zero) => zero.as_ref(),
+                    Some(PluralCategory::One) => one.as_ref(),
+                    _ => None,
+                };
+                selected.unwrap_or(other)
+            }
+        }
+    }
+}
+
+///
+/// `{name}` を引数の値に置き換える
+///
+/// 文字列を先頭から1回だけ読むので、引数の値に含まれる `{name}` は置き換えません。
+/// 引数に無い名前はそのまま残します。
+///
+fn substitute(text: &str, args: &[(String, String)]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = match after.find(['{', '}']) {
+            Some(end) if after[end..].starts_with('}') => end,
+            // 閉じていない `{` はそのまま
+            _ => {
+                result.push('{');
+                rest = after;
+                continue;
+            }
+        };
+
+        let name = &after[..end];
+        match args.iter().find(|(arg, _)| arg == name) {
+            Some((_, value)) => result.push_str(value),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+///
+/// 現在の言語と文字列表
+///
+pub struct Localization {
+    locale: String,
+    fallback: String,
+    tables: HashMap<String, Handle<StringTable>>,
+    /// 代替をたどる順の (言語コード, 文字列表)
+    resolved: Vec<(String, StringTable)>,
+    ready: bool,
+    dirty: bool,
+    missing: Mutex<HashSet<(String, String)>>,
+}
+
+impl Localization {
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        let locale = locale.into();
+        if self.locale != locale {
+            self.locale = locale;
+            self.dirty = true;
+        }
+    }
+
+    ///
+    /// キーを探す言語の順番
+    ///
+    /// `en-US` の場合は `en-US`, `en`, 代替の言語の順です。
+    ///
+    pub fn chain(&self) -> Vec<String> {
+        let mut chain = vec![self.locale.clone()];
+        let mut locale = self.locale.as_str();
+        while let Some(index) = locale.rfind(['-', '_']) {
+            locale = &locale[..index];
+            chain.push(locale.to_owned());
+        }
+        if !chain.contains(&self.fallback) {
+            chain.push(self.fallback.clone());
+        }
+        chain
+    }
+
+    /// 全ての文字列表が読み込まれたか
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// これまでに見つからなかった (言語, キー)
+    pub fn missing_keys(&self) -> Vec<(String, String)> {
+        self.missing.lock().unwrap().iter().cloned().collect()
+    }
+
+    ///
+    /// キーに対応する文字列
+    ///
+    pub fn text(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    ///
+    /// キーに対応する文字列に引数を埋め込む
+    ///
+    /// `count` 引数は複数形の選択にも使います。
+    ///
+    pub fn format(&self, key: &str, args: &[(String, String)]) -> String {
+        let found = self
+            .resolved
+            .iter()
+            .find_map(|(locale, table)| table.0.get(key).map(|entry| (locale, entry)));
+
+        match found {
+            Some((locale, entry)) => substitute(entry.select(locale, args), args),
+            None => {
+                // 読み込み中は報告しない
+                if self.ready {
+                    let mut missing = self.missing.lock().unwrap();
+                    if missing.insert((self.locale.clone(), key.to_owned())) {
+                        warn!("missing localization key {:?} for {:?}", key, self.locale);
+                    }
+                }
+                substitute(key, args)
+            }
+        }
+    }
+}
+
+///
+/// 現在の言語で表示するテキスト
+///
+/// 同じエンティティの `Text` の最初のセクションを書き換えます。
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct LocalizedText(pub String);
+
+///
+/// `LocalizedText` に埋め込む引数
+///
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocaleArgs(pub Vec<(String, String)>);
+
+impl LocaleArgs {
+    pub fn with(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl ToString) {
+        let name = name.into();
+        let value = value.to_string();
+        match self.0.iter_mut().find(|(arg, _)| *arg == name) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((name, value)),
+        }
+    }
+}
+
+///
+/// 言語の変更や文字列表の読み込みに合わせて、参照する文字列表を作り直す
+///
+fn resolve_tables_system(
+    mut localization: ResMut<Localization>,
+    mut events: EventReader<AssetEvent<StringTable>>,
+    asset_server: Res<AssetServer>,
+    tables: Res<Assets<StringTable>>,
+) {
+    // 読み込みが終わるまでは毎フレーム確認する
+    let table_changed = events.iter().count() > 0;
+    if !localization.dirty && !table_changed && localization.ready {
+        return;
+    }
+
+    let chain = localization.chain();
+    let mut resolved = Vec::new();
+    let mut ready = true;
+    for locale in chain {
+        let handle = match localization.tables.get(&locale) {
+            Some(handle) => handle,
+            None => continue,
+        };
+        match tables.get(handle) {
+            Some(table) => resolved.push((locale, table.clone())),
+            None => {
+                // 読み込みに失敗した言語は飛ばす
+                if asset_server.get_load_state(handle) != LoadState::Failed {
+                    ready = false;
+                }
+            }
+        }
+    }
+
+    localization.resolved = resolved;
+    localization.ready = ready;
+    localization.dirty = false;
+}
+
+#[allow(clippy::type_complexity)]
+fn localized_text_system(
+    localization: Res<Localization>,
+    mut query: Query<(
+        &LocalizedText,
+        ChangeTrackers<LocalizedText>,
+        Option<&LocaleArgs>,
+        Option<ChangeTrackers<LocaleArgs>>,
+        &mut Text,
+    )>,
+) {
+    let all = localization.is_changed();
+
+    for (key, key_tracker, args, args_tracker, mut text) in query.iter_mut() {
+        let args_changed = matches!(args_tracker, Some(tracker) if tracker.is_changed());
+        if !all && !key_tracker.is_changed() && !args_changed {
+            continue;
+        }
+
+        let args = args.map_or(&[][..], |args| args.0.as_slice());
+        let value = localization.format(&key.0, args);
+        if let Some(section) = text.sections.first_mut() {
+            if section.value != value {
+                section.value = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(ron: &str) -> StringTable {
+        ron::de::from_str(ron).unwrap()
+    }
+
+    /// 読み込み済みの文字列表を持つ `Localization`
+    fn loaded(locale: &str, fallback: &str, tables: &[(&str, &str)]) -> Localization {
+        let mut localization = Localization {
+            locale: locale.to_owned(),
+            fallback: fallback.to_owned(),
+            tables: HashMap::default(),
+            resolved: Vec::new(),
+            ready: true,
+            dirty: false,
+            missing: Mutex::default(),
+        };
+        let tables: HashMap<_, _> = tables
+            .iter()
+            .map(|(locale, ron)| (locale.to_string(), table(ron)))
+            .collect();
+        localization.resolved = localization
+            .chain()
+            .into_iter()
+            .filter_map(|locale| tables.get(&locale).map(|table| (locale, table.clone())))
+            .collect();
+        localization
+    }
+
+    fn args(args: &[(&str, &str)]) -> Vec<(String, String)> {
+        args.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitute_arguments() {
+        let values = args(&[("name", "Alice"), ("count", "3")]);
+        assert_eq!(substitute("{name}: {count}", &values), "Alice: 3");
+        assert_eq!(substitute("{count}{count}", &values), "33");
+        // 引数に無い名前や閉じていない括弧はそのまま
+        assert_eq!(substitute("{other} {count", &values), "{other} {count");
+        assert_eq!(substitute("{{count}}", &values), "{3}");
+        assert_eq!(substitute("}{", &values), "}{");
+    }
+
+    #[test]
+    fn substitute_does_not_expand_argument_values() {
+        // プレイヤー名などに `{count}` が含まれていても置き換えない
+        let values = args(&[("name", "{count}"), ("count", "3")]);
+        assert_eq!(substitute("{name} x{count}", &values), "{count} x3");
+        let values = args(&[("count", "{name}"), ("name", "Bob")]);
+        assert_eq!(substitute("{name} {count}", &values), "Bob {name}");
+    }
+
+    #[test]
+    fn plural_categories() {
+        assert_eq!(plural_category("en", 0.0), PluralCategory::Zero);
+        assert_eq!(plural_category("en-US", 1.0), PluralCategory::One);
+        assert_eq!(plural_category("en", -1.0), PluralCategory::One);
+        assert_eq!(plural_category("en", 2.0), PluralCategory::Other);
+        assert_eq!(plural_category("en", 1.5), PluralCategory::Other);
+        assert_eq!(plural_category("ja", 1.0), PluralCategory::Other);
+        assert_eq!(plural_category("zh_TW", 1.0), PluralCategory::Other);
+    }
+
+    #[test]
+    fn format_selects_plural() {
+        let localization = loaded(
+            "en",
+            "en",
+            &[(
+                "en",
+                r#"{"missiles": (zero: "no missiles", one: "{count} missile", other: "{count} missiles")}"#,
+            )],
+        );
+        let format = |count: &str| localization.format("missiles", &args(&[("count", count)]));
+        assert_eq!(format("0"), "no missiles");
+        assert_eq!(format("1"), "1 missile");
+        assert_eq!(format("2"), "2 missiles");
+        // 数でない場合や count が無い場合は other
+        assert_eq!(format("many"), "many missiles");
+        assert_eq!(localization.text("missiles"), "{count} missiles");
+    }
+
+    #[test]
+    fn plural_without_zero_uses_other() {
+        let localization = loaded(
+            "ja",
+            "ja",
+            &[("ja", r#"{"missiles": (other: "ミサイル {count} 発")}"#)],
+        );
+        let format = |count: &str| localization.format("missiles", &args(&[("count", count)]));
+        assert_eq!(format("0"), "ミサイル 0 発");
+        assert_eq!(format("1"), "ミサイル 1 発");
+    }
+
+    #[test]
+    fn chain_of_regional_locale() {
+        let localization = loaded("en-US", "ja", &[]);
+        assert_eq!(localization.chain(), ["en-US", "en", "ja"]);
+        let localization = loaded("zh_Hant_TW", "en", &[]);
+        assert_eq!(localization.chain(), ["zh_Hant_TW", "zh_Hant", "zh", "en"]);
+        // 代替の言語は重複させない
+        let localization = loaded("en-GB", "en", &[]);
+        assert_eq!(localization.chain(), ["en-GB", "en"]);
+    }
+
+    #[test]
+    fn format_follows_fallback_chain() {
+        let localization = loaded(
+            "en-US",
+            "ja",
+            &[
+                ("en-US", r#"{"color": "color"}"#),
+                ("en", r#"{"color": "colour", "play": "Play"}"#),
+                ("ja", r#"{"color": "色", "play": "開始", "quit": "終了"}"#),
+            ],
+        );
+        assert_eq!(localization.text("color"), "color");
+        assert_eq!(localization.text("play"), "Play");
+        assert_eq!(localization.text("quit"), "終了");
+        assert!(localization.missing_keys().is_empty());
+    }
+
+    #[test]
+    fn missing_key_is_shown_and_reported_once() {
+        let localization = loaded("en", "en", &[("en", r#"{"play": "Play"}"#)]);
+        assert_eq!(
+            localization.format("menu.{count}", &args(&[("count", "2")])),
+            "menu.2"
+        );
+        assert_eq!(localization.text("menu.quit"), "menu.quit");
+        assert_eq!(localization.text("menu.quit"), "menu.quit");
+
+        let mut missing = localization.missing_keys();
+        missing.sort();
+        assert_eq!(
+            missing,
+            [
+                ("en".to_owned(), "menu.quit".to_owned()),
+                ("en".to_owned(), "menu.{count}".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_key_is_not_reported_while_loading() {
+        let mut localization = loaded("en", "en", &[]);
+        localization.ready = false;
+        assert_eq!(localization.text("menu.play"), "menu.play");
+        assert!(localization.missing_keys().is_empty());
+    }
+}
@@ -0,0 +1,397 @@
+//
+// 2Dスプライトをターゲットの方向に向ける
+//
+// 回転は常にZ軸周りで行うので、`Transform::look_at` のようにスプライトが
+// 奥行き方向に傾いて見えなくなることはありません。
+//
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::cursor::MousePos;
+use crate::pause::{game_delta, GameTime};
+
+///
+/// `LookAt2d` を更新するプラグイン
+///
+pub struct LookAtPlugin;
+
+impl Plugin for LookAtPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(look_at_2d_system.label(LookAtSystem));
+    }
+}
+
+///
+/// `look_at_2d_system` のラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LookAtSystem;
+
+///
+/// 向く先
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LookTarget {
+    /// エンティティのワールド座標
+    Entity(Entity),
+    /// ワールド座標
+    Point(Vec2),
+    /// カーソル（`MousePos`）
+    Cursor,
+}
+
+///
+/// Z軸周りの回転でターゲットの方向を向くコンポーネント
+///
+/// `arc` は親エンティティに対する回転角の範囲で、-π ~ π の範囲で指定します。
+/// `min` から反時計回りに `max` までが範囲なので、`min > max` の場合は ±π をまたぐ範囲になります。
+///
+#[derive(Component, Debug, Clone)]
+pub struct LookAt2d {
+    pub target: LookTarget,
+    /// スプライトの前方向（ローカル座標）
+    pub forward: Vec2,
+    /// 最大角速度 (rad/s)、`None` の場合は制限なし
+    pub max_angular_speed: Option<f32>,
+    /// 追従の速さ (1/s)、`None` の場合は補間なし
+    pub easing: Option<f32>,
+    /// 親に対する回転可能範囲 (min, max)
+    pub arc: Option<(f32, f32)>,
+}
+
+impl LookAt2d {
+    pub fn new(target: LookTarget) -> Self {
+        Self {
+            target,
+            forward: Vec2::Y,
+            max_angular_speed: None,
+            easing: None,
+            arc: None,
+        }
+    }
+
+    pub fn with_forward(mut self, forward: Vec2) -> Self {
+        self.forward = forward;
+        self
+    }
+
+    pub fn with_max_angular_speed(mut self, max_angular_speed: f32) -> Self {
+        self.max_angular_speed = Some(max_angular_speed);
+        self
+    }
+
+    pub fn with_easing(mut self, easing: f32) -> Self {
+        self.easing = Some(easing);
+        self
+    }
+
+    ///
+    /// 回転可能範囲を `min` から反時計回りに `max` までにする
+    ///
+    /// 角度は -π ~ π に丸めるので、`with_arc(170°, -170°)` は真後ろを中心とした 20° の範囲です。
+    ///
+    pub fn with_arc(mut self, min: f32, max: f32) -> Self {
+        self.arc = if max - min >= TAU {
+            // 一周以上は制限なしと同じ
+            None
+        } else {
+            Some((wrap_angle(min), wrap_angle(max)))
+        };
+        self
+    }
+}
+
+///
+/// 角度を -π ~ π の範囲に丸める
+///
+pub fn wrap_angle(angle: f32) -> f32 {
+    let wrapped = (angle + PI).rem_euclid(TAU) - PI;
+    // rem_euclid の結果が -π になった場合は π に揃える
+    if wrapped <= -PI {
+        wrapped + TAU
+    } else {
+        wrapped
+    }
+}
+
+///
+/// `forward` を `from` から `to` の方向に向けるためのZ軸回転角
+///
+pub fn look_angle(from: Vec2, to: Vec2, forward: Vec2) -> f32 {
+    let direction = to - from;
+    if direction == Vec2::ZERO || forward == Vec2::ZERO {
+        return 0.0;
+    }
+    wrap_angle(direction.y.atan2(direction.x) - forward.y.atan2(forward.x))
+}
+
+///
+/// 角度を `min` ~ `max` の範囲に収める
+///
+/// 範囲は `min` から反時計回りに `max` までで、`min > max` の場合は ±π をまたぎます。
+/// 範囲外の場合は角度差の小さい方の端に合わせます。
+///
+pub fn clamp_to_arc(angle: f32, min: f32, max: f32) -> f32 {
+    let angle = wrap_angle(angle);
+    if arc_offset(angle, min) <= arc_width(min, max) {
+        return angle;
+    }
+
+    if wrap_angle(angle - min).abs() <= wrap_angle(angle - max).abs() {
+        min
+    } else {
+        max
+    }
+}
+
+///
+/// `min` から反時計回りに `max` までの範囲の幅 (0 ~ 2π)
+///
+fn arc_width(min: f32, max: f32) -> f32 {
+    (max - min).rem_euclid(TAU)
+}
+
+///
+/// `min` から反時計回りに測った `angle` の角度 (0 ~ 2π)
+///
+fn arc_offset(angle: f32, min: f32) -> f32 {
+    (angle - min).rem_euclid(TAU)
+}
+
+///
+/// `current` から `target` へ最大 `max_delta` だけ回転させた角度
+///
+pub fn step_angle(current: f32, target: f32, max_delta: f32) -> f32 {
+    let delta = wrap_angle(target - current);
+    wrap_angle(current + delta.clamp(-max_delta, max_delta))
+}
+
+///
+/// 回転（Z軸のみ）から角度を取り出す
+///
+pub fn z_angle(rotation: Quat) -> f32 {
+    wrap_angle(2.0 * rotation.z.atan2(rotation.w))
+}
+
+///
+/// 現在の角度から次のフレームの角度を計算する
+///
+/// `target` は親に対する角度です。
+///
+pub fn next_angle(look_at: &LookAt2d, current: f32, target: f32, delta_seconds: f32) -> f32 {
+    let current = wrap_angle(current);
+
+    // 可動範囲がある場合は範囲の外側を通らないように回す
+    let mut delta = match look_at.arc {
+        Some((min, max)) => {
+            // 範囲の端からの角度で差を取れば ±π をまたぐ範囲でも外側を通らない
+            let width = arc_width(min, max);
+            let target = arc_offset(clamp_to_arc(target, min, max), min).min(width);
+            let current = arc_offset(clamp_to_arc(current, min, max), min).min(width);
+            target - current
+        }
+        None => wrap_angle(target - current),
+    };
+
+    if let Some(easing) = look_at.easing {
+        delta *= 1.0 - (-easing * delta_seconds).exp();
+    }
+
+    if let Some(max_angular_speed) = look_at.max_angular_speed {
+        let max_delta = max_angular_speed * delta_seconds;
+        delta = delta.clamp(-max_delta, max_delta);
+    }
+
+    wrap_angle(current + delta)
+}
+
+///
+/// ターゲットのワールド座標を求める
+///
+pub fn target_position(
+    target: LookTarget,
+    mouse_pos: Option<&MousePos>,
+    targets: &Query<&GlobalTransform>,
+) -> Option<Vec2> {
+    match target {
+        LookTarget::Entity(entity) => targets
+            .get(entity)
+            .ok()
+            .map(|target_tf| target_tf.translation.truncate()),
+        LookTarget::Point(point) => Some(point),
+        LookTarget::Cursor => mouse_pos.map(|mouse_pos| mouse_pos.0),
+    }
+}
+
+///
+/// 親エンティティのワールド回転角（親がいない場合は 0）
+///
+pub fn parent_angle(parent: Option<&Parent>, targets: &Query<&GlobalTransform>) -> f32 {
+    parent
+        .and_then(|parent| targets.get(parent.0).ok())
+        .map_or(0.0, |parent_tf| z_angle(parent_tf.rotation))
+}
+
+fn look_at_2d_system(
+    time: Res<Time>,
+    game_time: Option<Res<GameTime>>,
+    mouse_pos: Option<Res<MousePos>>,
+    mut query: Query<(&mut Transform, &GlobalTransform, &LookAt2d, Option<&Parent>)>,
+    targets: Query<&GlobalTransform>,
+) {
+    for (mut tf, global_tf, look_at, parent) in query.iter_mut() {
+        let target = match target_position(look_at.target, mouse_pos.as_deref(), &targets) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        // ワールド座標での目標角度を親に対する角度に変換する
+        let world_angle = look_angle(global_tf.translation.truncate(), target, look_at.forward);
+        let local_target = wrap_angle(world_angle - parent_angle(parent, &targets));
+
+        let angle = next_angle(
+            look_at,
+            z_angle(tf.rotation),
+            local_target,
+            game_delta(&time, game_time.as_deref()),
+        );
+        tf.rotation = Quat::from_rotation_z(angle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_angle(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn look_angle_in_all_quadrants() {
+        let cases = [
+            (Vec2::new(1.0, 1.0), PI / 4.0),
+            (Vec2::new(-1.0, 1.0), PI * 3.0 / 4.0),
+            (Vec2::new(-1.0, -1.0), -PI * 3.0 / 4.0),
+            (Vec2::new(1.0, -1.0), -PI / 4.0),
+        ];
+        for (to, expected) in cases {
+            assert_angle(look_angle(Vec2::ZERO, to, Vec2::X), expected);
+        }
+    }
+
+    #[test]
+    fn look_angle_on_axes() {
+        let from = Vec2::new(10.0, -5.0);
+        assert_angle(look_angle(from, from + Vec2::X, Vec2::X), 0.0);
+        assert_angle(look_angle(from, from + Vec2::Y, Vec2::X), FRAC_PI_2);
+        assert_angle(look_angle(from, from - Vec2::Y, Vec2::X), -FRAC_PI_2);
+        assert_angle(look_angle(from, from - Vec2::X, Vec2::X), PI);
+        assert_eq!(look_angle(from, from, Vec2::X), 0.0);
+    }
+
+    #[test]
+    fn look_angle_with_forward_axis() {
+        // スプライトの前方が +Y の場合
+        assert_angle(look_angle(Vec2::ZERO, Vec2::Y, Vec2::Y), 0.0);
+        assert_angle(look_angle(Vec2::ZERO, Vec2::X, Vec2::Y), -FRAC_PI_2);
+        assert_angle(look_angle(Vec2::ZERO, -Vec2::X, Vec2::Y), FRAC_PI_2);
+        assert_angle(look_angle(Vec2::ZERO, -Vec2::Y, Vec2::Y), PI);
+        assert_angle(
+            look_angle(Vec2::ZERO, Vec2::new(-1.0, -1.0), Vec2::Y),
+            PI * 3.0 / 4.0,
+        );
+    }
+
+    #[test]
+    fn wrap_angle_around_pi() {
+        assert_angle(wrap_angle(PI), PI);
+        assert_angle(wrap_angle(-PI), PI);
+        assert_angle(wrap_angle(PI + 0.1), -PI + 0.1);
+        assert_angle(wrap_angle(-PI - 0.1), PI - 0.1);
+        assert_angle(wrap_angle(TAU * 3.0 + 0.5), 0.5);
+        assert_angle(wrap_angle(-TAU * 3.0 - 0.5), -0.5);
+    }
+
+    #[test]
+    fn step_angle_crosses_pi() {
+        // 3.0 から -3.0 へは ±π をまたぐ方が近い
+        assert_angle(step_angle(3.0, -3.0, 0.1), 3.1);
+        assert_angle(step_angle(3.1, -3.0, 0.1), wrap_angle(3.2));
+        assert_angle(step_angle(-3.0, 3.0, 1.0), 3.0);
+    }
+
+    #[test]
+    fn clamp_to_arc_inside_and_outside() {
+        assert_angle(clamp_to_arc(0.5, -1.0, 1.0), 0.5);
+        assert_angle(clamp_to_arc(1.0, -1.0, 1.0), 1.0);
+        assert_angle(clamp_to_arc(1.5, -1.0, 1.0), 1.0);
+        assert_angle(clamp_to_arc(-1.5, -1.0, 1.0), -1.0);
+    }
+
+    #[test]
+    fn clamp_to_arc_wraps_to_nearest_edge() {
+        // 真後ろ付近では角度差の小さい方の端
+        assert_angle(clamp_to_arc(3.0, -1.0, 1.0), 1.0);
+        assert_angle(clamp_to_arc(-3.0, -1.0, 1.0), -1.0);
+        // 範囲外で一周した角度も同じ
+        assert_angle(clamp_to_arc(0.5 + TAU, -1.0, 1.0), 0.5);
+        assert_angle(clamp_to_arc(3.5, -1.0, 1.0), -1.0);
+        // 片側だけの範囲
+        assert_angle(clamp_to_arc(-0.5, 0.0, FRAC_PI_2), 0.0);
+        assert_angle(clamp_to_arc(-2.5, 0.0, FRAC_PI_2), FRAC_PI_2);
+    }
+
+    #[test]
+    fn clamp_to_arc_across_pi() {
+        let (min, max) = (170f32.to_radians(), (-170f32).to_radians());
+        assert_angle(clamp_to_arc(PI, min, max), PI);
+        assert_angle(
+            clamp_to_arc(175f32.to_radians(), min, max),
+            175f32.to_radians(),
+        );
+        assert_angle(
+            clamp_to_arc((-175f32).to_radians(), min, max),
+            (-175f32).to_radians(),
+        );
+        assert_angle(clamp_to_arc(90f32.to_radians(), min, max), min);
+        assert_angle(clamp_to_arc((-90f32).to_radians(), min, max), max);
+    }
+
+    #[test]
+    fn next_angle_with_arc_across_pi() {
+        let look_at = LookAt2d::new(LookTarget::Point(Vec2::ZERO))
+            .with_arc(170f32.to_radians(), (-170f32).to_radians());
+        let (min, max) = look_at.arc.unwrap();
+
+        // 範囲内のターゲットへは ±π をまたいで回る
+        assert_angle(
+            next_angle(&look_at, min, (-175f32).to_radians(), 1.0),
+            (-175f32).to_radians(),
+        );
+        // 範囲外のターゲットは近い方の端で止まる
+        assert_angle(next_angle(&look_at, PI, 2.5, 1.0), min);
+        assert_angle(next_angle(&look_at, PI, -2.5, 1.0), max);
+
+        // 速度制限があっても範囲の内側（±π 側）を回る
+        let look_at = look_at.with_max_angular_speed(5f32.to_radians());
+        assert_angle(next_angle(&look_at, min, max, 1.0), 175f32.to_radians());
+        assert_angle(next_angle(&look_at, max, min, 1.0), (-175f32).to_radians());
+    }
+
+    #[test]
+    fn with_arc_of_full_turn_is_unlimited() {
+        let look_at = LookAt2d::new(LookTarget::Cursor).with_arc(-PI, PI);
+        assert!(look_at.arc.is_none());
+        assert_angle(next_angle(&look_at, 3.0, -3.0, 1.0), -3.0);
+    }
+}
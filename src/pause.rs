@@ -0,0 +1,299 @@
+//
+// 一時停止
+//
+// ゲームプレイの更新は `Time` ではなく `GameTime` と `gameplay_timestep` を使います。
+// 一時停止中は経過時間が 0 になるので、再開した時に止まっていた分の時間が一度に進むことはありません。
+//
+// ```
+// app.add_plugin(PausePlugin::default()).add_system_set(
+//     SystemSet::new()
+//         .with_run_criteria(gameplay_timestep)
+//         .with_system(movement),
+// );
+// ```
+//
+
+use std::time::Duration;
+
+use bevy::{core::CoreSystem, ecs::schedule::ShouldRun, prelude::*};
+
+use crate::TIME_STEP;
+
+///
+/// 一時停止とゲーム内時間を追加するプラグイン
+///
+pub struct PausePlugin {
+    /// 一時停止を切り替えるキー
+    pub key: Option<KeyCode>,
+}
+
+impl Default for PausePlugin {
+    fn default() -> Self {
+        Self {
+            key: Some(KeyCode::P),
+        }
+    }
+}
+
+impl PausePlugin {
+    ///
+    /// キーやボタンで切り替えない（アプリ側で `Pause` を操作する）
+    ///
+    pub fn manual() -> Self {
+        Self { key: None }
+    }
+}
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pause>()
+            .init_resource::<GameTime>()
+            .add_system_to_stage(CoreStage::First, game_time_system.after(CoreSystem::Time));
+
+        if let Some(key) = self.key {
+            app.insert_resource(PauseKey(key))
+                .add_system_to_stage(CoreStage::PreUpdate, pause_input_system);
+        }
+    }
+}
+
+///
+/// 一時停止中か
+///
+#[derive(Debug, Default)]
+pub struct Pause {
+    paused: bool,
+}
+
+impl Pause {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn toggle(&mut self) {
+        self.paused = !self.paused;
+    }
+}
+
+///
+/// 一時停止中は止まるゲーム内の時間
+///
+#[derive(Debug, Default)]
+pub struct GameTime {
+    delta: f32,
+    elapsed: f64,
+}
+
+impl GameTime {
+    /// 前のフレームからの経過時間
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f32(self.delta)
+    }
+
+    /// 前のフレームからの経過時間（秒）
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta
+    }
+
+    /// ゲーム開始からの経過時間（秒）
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// 時間を `delta` 秒進める（一時停止中は進めない）
+    pub fn advance(&mut self, delta: f32, paused: bool) {
+        self.delta = if paused { 0.0 } else { delta };
+        self.elapsed += self.delta as f64;
+    }
+}
+
+///
+/// `GameTime` の経過時間（`PausePlugin` が無い場合は `Time` の経過時間）
+///
+pub fn game_delta(time: &Time, game_time: Option<&GameTime>) -> f32 {
+    game_time.map_or(time.delta_seconds(), GameTime::delta_seconds)
+}
+
+///
+/// 固定タイムステップの時間の蓄積
+///
+#[derive(Debug, Default)]
+pub struct StepClock {
+    accumulator: f32,
+    looping: bool,
+}
+
+impl StepClock {
+    ///
+    /// `delta` 秒進め、`step` 秒ごとに1回実行するかを返す
+    ///
+    /// 1フレームで複数回実行する場合は `YesAndCheckAgain` を返します。
+    ///
+    pub fn update(&mut self, delta: f32, step: f32) -> ShouldRun {
+        if !self.looping {
+            self.accumulator += delta;
+        }
+
+        if self.accumulator >= step {
+            self.accumulator -= step;
+            self.looping = true;
+            ShouldRun::YesAndCheckAgain
+        } else {
+            self.looping = false;
+            ShouldRun::No
+        }
+    }
+
+    ///
+    /// 時間を進めずに今回は実行しない
+    ///
+    /// 実行しない間に経過した時間は後から取り戻しません。
+    ///
+    pub fn skip(&mut self) -> ShouldRun {
+        self.looping = false;
+        ShouldRun::No
+    }
+}
+
+///
+/// ゲームプレイの経過時間（一時停止中は 0）
+///
+pub fn gameplay_delta(time: &Time, pause: Option<&Pause>, game_time: Option<&GameTime>) -> f32 {
+    // 同じフレームで一時停止された場合も進めない
+    if matches!(pause, Some(pause) if pause.is_paused()) {
+        0.0
+    } else {
+        game_delta(time, game_time)
+    }
+}
+
+///
+/// `TIME_STEP` ごとに実行し、一時停止中は実行しないランクライテリア
+///
+/// `FixedTimestep::step(TIME_STEP as f64)` の代わりに使います。
+///
+pub fn gameplay_timestep(
+    time: Res<Time>,
+    pause: Option<Res<Pause>>,
+    game_time: Option<Res<GameTime>>,
+    mut clock: Local<StepClock>,
+) -> ShouldRun {
+    let delta = gameplay_delta(&time, pause.as_deref(), game_time.as_deref());
+    clock.update(delta, TIME_STEP)
+}
+
+struct PauseKey(KeyCode);
+
+fn game_time_system(time: Res<Time>, pause: Res<Pause>, mut game_time: ResMut<GameTime>) {
+    game_time.advance(time.delta_seconds(), pause.is_paused());
+}
+
+fn pause_input_system(
+    key: Res<PauseKey>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut pause: ResMut<Pause>,
+) {
+    let start_pressed = gamepad_input
+        .get_just_pressed()
+        .any(|button| button.1 == GamepadButtonType::Start);
+
+    if keyboard_input.just_pressed(key.0) || start_pressed {
+        pause.toggle();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::look_at::LookTarget;
+    use crate::tracking::{TrackingMissile, TrackingPlugin};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Pause>()
+            .init_resource::<GameTime>()
+            .add_plugin(TrackingPlugin::default());
+        app
+    }
+
+    /// `Time` の代わりにゲーム内時間を `delta` 秒進めて1フレーム実行する
+    fn tick(app: &mut App, delta: f32) {
+        let paused = app.world.resource::<Pause>().is_paused();
+        app.world.resource_mut::<GameTime>().advance(delta, paused);
+        app.update();
+    }
+
+    /// 正面 (+Y) のターゲットに向かって直進するミサイル
+    fn spawn_missile(app: &mut App) -> Entity {
+        app.world
+            .spawn()
+            .insert_bundle(TransformBundle::identity())
+            .insert(TrackingMissile::new(LookTarget::Point(Vec2::Y * 1000.0)))
+            .id()
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec3 {
+        app.world.get::<Transform>(entity).unwrap().translation
+    }
+
+    fn set_paused(app: &mut App, paused: bool) {
+        app.world.resource_mut::<Pause>().set(paused);
+    }
+
+    #[test]
+    fn game_time_stops_while_paused() {
+        let mut game_time = GameTime::default();
+        game_time.advance(0.5, false);
+        game_time.advance(0.25, true);
+        assert_eq!(game_time.delta_seconds(), 0.0);
+        assert_eq!(game_time.seconds_since_startup(), 0.5);
+    }
+
+    #[test]
+    fn missiles_stay_still_while_paused() {
+        let mut app = app();
+        let missile = spawn_missile(&mut app);
+
+        tick(&mut app, 0.125);
+        let before = position(&app, missile);
+        assert!(before.y > 0.0);
+
+        set_paused(&mut app, true);
+        for _ in 0..10 {
+            tick(&mut app, 0.125);
+        }
+        assert_eq!(position(&app, missile), before);
+
+        // 再開しても止まっていた分は進まない
+        set_paused(&mut app, false);
+        tick(&mut app, 0.125);
+        let steps = (0.125 / TIME_STEP).floor();
+        let speed = app.world.get::<TrackingMissile>(missile).unwrap().speed;
+        let moved = position(&app, missile).y - before.y;
+        assert!(
+            moved <= speed * TIME_STEP * (steps + 1.0) + 1e-3,
+            "moved {} after resuming",
+            moved
+        );
+    }
+
+    #[test]
+    fn pause_in_the_same_frame_stops_movement() {
+        let mut app = app();
+        let missile = spawn_missile(&mut app);
+        tick(&mut app, 0.125);
+        let before = position(&app, missile);
+
+        // `GameTime` が進んだ後に一時停止された場合も動かない
+        app.world.resource_mut::<GameTime>().advance(0.125, false);
+        set_paused(&mut app, true);
+        app.update();
+        assert_eq!(position(&app, missile), before);
+    }
+}
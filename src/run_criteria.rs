@@ -0,0 +1,176 @@
+//
+// ランクライテリアの組み合わせ
+//
+// `SystemSet::on_update(state).with_run_criteria(...)` は状態のランクライテリアを置き換えてしまい、
+// 状態に関係無く実行されます。状態と固定タイムステップの両方を満たす時だけ実行するには、
+// `in_state` の結果をタイムステップのランクライテリアにパイプします。
+//
+// ```
+// app.add_system_set(
+//     SystemSet::new()
+//         .with_run_criteria(gameplay_timestep_in(GameState::Game))
+//         .with_system(movement),
+// );
+//
+// // 任意の間隔の場合
+// app.add_system_set(
+//     SystemSet::new()
+//         .with_run_criteria(in_state(GameState::B).chain(and_timestep(1.0)))
+//         .with_system(b_system),
+// );
+// ```
+//
+// 状態が違う間はタイムステップの時間も進まないので、状態に戻った時にまとめて実行されることはありません。
+//
+
+use bevy::{
+    ecs::schedule::{ShouldRun, StateData},
+    prelude::*,
+};
+
+use crate::pause::{gameplay_delta, GameTime, Pause, StepClock};
+use crate::TIME_STEP;
+
+///
+/// 現在の状態が `state` か（スタックの下にある状態は含まない）
+///
+pub fn in_state<S: StateData>(state: S) -> impl System<In = (), Out = bool> {
+    IntoSystem::into_system(move |current: Res<State<S>>| current.current() == &state)
+}
+
+///
+/// パイプされた条件が `true` の間だけ `TIME_STEP` ごとに実行し、一時停止中は実行しない
+///
+pub fn and_gameplay_timestep(
+    In(active): In<bool>,
+    time: Res<Time>,
+    pause: Option<Res<Pause>>,
+    game_time: Option<Res<GameTime>>,
+    mut clock: Local<StepClock>,
+) -> ShouldRun {
+    if !active {
+        return clock.skip();
+    }
+    let delta = gameplay_delta(&time, pause.as_deref(), game_time.as_deref());
+    clock.update(delta, TIME_STEP)
+}
+
+///
+/// パイプされた条件が `true` の間だけ `step` 秒ごとに実行する
+///
+pub fn and_timestep(step: f32) -> impl System<In = bool, Out = ShouldRun> {
+    IntoSystem::into_system(
+        move |In(active): In<bool>, time: Res<Time>, mut clock: Local<StepClock>| {
+            if active {
+                clock.update(time.delta_seconds(), step)
+            } else {
+                clock.skip()
+            }
+        },
+    )
+}
+
+///
+/// `state` の間だけ `TIME_STEP` ごとに実行するランクライテリア
+///
+pub fn gameplay_timestep_in<S: StateData>(state: S) -> impl System<In = (), Out = ShouldRun> {
+    in_state(state).chain(and_gameplay_timestep)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum TestState {
+        A,
+        B,
+    }
+
+    /// `gameplay_timestep_in` で実行した回数
+    #[derive(Default)]
+    struct GameplayRuns(usize);
+
+    /// `and_timestep` で実行した回数
+    #[derive(Default)]
+    struct TimestepRuns(usize);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Pause>()
+            .init_resource::<GameTime>()
+            .init_resource::<GameplayRuns>()
+            .init_resource::<TimestepRuns>()
+            .add_state(TestState::A)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(gameplay_timestep_in(TestState::B))
+                    .with_system(|mut runs: ResMut<GameplayRuns>| runs.0 += 1),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(in_state(TestState::B).chain(and_timestep(0.001)))
+                    .with_system(|mut runs: ResMut<TimestepRuns>| runs.0 += 1),
+            );
+        app
+    }
+
+    /// ゲーム内時間を `delta` 秒進めて1フレーム実行する
+    fn tick(app: &mut App, delta: f32) {
+        app.world.resource_mut::<GameTime>().advance(delta, false);
+        app.update();
+    }
+
+    fn set_state(app: &mut App, state: TestState) {
+        app.world
+            .resource_mut::<State<TestState>>()
+            .set(state)
+            .unwrap();
+        // 状態の切り替えはフレームの中で行われる
+        tick(app, 0.0);
+    }
+
+    fn runs(app: &App) -> (usize, usize) {
+        (
+            app.world.resource::<GameplayRuns>().0,
+            app.world.resource::<TimestepRuns>().0,
+        )
+    }
+
+    #[test]
+    fn b_system_does_not_run_in_state_a() {
+        let mut app = app();
+        for _ in 0..5 {
+            std::thread::sleep(Duration::from_millis(2));
+            tick(&mut app, 0.1);
+        }
+        assert_eq!(runs(&app), (0, 0));
+    }
+
+    #[test]
+    fn b_system_runs_only_while_in_state_b() {
+        let mut app = app();
+        // A の間の時間は B に入った時に取り戻さない
+        tick(&mut app, 1.0);
+        set_state(&mut app, TestState::B);
+        let (gameplay, _) = runs(&app);
+        assert_eq!(gameplay, 0);
+
+        std::thread::sleep(Duration::from_millis(5));
+        tick(&mut app, 2.5 * TIME_STEP);
+        let (gameplay, timestep) = runs(&app);
+        assert_eq!(gameplay, 2);
+        assert!(timestep > 0);
+
+        set_state(&mut app, TestState::A);
+        let before = runs(&app);
+        for _ in 0..5 {
+            std::thread::sleep(Duration::from_millis(2));
+            tick(&mut app, 0.1);
+        }
+        assert_eq!(runs(&app), before);
+    }
+}
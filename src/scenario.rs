@@ -0,0 +1,470 @@
+//
+// 追跡ミサイル・追跡ボムの負荷試験用シナリオ
+//
+// シードから決まる位置・向き・旋回速度で大量のミサイルとボムを配置し、
+// 円を描いて動く複数のターゲットを追わせます。同じシードなら同じ配置になります。
+//
+// シナリオは `*.scenario.ron` ファイルか、コマンドライン引数で指定します。
+// tracking_missile の例はミサイルだけ、tracking_bomb の例はボムだけを配置します。
+//
+// ```ron
+// (
+//     seed: 42,
+//     arena: 600.0,
+//     targets: (count: 4, orbit_radius: (50.0, 250.0), orbit_speed: (0.2, 1.0)),
+//     missiles: (count: 2000, speed: (120.0, 240.0), turn_rate: (30.0, 120.0)),
+//     bombs: (count: 500, speed: (90.0, 180.0), turn_rate: (15.0, 60.0)),
+// )
+// ```
+//
+// ```
+// cargo run --release --example tracking_missile -- --scenario assets/scenarios/stress.scenario.ron
+// cargo run --release --example tracking_bomb -- --seed 7 --targets 8 --bombs 3000
+// ```
+//
+
+use std::f32::consts::TAU;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::pause::gameplay_timestep;
+use crate::TIME_STEP;
+
+///
+/// シナリオのターゲットを動かすプラグイン
+///
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(scenario_target_system),
+        );
+    }
+}
+
+///
+/// 負荷試験のシナリオ
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub seed: u64,
+    /// 配置する範囲（中心からの距離）
+    pub arena: f32,
+    pub targets: TargetGroup,
+    pub missiles: ProjectileGroup,
+    pub bombs: ProjectileGroup,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            arena: 600.0,
+            targets: TargetGroup::default(),
+            missiles: ProjectileGroup {
+                count: 1000,
+                speed: (120.0, 240.0),
+                turn_rate: (30.0, 120.0),
+            },
+            bombs: ProjectileGroup {
+                count: 1000,
+                speed: (90.0, 180.0),
+                turn_rate: (15.0, 60.0),
+            },
+        }
+    }
+}
+
+///
+/// 円を描いて動くターゲットの配置
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TargetGroup {
+    pub count: usize,
+    /// 円の半径の範囲
+    pub orbit_radius: (f32, f32),
+    /// 1秒間の回転数の範囲
+    pub orbit_speed: (f32, f32),
+}
+
+impl Default for TargetGroup {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            orbit_radius: (50.0, 250.0),
+            orbit_speed: (0.2, 1.0),
+        }
+    }
+}
+
+///
+/// ミサイルかボムの配置
+///
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectileGroup {
+    pub count: usize,
+    pub speed: (f32, f32),
+    /// 旋回速度の範囲（度/秒）
+    pub turn_rate: (f32, f32),
+}
+
+///
+/// 生成したターゲット
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetSpawn {
+    pub center: Vec2,
+    pub radius: f32,
+    /// 角速度 (rad/s)
+    pub angular_speed: f32,
+    pub phase: f32,
+}
+
+impl TargetSpawn {
+    /// 開始から `time` 秒後の位置
+    pub fn position(&self, time: f32) -> Vec2 {
+        let angle = self.phase + self.angular_speed * time;
+        self.center + Vec2::new(angle.cos(), angle.sin()) * self.radius
+    }
+}
+
+///
+/// 生成したミサイルかボム
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileSpawn {
+    pub position: Vec2,
+    /// 向き (rad)、0 が +Y 方向
+    pub heading: f32,
+    pub speed: f32,
+    /// 旋回速度 (rad/s)
+    pub turn_rate: f32,
+    /// 追うターゲットの番号
+    pub target: usize,
+}
+
+impl ProjectileSpawn {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: self.position.extend(0.0),
+            rotation: Quat::from_rotation_z(self.heading),
+            ..Default::default()
+        }
+    }
+}
+
+///
+/// シナリオから生成した配置
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioSpawns {
+    pub targets: Vec<TargetSpawn>,
+    pub missiles: Vec<ProjectileSpawn>,
+    pub bombs: Vec<ProjectileSpawn>,
+}
+
+impl ScenarioSpawns {
+    ///
+    /// ターゲットを `sprite` の見た目で生成し、エンティティを番号順に返す
+    ///
+    pub fn spawn_targets(&self, commands: &mut Commands, sprite: SpriteBundle) -> Vec<Entity> {
+        self.targets
+            .iter()
+            .map(|target| {
+                let mut sprite = sprite.clone();
+                sprite.transform.translation = target.position(0.0).extend(0.0);
+                commands
+                    .spawn_bundle(sprite)
+                    .insert(ScenarioTarget(*target))
+                    .id()
+            })
+            .collect()
+    }
+}
+
+///
+/// シナリオのターゲット
+///
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ScenarioTarget(pub TargetSpawn);
+
+impl Scenario {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        ron::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    ///
+    /// コマンドライン引数からシナリオを作る
+    ///
+    /// `--scenario FILE` でファイルを読み込み、`--seed` `--targets` `--missiles` `--bombs`
+    /// で値を上書きします。どれも指定されていない場合は `None` を返します。
+    ///
+    pub fn from_args(args: impl Iterator<Item = String>) -> anyhow::Result<Option<Self>> {
+        let mut scenario = None;
+        let mut overrides = Vec::new();
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for {}", arg))?;
+            match arg.as_str() {
+                "--scenario" => scenario = Some(Self::load(&value)?),
+                "--seed" | "--targets" | "--missiles" | "--bombs" => overrides.push((arg, value)),
+                _ => bail!("unknown argument {}", arg),
+            }
+        }
+
+        if scenario.is_none() && overrides.is_empty() {
+            return Ok(None);
+        }
+
+        let mut scenario = scenario.unwrap_or_default();
+        for (arg, value) in overrides {
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid value for {}: {}", arg, value))
+            };
+            match arg.as_str() {
+                "--seed" => scenario.seed = number()? as u64,
+                "--targets" => scenario.targets.count = number()?,
+                "--missiles" => scenario.missiles.count = number()?,
+                _ => scenario.bombs.count = number()?,
+            }
+        }
+        Ok(Some(scenario))
+    }
+
+    ///
+    /// シードから配置を生成する
+    ///
+    /// ターゲット・ミサイル・ボムは別々の乱数列を使うので、
+    /// ボムの数を変えてもミサイルの配置は変わりません。
+    /// ターゲットが1つも無い場合、ミサイルとボムは生成しません。
+    ///
+    pub fn generate(&self) -> ScenarioSpawns {
+        let rng =
+            |stream: u64| StdRng::seed_from_u64(self.seed.wrapping_mul(3).wrapping_add(stream));
+        let arena = self.arena;
+
+        let mut target_rng = rng(0);
+        let targets: Vec<TargetSpawn> = (0..self.targets.count)
+            .map(|_| {
+                let rng = &mut target_rng;
+                let radius = sample(rng, self.targets.orbit_radius);
+                // 円が範囲に収まるように中心を決める
+                let inner = (arena - radius).max(0.0);
+                TargetSpawn {
+                    center: Vec2::new(sample(rng, (-inner, inner)), sample(rng, (-inner, inner))),
+                    radius,
+                    angular_speed: sample(rng, self.targets.orbit_speed) * TAU,
+                    phase: rng.gen_range(0.0..TAU),
+                }
+            })
+            .collect();
+
+        let projectiles = |group: &ProjectileGroup, mut rng: StdRng| -> Vec<ProjectileSpawn> {
+            if targets.is_empty() {
+                return Vec::new();
+            }
+            (0..group.count)
+                .map(|_| ProjectileSpawn {
+                    position: Vec2::new(
+                        sample(&mut rng, (-arena, arena)),
+                        sample(&mut rng, (-arena, arena)),
+                    ),
+                    heading: rng.gen_range(0.0..TAU),
+                    speed: sample(&mut rng, group.speed),
+                    turn_rate: sample(&mut rng, group.turn_rate).to_radians(),
+                    target: rng.gen_range(0..targets.len()),
+                })
+                .collect()
+        };
+        let missiles = projectiles(&self.missiles, rng(1));
+        let bombs = projectiles(&self.bombs, rng(2));
+
+        ScenarioSpawns {
+            targets,
+            missiles,
+            bombs,
+        }
+    }
+}
+
+/// `(min, max)` の範囲から選ぶ（`min >= max` の場合は `min`）
+fn sample(rng: &mut StdRng, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        rng.gen_range(min..max)
+    } else {
+        min
+    }
+}
+
+fn scenario_target_system(
+    mut elapsed: Local<f32>,
+    mut query: Query<(&mut Transform, &ScenarioTarget)>,
+) {
+    *elapsed += TIME_STEP;
+    for (mut tf, target) in query.iter_mut() {
+        let position = target.0.position(*elapsed);
+        tf.translation.x = position.x;
+        tf.translation.y = position.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(seed: u64) -> Scenario {
+        Scenario {
+            seed,
+            targets: TargetGroup {
+                count: 3,
+                ..Default::default()
+            },
+            missiles: ProjectileGroup {
+                count: 50,
+                ..Scenario::default().missiles
+            },
+            bombs: ProjectileGroup {
+                count: 20,
+                ..Scenario::default().bombs
+            },
+            ..Default::default()
+        }
+    }
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn same_seed_gives_same_spawns() {
+        let spawns = scenario(7).generate();
+        assert_eq!(spawns, scenario(7).generate());
+        assert_eq!(spawns.targets.len(), 3);
+        assert_eq!(spawns.missiles.len(), 50);
+        assert_eq!(spawns.bombs.len(), 20);
+    }
+
+    #[test]
+    fn different_seed_gives_different_spawns() {
+        let a = scenario(7).generate();
+        let b = scenario(8).generate();
+        assert_ne!(a.targets, b.targets);
+        assert_ne!(a.missiles, b.missiles);
+        assert_ne!(a.bombs, b.bombs);
+    }
+
+    #[test]
+    fn bomb_count_does_not_change_missiles() {
+        let spawns = scenario(7).generate();
+        let mut more_bombs = scenario(7);
+        more_bombs.bombs.count = 500;
+        let more = more_bombs.generate();
+
+        assert_eq!(more.targets, spawns.targets);
+        assert_eq!(more.missiles, spawns.missiles);
+        assert_eq!(more.bombs.len(), 500);
+        assert_eq!(more.bombs[..20], spawns.bombs[..]);
+    }
+
+    #[test]
+    fn spawns_stay_in_ranges() {
+        let scenario = scenario(3);
+        let spawns = scenario.generate();
+        for target in &spawns.targets {
+            let (min, max) = scenario.targets.orbit_radius;
+            assert!((min..max).contains(&target.radius));
+            // 円が範囲に収まる
+            assert!(target.center.abs().max_element() + target.radius <= scenario.arena);
+        }
+        for missile in &spawns.missiles {
+            assert!(missile.position.abs().max_element() <= scenario.arena);
+            assert!(missile.target < spawns.targets.len());
+            let (min, max) = scenario.missiles.turn_rate;
+            assert!((min.to_radians()..max.to_radians()).contains(&missile.turn_rate));
+        }
+    }
+
+    #[test]
+    fn no_projectiles_without_targets() {
+        let mut scenario = scenario(1);
+        scenario.targets.count = 0;
+        let spawns = scenario.generate();
+        assert!(spawns.targets.is_empty());
+        assert!(spawns.missiles.is_empty());
+        assert!(spawns.bombs.is_empty());
+    }
+
+    #[test]
+    fn from_args_applies_overrides() {
+        assert_eq!(Scenario::from_args(args(&[])).unwrap(), None);
+
+        let scenario = Scenario::from_args(args(&["--seed", "9", "--bombs", "30"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            scenario,
+            Scenario {
+                seed: 9,
+                bombs: ProjectileGroup {
+                    count: 30,
+                    ..Scenario::default().bombs
+                },
+                ..Default::default()
+            }
+        );
+
+        // ファイルの値を上書きする（引数の順番によらない）
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/scenarios/stress.scenario.ron");
+        let path = path.to_str().unwrap();
+        let scenario = Scenario::from_args(args(&[
+            "--targets",
+            "8",
+            "--scenario",
+            path,
+            "--missiles",
+            "100",
+        ]))
+        .unwrap()
+        .unwrap();
+        let file = Scenario::load(path).unwrap();
+        assert_eq!(file.seed, 42);
+        assert_eq!(scenario.seed, 42);
+        assert_eq!(scenario.targets.count, 8);
+        assert_eq!(scenario.missiles.count, 100);
+        assert_eq!(scenario.bombs, file.bombs);
+    }
+
+    #[test]
+    fn from_args_rejects_bad_arguments() {
+        let error = |list: &[&str]| Scenario::from_args(args(list)).unwrap_err().to_string();
+        assert_eq!(error(&["--count", "10"]), "unknown argument --count");
+        assert_eq!(error(&["--seed"]), "missing value for --seed");
+        assert_eq!(
+            error(&["--bombs", "many"]),
+            "invalid value for --bombs: many"
+        );
+        assert!(error(&["--scenario", "missing.scenario.ron"]).starts_with("failed to read"));
+    }
+}
@@ -0,0 +1,314 @@
+//
+// ゲーム設定（音量・ウィンドウ・言語・プレイヤー名）の保存と反映
+//
+// `SettingsPlugin` が反映するのはウィンドウとUIの設定だけです。
+// 音量は設定画面で変更して保存するだけで、音の出力には反映しません
+// （このクレートには音を鳴らす処理がありません）。
+// 音を鳴らす側で `Settings::effective_sfx_volume` などを読んで使ってください。
+//
+// ウィンドウの設定は `DefaultPlugins` がウィンドウを作る前に必要なので、
+// `Settings::load` で読み込んでから `WindowDescriptor` を作ります。
+//
+// ```
+// let settings = Settings::load(SETTINGS_PATH);
+// App::new()
+//     .insert_resource(settings.window_descriptor(WindowDescriptor::default()))
+//     .insert_resource(settings)
+//     .add_plugins(DefaultPlugins)
+//     .add_plugin(SettingsPlugin::new(SETTINGS_PATH))
+//     .run();
+// ```
+//
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode};
+use serde::{Deserialize, Serialize};
+
+/// 設定ファイルの既定の保存先
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+/// 変更が止まってから保存するまでの時間（秒）
+const SAVE_DELAY: f32 = 0.5;
+
+/// UIの拡大率の最小値
+pub const UI_SCALE_MIN: f32 = 0.5;
+
+/// UIの拡大率の最大値
+pub const UI_SCALE_MAX: f32 = 2.0;
+
+///
+/// 設定の反映と保存を行うプラグイン
+///
+pub struct SettingsPlugin {
+    pub path: PathBuf,
+}
+
+impl SettingsPlugin {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // 先に読み込まれていない場合はここで読み込む
+        if !app.world.contains_resource::<Settings>() {
+            app.insert_resource(Settings::load(&self.path));
+        }
+
+        app.insert_resource(SettingsFile {
+            path: self.path.clone(),
+            save_timer: None,
+        })
+        .add_system(apply_settings)
+        .add_system(save_settings);
+    }
+}
+
+///
+/// ウィンドウの表示モード
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub const ALL: [WindowModeSetting; 3] = [
+        WindowModeSetting::Windowed,
+        WindowModeSetting::BorderlessFullscreen,
+        WindowModeSetting::Fullscreen,
+    ];
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+///
+/// UIの表示言語
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    Japanese,
+    English,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Japanese, Language::English];
+
+    /// 言語コード（ロケール名）
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Japanese => "ja",
+            Language::English => "en",
+        }
+    }
+}
+
+///
+/// ゲーム設定
+///
+/// 音量は 0.0 ~ 1.0 です。保存するだけで、音の出力には反映しません。
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub window_mode: WindowModeSetting,
+    pub vsync: bool,
+    /// UIの拡大率 (`UI_SCALE_MIN` ~ `UI_SCALE_MAX`)
+    pub ui_scale: f32,
+    pub language: Language,
+    pub player_name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            sfx_volume: 0.8,
+            music_volume: 0.6,
+            window_mode: WindowModeSetting::Windowed,
+            vsync: true,
+            ui_scale: 1.0,
+            language: Language::Japanese,
+            player_name: "Player".to_owned(),
+        }
+    }
+}
+
+impl Settings {
+    ///
+    /// 設定ファイルを読み込む
+    ///
+    /// ファイルが無い・読めない場合は既定値を使います。
+    /// 範囲外の値は `sanitize` で範囲内に収めます。
+    ///
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut settings = match std::fs::read_to_string(path) {
+            Ok(text) => ron::from_str(&text).unwrap_or_else(|e| {
+                warn!("failed to parse {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        settings.sanitize();
+        settings
+    }
+
+    ///
+    /// 音量と拡大率を有効な範囲に収める
+    ///
+    /// 数値でない値 (NaN) は既定値に戻します。
+    ///
+    pub fn sanitize(&mut self) {
+        let defaults = Self::default();
+        let clamp = |value: f32, default: f32, min: f32, max: f32| {
+            if value.is_nan() {
+                default
+            } else {
+                value.clamp(min, max)
+            }
+        };
+        self.master_volume = clamp(self.master_volume, defaults.master_volume, 0.0, 1.0);
+        self.sfx_volume = clamp(self.sfx_volume, defaults.sfx_volume, 0.0, 1.0);
+        self.music_volume = clamp(self.music_volume, defaults.music_volume, 0.0, 1.0);
+        self.ui_scale = clamp(self.ui_scale, defaults.ui_scale, UI_SCALE_MIN, UI_SCALE_MAX);
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// 全体音量を掛けた効果音の音量（再生時の `PlaybackSettings` に渡す）
+    pub fn effective_sfx_volume(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+
+    /// 全体音量を掛けたBGMの音量（再生中の `AudioSink` に設定する）
+    pub fn effective_music_volume(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    ///
+    /// 設定を反映した `WindowDescriptor` を作る
+    ///
+    pub fn window_descriptor(&self, base: WindowDescriptor) -> WindowDescriptor {
+        WindowDescriptor {
+            mode: self.window_mode.into(),
+            present_mode: self.present_mode(),
+            ..base
+        }
+    }
+}
+
+struct SettingsFile {
+    path: PathBuf,
+    save_timer: Option<f32>,
+}
+
+fn apply_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = settings.window_mode.into();
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+
+        let present_mode = settings.present_mode();
+        if window.present_mode() != present_mode {
+            window.set_present_mode(present_mode);
+        }
+
+        // 拡大率 1.0 の場合はOSの設定に従う
+        let ui_scale = settings.ui_scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX);
+        let scale_factor = if (ui_scale - 1.0).abs() < f32::EPSILON {
+            None
+        } else {
+            Some(window.backend_scale_factor() * ui_scale as f64)
+        };
+        if window.scale_factor_override() != scale_factor {
+            window.set_scale_factor_override(scale_factor);
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>, time: Res<Time>, mut file: ResMut<SettingsFile>) {
+    // スライダー操作中に何度も書き込まないよう、変更が落ち着いてから保存する
+    if settings.is_changed() && !settings.is_added() {
+        file.save_timer = Some(SAVE_DELAY);
+    }
+
+    if let Some(timer) = &mut file.save_timer {
+        *timer -= time.delta_seconds();
+        if *timer <= 0.0 {
+            file.save_timer = None;
+            if let Err(e) = settings.save(&file.path) {
+                error!("failed to save {}: {}", file.path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_clamps_out_of_range_values() {
+        let path = std::env::temp_dir().join(format!("settings-{}.ron", std::process::id()));
+        std::fs::write(
+            &path,
+            "(ui_scale: 40.0, master_volume: -1.0, sfx_volume: 3.0)",
+        )
+        .unwrap();
+        let settings = Settings::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(settings.ui_scale, UI_SCALE_MAX);
+        assert_eq!(settings.master_volume, 0.0);
+        assert_eq!(settings.sfx_volume, 1.0);
+        assert_eq!(settings.music_volume, Settings::default().music_volume);
+    }
+
+    #[test]
+    fn sanitize_resets_nan_to_default() {
+        let mut settings = Settings {
+            ui_scale: f32::NAN,
+            music_volume: f32::NAN,
+            ..Default::default()
+        };
+        settings.sanitize();
+        assert_eq!(settings.ui_scale, 1.0);
+        assert_eq!(settings.music_volume, Settings::default().music_volume);
+    }
+}
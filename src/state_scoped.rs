@@ -0,0 +1,352 @@
+//
+// 状態に紐づくエンティティの削除
+//
+// `StateScoped(state)` を付けたエンティティは、その状態がスタックから無くなった時に
+// 子ごと削除されます。`push` で上に別の状態を重ねている間は削除されません。
+//
+// `with_auto_scope` で指定した状態の間に作られたエンティティ（親を持たないもの）には
+// フレームの最後に `StateScoped` が自動で付きます。
+//
+// どちらも付いていないエンティティが、作られた時の状態を抜けた後も残っている場合は
+// 警告を出し、`OrphanedEntity` イベントを送ります。
+//
+// ```
+// app.add_state(GameState::Menu)
+//     .add_plugin(StateScopedPlugin::new(GameState::Menu).with_auto_scope(GameState::Menu));
+//
+// fn game_setup(mut commands: Commands) {
+//     commands
+//         .spawn_bundle(SpriteBundle::default())
+//         .insert(StateScoped(GameState::Game));
+// }
+// ```
+//
+
+use bevy::{
+    ecs::schedule::StateData,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+///
+/// 状態に紐づくエンティティを削除するプラグイン
+///
+/// 型引数 `S` は状態の型です。状態は `App::add_state` で別に追加します。
+///
+pub struct StateScopedPlugin<S> {
+    /// 型を決めるための値（使わない）
+    pub state: S,
+    /// 作られたエンティティに自動で `StateScoped` を付ける状態
+    pub auto_scopes: Vec<S>,
+    /// 状態を抜けた後に残ったエンティティを警告する
+    pub warn_orphans: bool,
+}
+
+impl<S: StateData> StateScopedPlugin<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            auto_scopes: Vec::new(),
+            warn_orphans: true,
+        }
+    }
+
+    pub fn with_auto_scope(mut self, state: S) -> Self {
+        self.auto_scopes.push(state);
+        self
+    }
+
+    pub fn with_warn_orphans(mut self, warn_orphans: bool) -> Self {
+        self.warn_orphans = warn_orphans;
+        self
+    }
+}
+
+impl<S: StateData> Plugin for StateScopedPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScopeTracker::<S> {
+            auto_scopes: self.auto_scopes.clone(),
+            warn_orphans: self.warn_orphans,
+            stack: Vec::new(),
+            known: HashSet::default(),
+            spawned: HashMap::default(),
+        })
+        .add_event::<OrphanedEntity<S>>()
+        .add_startup_system_to_stage(StartupStage::PostStartup, scope_startup_system::<S>)
+        .add_system_to_stage(
+            CoreStage::Last,
+            scope_despawn_system::<S>.label(StateScopedSystem::Despawn),
+        )
+        .add_system_to_stage(
+            CoreStage::Last,
+            scope_track_system::<S>
+                .label(StateScopedSystem::Track)
+                .after(StateScopedSystem::Despawn),
+        );
+    }
+}
+
+///
+/// 状態に紐づくエンティティを扱うシステムのラベル
+///
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateScopedSystem {
+    /// スタックから無くなった状態のエンティティを削除する
+    Despawn,
+    /// 作られたエンティティを記録する
+    Track,
+}
+
+///
+/// 状態に紐づくエンティティ
+///
+/// 状態がスタックに無い時に付けた場合は、そのフレームの最後に削除されます。
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct StateScoped<S>(pub S);
+
+///
+/// 状態を抜けても削除せず、警告もしないエンティティ
+///
+/// 画面の切り替え効果のように、状態をまたいで表示するものに付けます。
+///
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct KeepOnExit;
+
+///
+/// 作られた時の状態を抜けた後も残っているエンティティ
+///
+#[derive(Debug, Clone)]
+pub struct OrphanedEntity<S> {
+    pub entity: Entity,
+    /// 作られた時の状態
+    pub state: S,
+}
+
+struct ScopeTracker<S> {
+    auto_scopes: Vec<S>,
+    warn_orphans: bool,
+    /// 前のフレームのスタック
+    stack: Vec<S>,
+    /// 記録済みのエンティティ
+    known: HashSet<Entity>,
+    /// `StateScoped` の付いていないエンティティと作られた時の状態
+    spawned: HashMap<Entity, S>,
+}
+
+/// 今のスタック（下から順）
+fn state_stack<S: StateData>(state: &State<S>) -> impl Iterator<Item = &S> {
+    state
+        .inactives()
+        .iter()
+        .chain(std::iter::once(state.current()))
+}
+
+///
+/// 起動時に作られたエンティティは状態に含めない
+///
+fn scope_startup_system<S: StateData>(
+    mut tracker: ResMut<ScopeTracker<S>>,
+    roots: Query<Entity, Without<Parent>>,
+) {
+    tracker.known.extend(roots.iter());
+}
+
+fn scope_despawn_system<S: StateData>(
+    mut commands: Commands,
+    state: Res<State<S>>,
+    tracker: Res<ScopeTracker<S>>,
+    scoped: Query<(Entity, &StateScoped<S>)>,
+    added: Query<(Entity, &StateScoped<S>), Added<StateScoped<S>>>,
+) {
+    let in_stack = |scope: &S| state_stack(&state).any(|s| s == scope);
+    let mut despawn = |(entity, StateScoped(scope)): (Entity, &StateScoped<S>)| {
+        if !in_stack(scope) {
+            commands.entity(entity).despawn_recursive();
+        }
+    };
+
+    // スタックが変わった時だけ全て確かめる
+    if state_stack(&state).ne(tracker.stack.iter()) {
+        scoped.for_each(&mut despawn);
+    } else {
+        added.for_each(&mut despawn);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn scope_track_system<S: StateData>(
+    mut commands: Commands,
+    state: Res<State<S>>,
+    mut tracker: ResMut<ScopeTracker<S>>,
+    mut orphans: EventWriter<OrphanedEntity<S>>,
+    roots: Query<
+        Entity,
+        (
+            Without<Parent>,
+            Without<StateScoped<S>>,
+            Without<KeepOnExit>,
+        ),
+    >,
+) {
+    let tracker = &mut *tracker;
+    tracker.known.retain(|entity| roots.get(*entity).is_ok());
+    tracker
+        .spawned
+        .retain(|entity, _| roots.get(*entity).is_ok());
+
+    // 状態を抜けた後も残っているエンティティ
+    let stack_changed = state_stack(&state).ne(tracker.stack.iter());
+    if stack_changed {
+        tracker.stack = state_stack(&state).cloned().collect();
+        let stack = &tracker.stack;
+        tracker.spawned.retain(|&entity, spawned_in| {
+            if stack.contains(spawned_in) {
+                return true;
+            }
+            warn!(
+                "{:?} spawned in {:?} is left after exiting the state; add StateScoped or KeepOnExit",
+                entity, spawned_in
+            );
+            orphans.send(OrphanedEntity {
+                entity,
+                state: spawned_in.clone(),
+            });
+            false
+        });
+    }
+
+    let current = state.current();
+    let auto = tracker.auto_scopes.contains(current);
+    for entity in roots.iter() {
+        if !tracker.known.insert(entity) {
+            continue;
+        }
+        if auto {
+            commands.entity(entity).insert(StateScoped(current.clone()));
+        } else if tracker.warn_orphans {
+            tracker.spawned.insert(entity, current.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{
+        event::{Events, ManualEventReader},
+        schedule::StateError,
+    };
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum TestState {
+        Menu,
+        Game,
+        Pause,
+    }
+
+    fn app(plugin: StateScopedPlugin<TestState>) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state(TestState::Menu)
+            .add_plugin(plugin);
+        app.update();
+        app
+    }
+
+    /// 状態を操作して1フレーム実行する
+    fn change(app: &mut App, f: impl FnOnce(&mut State<TestState>) -> Result<(), StateError>) {
+        f(&mut app.world.resource_mut::<State<TestState>>()).unwrap();
+        app.update();
+    }
+
+    fn scoped(app: &mut App, state: TestState) -> Entity {
+        app.world.spawn().insert(StateScoped(state)).id()
+    }
+
+    fn exists(app: &App, entity: Entity) -> bool {
+        app.world.get_entity(entity).is_some()
+    }
+
+    #[test]
+    fn toggling_states_despawns_scoped_entities() {
+        let mut app = app(StateScopedPlugin::new(TestState::Menu));
+        let menu = scoped(&mut app, TestState::Menu);
+        change(&mut app, |state| state.set(TestState::Game));
+        assert!(!exists(&app, menu));
+
+        let game = scoped(&mut app, TestState::Game);
+        let child = app.world.spawn().id();
+        app.world.entity_mut(game).push_children(&[child]);
+        app.update();
+        assert!(exists(&app, game));
+
+        change(&mut app, |state| state.set(TestState::Menu));
+        assert!(!exists(&app, game));
+        assert!(!exists(&app, child));
+    }
+
+    #[test]
+    fn pushed_state_keeps_entities_below() {
+        let mut app = app(StateScopedPlugin::new(TestState::Menu));
+        change(&mut app, |state| state.set(TestState::Game));
+        let game = scoped(&mut app, TestState::Game);
+
+        change(&mut app, |state| state.push(TestState::Pause));
+        let pause = scoped(&mut app, TestState::Pause);
+        app.update();
+        assert!(exists(&app, game));
+        assert!(exists(&app, pause));
+
+        change(&mut app, |state| state.pop());
+        assert!(exists(&app, game));
+        assert!(!exists(&app, pause));
+    }
+
+    #[test]
+    fn scoped_to_inactive_state_is_despawned_immediately() {
+        let mut app = app(StateScopedPlugin::new(TestState::Menu));
+        let game = scoped(&mut app, TestState::Game);
+        app.update();
+        assert!(!exists(&app, game));
+    }
+
+    #[test]
+    fn leftover_entities_are_reported() {
+        let mut app = app(StateScopedPlugin::new(TestState::Menu));
+        let mut reader = ManualEventReader::<OrphanedEntity<TestState>>::default();
+        change(&mut app, |state| state.set(TestState::Game));
+        let orphan = app.world.spawn().id();
+        let kept = app.world.spawn().insert(KeepOnExit).id();
+        app.update();
+
+        change(&mut app, |state| state.set(TestState::Menu));
+        let orphans: Vec<_> = reader
+            .iter(app.world.resource::<Events<OrphanedEntity<TestState>>>())
+            .map(|orphan| (orphan.entity, orphan.state.clone()))
+            .collect();
+        assert_eq!(orphans, vec![(orphan, TestState::Game)]);
+        // 警告するだけで削除はしない
+        assert!(exists(&app, orphan));
+        assert!(exists(&app, kept));
+    }
+
+    #[test]
+    fn auto_scope_despawns_without_warning() {
+        let mut app = app(StateScopedPlugin::new(TestState::Menu).with_auto_scope(TestState::Game));
+        let mut reader = ManualEventReader::<OrphanedEntity<TestState>>::default();
+        change(&mut app, |state| state.set(TestState::Game));
+        let entity = app.world.spawn().id();
+        app.update();
+        assert_eq!(
+            app.world.get::<StateScoped<TestState>>(entity),
+            Some(&StateScoped(TestState::Game))
+        );
+
+        change(&mut app, |state| state.set(TestState::Menu));
+        assert!(!exists(&app, entity));
+        let events = app.world.resource::<Events<OrphanedEntity<TestState>>>();
+        assert_eq!(reader.iter(events).count(), 0);
+    }
+}
@@ -0,0 +1,598 @@
+//
+// 状態のスタック管理
+//
+// `State` を直接変更する代わりに `StateStack` に要求を積むと、ガードで確認してから
+// 状態を切り替え、結果を `StateHistory` に残します。ガードに拒否された要求は
+// 理由付きの `StateRejected` イベントになります。
+//
+// 状態を抜ける時にエンティティを削除するには `StateScopedPlugin` を使います。
+//
+// ```
+// app.add_state(GameState::Menu)
+//     .add_plugin(StateStackPlugin::new(GameState::Menu))
+//     .add_state_guard(|change: &StateChange<GameState>, world: &World| {
+//         if change.to.as_ref() == Some(&GameState::Settings) && change.depth > 1 {
+//             return Err("settings is already open".to_string());
+//         }
+//         Ok(())
+//     });
+//
+// fn open_settings(mut stack: ResMut<StateStack<GameState>>) {
+//     stack.push(GameState::Settings);
+// }
+// ```
+//
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use bevy::{
+    ecs::{
+        event::Events,
+        schedule::{StateData, StateError},
+    },
+    prelude::*,
+};
+
+///
+/// 状態のスタック管理を追加するプラグイン
+///
+/// 型引数 `S` は管理する状態の型です。状態は `App::add_state` で別に追加します。
+///
+pub struct StateStackPlugin<S> {
+    /// 型を決めるための値（使わない）
+    pub state: S,
+    /// 履歴に残す数
+    pub history: usize,
+}
+
+impl<S: StateData> StateStackPlugin<S> {
+    pub fn new(state: S) -> Self {
+        Self { state, history: 32 }
+    }
+
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+}
+
+impl<S: StateData> Plugin for StateStackPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StateStack::<S> {
+            requests: VecDeque::new(),
+        })
+        .insert_resource(StateHistory::<S>::new(self.history))
+        .add_event::<StateRejected<S>>()
+        .add_system(
+            state_request_system::<S>
+                .exclusive_system()
+                .at_start()
+                .label(StateStackSystem),
+        );
+        app.world
+            .get_resource_or_insert_with(StateGuards::<S>::default);
+    }
+}
+
+///
+/// 要求を処理して状態を切り替えるシステムのラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StateStackSystem;
+
+///
+/// 状態の操作
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateOperation<S> {
+    /// 今の状態を置き換える
+    Set(S),
+    /// 今の状態の上に重ねる
+    Push(S),
+    /// 重ねた状態を外して下の状態に戻る
+    Pop,
+    /// スタック全体を1つの状態に置き換える
+    Replace(S),
+}
+
+///
+/// 状態の切り替えの内容
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChange<S> {
+    pub operation: StateOperation<S>,
+    /// 切り替える前の状態
+    pub from: S,
+    /// 切り替えた後の状態（`Pop` で戻る状態が無い場合は `None`）
+    pub to: Option<S>,
+    /// 切り替える前のスタックの深さ
+    pub depth: usize,
+}
+
+impl<S: StateData> StateChange<S> {
+    fn new(operation: StateOperation<S>, state: &State<S>) -> Self {
+        let to = match &operation {
+            StateOperation::Set(next)
+            | StateOperation::Push(next)
+            | StateOperation::Replace(next) => Some(next.clone()),
+            StateOperation::Pop => state.inactives().last().cloned(),
+        };
+        Self {
+            operation,
+            from: state.current().clone(),
+            to,
+            depth: state.inactives().len() + 1,
+        }
+    }
+}
+
+///
+/// 状態の切り替えの要求
+///
+/// 要求は `StateStackSystem` で順番に処理され、1フレームに切り替わるのは1回だけです。
+/// 拒否された要求は次の要求の処理を妨げません。
+///
+pub struct StateStack<S> {
+    requests: VecDeque<StateOperation<S>>,
+}
+
+impl<S: StateData> StateStack<S> {
+    pub fn set(&mut self, state: S) {
+        self.request(StateOperation::Set(state));
+    }
+
+    pub fn push(&mut self, state: S) {
+        self.request(StateOperation::Push(state));
+    }
+
+    pub fn pop(&mut self) {
+        self.request(StateOperation::Pop);
+    }
+
+    pub fn replace(&mut self, state: S) {
+        self.request(StateOperation::Replace(state));
+    }
+
+    pub fn request(&mut self, operation: StateOperation<S>) {
+        self.requests.push_back(operation);
+    }
+
+    /// 処理されていない要求があるか
+    pub fn is_pending(&self) -> bool {
+        !self.requests.is_empty()
+    }
+}
+
+///
+/// 状態の切り替えが拒否されたことを知らせるイベント
+///
+#[derive(Debug, Clone)]
+pub struct StateRejected<S> {
+    pub change: StateChange<S>,
+    pub reason: String,
+}
+
+///
+/// 状態の切り替えを許可するかを決める関数
+///
+/// 拒否する場合は理由を `Err` で返します。
+///
+pub type StateGuard<S> = Box<dyn Fn(&StateChange<S>, &World) -> Result<(), String> + Send + Sync>;
+
+struct StateGuards<S> {
+    guards: Vec<StateGuard<S>>,
+}
+
+impl<S> Default for StateGuards<S> {
+    fn default() -> Self {
+        Self { guards: Vec::new() }
+    }
+}
+
+impl<S: StateData> StateGuards<S> {
+    fn check(&self, change: &StateChange<S>, world: &World) -> Result<(), String> {
+        self.guards
+            .iter()
+            .try_for_each(|guard| guard(change, world))
+    }
+}
+
+///
+/// 状態の切り替えの履歴
+///
+/// 古いものから `capacity` 件を超えた分は捨てられます。
+///
+pub struct StateHistory<S> {
+    capacity: usize,
+    entries: VecDeque<StateHistoryEntry<S>>,
+}
+
+///
+/// 履歴に残した1回分の切り替え
+///
+#[derive(Debug, Clone)]
+pub struct StateHistoryEntry<S> {
+    /// 起動してからの時間（秒）
+    pub time: f64,
+    pub change: StateChange<S>,
+    /// 拒否された場合は理由
+    pub result: Result<(), String>,
+}
+
+impl<S: StateData> StateHistory<S> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// 古い順
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &StateHistoryEntry<S>> {
+        self.entries.iter()
+    }
+
+    pub fn last(&self) -> Option<&StateHistoryEntry<S>> {
+        self.entries.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn record(&mut self, entry: StateHistoryEntry<S>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+impl<S: StateData> fmt::Display for StateHistoryEntry<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:8.2}s {:?}: {:?} -> {:?}",
+            self.time, self.change.operation, self.change.from, self.change.to
+        )?;
+        if let Err(reason) = &self.result {
+            write!(f, " (rejected: {})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// 状態のガードを追加する
+///
+pub trait StateStackAppExt {
+    fn add_state_guard<S: StateData>(
+        &mut self,
+        guard: impl Fn(&StateChange<S>, &World) -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl StateStackAppExt for App {
+    fn add_state_guard<S: StateData>(
+        &mut self,
+        guard: impl Fn(&StateChange<S>, &World) -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(StateGuards::<S>::default)
+            .guards
+            .push(Box::new(guard));
+        self
+    }
+}
+
+///
+/// `operation` がガードに許可されるかを調べる（状態は変更しない）
+///
+/// `StateStackPlugin` が無い場合は常に許可します。
+///
+pub fn check_state_change<S: StateData>(
+    world: &World,
+    operation: StateOperation<S>,
+) -> Result<(), StateRejected<S>> {
+    let (state, guards) = match (
+        world.get_resource::<State<S>>(),
+        world.get_resource::<StateGuards<S>>(),
+    ) {
+        (Some(state), Some(guards)) => (state, guards),
+        _ => return Ok(()),
+    };
+    let change = StateChange::new(operation, state);
+    guards
+        .check(&change, world)
+        .map_err(|reason| StateRejected { change, reason })
+}
+
+///
+/// 拒否された切り替えを履歴に残し、`StateRejected` を送る
+///
+pub fn report_rejected<S: StateData>(world: &mut World, rejected: StateRejected<S>) {
+    let entry = StateHistoryEntry {
+        time: world
+            .get_resource::<Time>()
+            .map_or(0.0, |time| time.seconds_since_startup()),
+        change: rejected.change.clone(),
+        result: Err(rejected.reason.clone()),
+    };
+    warn!("state change rejected: {}", entry);
+    if let Some(mut history) = world.get_resource_mut::<StateHistory<S>>() {
+        history.record(entry);
+    }
+    if let Some(mut events) = world.get_resource_mut::<Events<StateRejected<S>>>() {
+        events.send(rejected);
+    }
+}
+
+fn state_request_system<S: StateData>(world: &mut World) {
+    loop {
+        let operation = match world.resource_mut::<StateStack<S>>().requests.pop_front() {
+            Some(operation) => operation,
+            None => return,
+        };
+        let change = StateChange::new(operation.clone(), world.resource::<State<S>>());
+
+        let mut result =
+            world.resource_scope(|world, guards: Mut<StateGuards<S>>| guards.check(&change, world));
+        if result.is_ok() {
+            let mut state = world.resource_mut::<State<S>>();
+            let applied = match operation.clone() {
+                StateOperation::Set(next) => state.set(next),
+                StateOperation::Push(next) => state.push(next),
+                StateOperation::Pop => state.pop(),
+                StateOperation::Replace(next) => state.replace(next),
+            };
+            match applied {
+                // 前の切り替えが終わっていないので次のフレームでやり直す
+                Err(StateError::StateAlreadyQueued) => {
+                    world
+                        .resource_mut::<StateStack<S>>()
+                        .requests
+                        .push_front(operation);
+                    return;
+                }
+                Err(e) => result = Err(e.to_string()),
+                Ok(()) => {}
+            }
+        }
+
+        match result {
+            Ok(()) => {
+                let entry = StateHistoryEntry {
+                    time: world
+                        .get_resource::<Time>()
+                        .map_or(0.0, |time| time.seconds_since_startup()),
+                    change,
+                    result,
+                };
+                debug!("state changed: {}", entry);
+                world.resource_mut::<StateHistory<S>>().record(entry);
+                return;
+            }
+            Err(reason) => report_rejected(world, StateRejected { change, reason }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum TestState {
+        Menu,
+        Game,
+        Pause,
+        Settings,
+    }
+
+    /// `Menu` に戻った回数
+    #[derive(Default)]
+    struct MenuResumed(usize);
+
+    fn app(history: usize) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state(TestState::Menu)
+            .add_plugin(StateStackPlugin::new(TestState::Menu).with_history(history))
+            .init_resource::<MenuResumed>()
+            .add_system_set(SystemSet::on_resume(TestState::Menu).with_system(
+                |mut resumed: ResMut<MenuResumed>| {
+                    resumed.0 += 1;
+                },
+            ));
+        app.update();
+        app
+    }
+
+    fn stack(app: &mut App) -> Mut<'_, StateStack<TestState>> {
+        app.world.resource_mut::<StateStack<TestState>>()
+    }
+
+    fn current(app: &App) -> TestState {
+        *app.world.resource::<State<TestState>>().current()
+    }
+
+    fn inactives(app: &App) -> Vec<TestState> {
+        app.world
+            .resource::<State<TestState>>()
+            .inactives()
+            .to_vec()
+    }
+
+    fn rejected(
+        app: &App,
+        reader: &mut ManualEventReader<StateRejected<TestState>>,
+    ) -> Vec<StateRejected<TestState>> {
+        reader
+            .iter(app.world.resource::<Events<StateRejected<TestState>>>())
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn push_and_pop_restore_state_underneath() {
+        let mut app = app(32);
+
+        stack(&mut app).push(TestState::Settings);
+        app.update();
+        assert_eq!(current(&app), TestState::Settings);
+        assert_eq!(inactives(&app), [TestState::Menu]);
+
+        stack(&mut app).pop();
+        app.update();
+        assert_eq!(current(&app), TestState::Menu);
+        assert!(inactives(&app).is_empty());
+        assert_eq!(app.world.resource::<MenuResumed>().0, 1);
+
+        // 戻る状態が無い Pop はエラーとして残る
+        stack(&mut app).pop();
+        app.update();
+        assert_eq!(current(&app), TestState::Menu);
+        let history = app.world.resource::<StateHistory<TestState>>();
+        let last = history.last().unwrap();
+        assert_eq!(last.change.operation, StateOperation::Pop);
+        assert_eq!(last.change.to, None);
+        assert!(last.result.is_err());
+    }
+
+    #[test]
+    fn one_change_per_frame() {
+        let mut app = app(32);
+
+        stack(&mut app).set(TestState::Game);
+        stack(&mut app).push(TestState::Pause);
+        app.update();
+        assert_eq!(current(&app), TestState::Game);
+        assert!(stack(&mut app).is_pending());
+
+        app.update();
+        assert_eq!(current(&app), TestState::Pause);
+        assert_eq!(inactives(&app), [TestState::Game]);
+        assert!(!stack(&mut app).is_pending());
+    }
+
+    #[test]
+    fn guard_rejection_sends_reason() {
+        let mut app = app(32);
+        app.add_state_guard(|change: &StateChange<TestState>, _: &World| {
+            if change.to == Some(TestState::Settings) && change.depth > 1 {
+                return Err("settings is already open".to_string());
+            }
+            Ok(())
+        });
+        let mut reader = ManualEventReader::default();
+
+        stack(&mut app).push(TestState::Game);
+        app.update();
+        // 拒否された要求の後の要求は同じフレームで処理される
+        stack(&mut app).push(TestState::Settings);
+        stack(&mut app).set(TestState::Pause);
+        app.update();
+
+        let events = rejected(&app, &mut reader);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason, "settings is already open");
+        assert_eq!(
+            events[0].change,
+            StateChange {
+                operation: StateOperation::Push(TestState::Settings),
+                from: TestState::Game,
+                to: Some(TestState::Settings),
+                depth: 2,
+            }
+        );
+        assert_eq!(current(&app), TestState::Pause);
+        assert_eq!(inactives(&app), [TestState::Menu]);
+
+        let history = app.world.resource::<StateHistory<TestState>>();
+        let results: Vec<_> = history.iter().map(|entry| entry.result.clone()).collect();
+        assert_eq!(
+            results,
+            [Ok(()), Err("settings is already open".to_string()), Ok(())]
+        );
+    }
+
+    #[test]
+    fn history_drops_oldest_entry_at_capacity() {
+        let mut app = app(2);
+
+        for state in [TestState::Game, TestState::Pause, TestState::Settings] {
+            stack(&mut app).set(state);
+            app.update();
+        }
+
+        let history = app.world.resource::<StateHistory<TestState>>();
+        assert_eq!(history.len(), 2);
+        let changes: Vec<_> = history
+            .iter()
+            .map(|entry| (entry.change.from, entry.change.to))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (TestState::Game, Some(TestState::Pause)),
+                (TestState::Pause, Some(TestState::Settings)),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_capacity_keeps_no_history() {
+        let mut app = app(0);
+        stack(&mut app).set(TestState::Game);
+        app.update();
+        assert_eq!(current(&app), TestState::Game);
+        assert!(app.world.resource::<StateHistory<TestState>>().is_empty());
+    }
+
+    #[test]
+    fn retries_when_state_change_is_already_queued() {
+        let mut app = app(32);
+
+        // 同じフレームに先に `State` を直接変更したシステムがあると要求はやり直しになる
+        app.add_system(
+            (|mut state: ResMut<State<TestState>>| {
+                if *state.current() == TestState::Menu {
+                    state.set(TestState::Game).unwrap();
+                }
+            })
+            .exclusive_system()
+            .at_start()
+            .before(StateStackSystem),
+        );
+        stack(&mut app).push(TestState::Pause);
+        app.update();
+
+        assert_eq!(current(&app), TestState::Game);
+        assert!(stack(&mut app).is_pending());
+        assert!(app.world.resource::<StateHistory<TestState>>().is_empty());
+
+        app.update();
+        assert_eq!(current(&app), TestState::Pause);
+        assert_eq!(inactives(&app), [TestState::Game]);
+        assert!(!stack(&mut app).is_pending());
+        let history = app.world.resource::<StateHistory<TestState>>();
+        assert_eq!(history.len(), 1);
+        let last = history.last().unwrap();
+        assert_eq!(last.change.from, TestState::Game);
+        assert!(last.result.is_ok());
+    }
+}
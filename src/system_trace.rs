@@ -0,0 +1,985 @@
+//
+// システムの実行順序のトレース
+//
+// Bevy のシステムとステージの span を記録し、フレームごとの実行順と時間を
+// リングバッファに残します。1フレーム分を Chrome のトレース形式（chrome://tracing や
+// Perfetto で開ける JSON）で保存でき、スケジュールのステージ・ラベル・before/after の
+// 関係を Graphviz の DOT 形式で出力できます。
+//
+// span は `trace` フィーチャーを有効にした時だけ出ます。
+// このプラグインがログの出力も行うので、`LogPlugin` は外してください。
+//
+// ```
+// app.add_plugins_with(DefaultPlugins, |plugins| plugins.disable::<LogPlugin>())
+//     .add_plugin(SystemTracePlugin::default());
+// save_schedule_dot(&app.schedule, "schedule.dot")?;
+// ```
+//
+// ```
+// cargo run --example stage --features trace -- --headless
+// dot -Tsvg stage.dot -o stage.svg
+// ```
+//
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write},
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use bevy::{
+    ecs::schedule::{SystemContainer, SystemLabel},
+    prelude::*,
+    utils::{
+        tracing::{
+            field::{Field, Visit},
+            span::{Attributes, Id},
+            Subscriber,
+        },
+        HashMap,
+    },
+};
+use serde::Serialize;
+use tracing_subscriber::{
+    filter::filter_fn, layer::Context, prelude::*, registry::LookupSpan, EnvFilter, Registry,
+};
+
+use crate::ui::diagnostics::short_type_name;
+
+///
+/// システムの実行順序を記録するプラグイン
+///
+/// `key` を押すと直前のフレームのトレースを `output` に保存します。
+///
+pub struct SystemTracePlugin {
+    /// 残すフレーム数
+    pub frames: usize,
+    pub key: Option<KeyCode>,
+    /// トレースを保存するディレクトリ
+    pub output: String,
+    /// ログの出力に使うフィルター（`RUST_LOG` が優先）
+    pub filter: String,
+}
+
+impl Default for SystemTracePlugin {
+    fn default() -> Self {
+        Self {
+            frames: 120,
+            key: Some(KeyCode::F5),
+            output: "traces".to_string(),
+            filter: "info,wgpu=error".to_string(),
+        }
+    }
+}
+
+impl SystemTracePlugin {
+    pub fn with_frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn with_key(mut self, key: Option<KeyCode>) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_output(mut self, output: impl Into<String>) -> Self {
+        self.output = output.into();
+        self
+    }
+
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+}
+
+impl Plugin for SystemTracePlugin {
+    fn build(&self, app: &mut App) {
+        let (layer, trace) = SystemTraceLayer::new(self.frames);
+        let filter = EnvFilter::try_from_default_env()
+            .or_else(|_| EnvFilter::try_new(&self.filter))
+            .unwrap_or_else(|_| EnvFilter::new("info"));
+        let installed = Registry::default()
+            .with(tracing_subscriber::fmt::layer().with_filter(filter))
+            .with(layer.with_filter(filter_fn(|metadata| {
+                metadata.is_span() && SpanKind::from_name(metadata.name()).is_some()
+            })))
+            .try_init();
+        if let Err(e) = installed {
+            warn!(
+                "failed to install the system trace subscriber (disable LogPlugin): {}",
+                e
+            );
+        }
+        if !cfg!(feature = "trace") {
+            warn!("system spans are recorded only with `--features trace`");
+        }
+
+        app.insert_resource(trace);
+        if let Some(key) = self.key {
+            app.insert_resource(TraceDump {
+                key,
+                output: self.output.clone(),
+            })
+            .add_system(trace_dump_system);
+        }
+    }
+}
+
+///
+/// 記録した span の種類
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TraceKind {
+    Stage,
+    System,
+    ExclusiveSystem,
+    /// システムのコマンドの適用
+    Commands,
+}
+
+impl TraceKind {
+    fn category(&self) -> &'static str {
+        match self {
+            TraceKind::Stage => "stage",
+            TraceKind::System => "system",
+            TraceKind::ExclusiveSystem => "exclusive_system",
+            TraceKind::Commands => "commands",
+        }
+    }
+}
+
+/// 記録する span（フレームは区切りにだけ使う）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpanKind {
+    Frame,
+    Trace(TraceKind),
+}
+
+impl SpanKind {
+    fn from_name(name: &str) -> Option<Self> {
+        let kind = match name {
+            "frame" => return Some(SpanKind::Frame),
+            "stage" => TraceKind::Stage,
+            "system" => TraceKind::System,
+            "exclusive_system" => TraceKind::ExclusiveSystem,
+            "system_commands" => TraceKind::Commands,
+            _ => return None,
+        };
+        Some(SpanKind::Trace(kind))
+    }
+}
+
+///
+/// 1回分の実行
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEvent {
+    pub kind: TraceKind,
+    /// システムかステージの名前
+    pub name: String,
+    /// トレースを始めてからの時間（マイクロ秒）
+    pub start: f64,
+    /// 実行時間（マイクロ秒）
+    pub duration: f64,
+    /// 実行したスレッドの番号
+    pub thread: usize,
+}
+
+///
+/// 1フレーム分の記録
+///
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceFrame {
+    pub index: u64,
+    /// トレースを始めてからの時間（マイクロ秒）
+    pub start: f64,
+    pub duration: f64,
+    /// フレームを実行したスレッドの番号
+    pub thread: usize,
+    /// 開始が早い順
+    pub events: Vec<TraceEvent>,
+    /// スレッドの番号と名前
+    pub threads: Vec<(usize, String)>,
+}
+
+impl TraceFrame {
+    /// 実行されたシステム（開始が早い順）
+    pub fn systems(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.events
+            .iter()
+            .filter(|event| matches!(event.kind, TraceKind::System | TraceKind::ExclusiveSystem))
+    }
+
+    /// 実行されたステージ（開始が早い順）
+    pub fn stages(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.events
+            .iter()
+            .filter(|event| event.kind == TraceKind::Stage)
+    }
+
+    ///
+    /// Chrome のトレース形式の JSON
+    ///
+    pub fn to_chrome_trace(&self) -> serde_json::Result<String> {
+        let complete =
+            |name: String, cat: &'static str, start: f64, duration: f64, thread| ChromeEvent {
+                name,
+                cat,
+                ph: "X",
+                ts: start,
+                dur: Some(duration),
+                pid: 1,
+                tid: thread,
+                args: None,
+            };
+        let mut events = vec![complete(
+            format!("frame {}", self.index),
+            "frame",
+            self.start,
+            self.duration,
+            self.thread,
+        )];
+        events.extend(self.events.iter().map(|event| {
+            complete(
+                event.name.clone(),
+                event.kind.category(),
+                event.start,
+                event.duration,
+                event.thread,
+            )
+        }));
+        events.extend(self.threads.iter().map(|(thread, name)| ChromeEvent {
+            name: "thread_name".to_string(),
+            cat: "__metadata",
+            ph: "M",
+            ts: 0.0,
+            dur: None,
+            pid: 1,
+            tid: *thread,
+            args: Some(serde_json::json!({ "name": name })),
+        }));
+        serde_json::to_string(&ChromeTrace {
+            trace_events: events,
+            display_time_unit: "ms",
+        })
+    }
+
+    pub fn save_chrome_trace(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_chrome_trace()?)
+            .map_err(|e| anyhow::anyhow!("failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChromeTrace {
+    trace_events: Vec<ChromeEvent>,
+    display_time_unit: &'static str,
+}
+
+#[derive(Serialize)]
+struct ChromeEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+    ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<serde_json::Value>,
+}
+
+///
+/// 記録したトレース
+///
+/// 古いフレームから `frames` を超えた分は捨てられます。
+///
+#[derive(Clone)]
+pub struct SystemTrace {
+    buffer: Arc<Mutex<TraceBuffer>>,
+}
+
+impl SystemTrace {
+    /// 残っているフレーム（古い順）
+    pub fn frames(&self) -> Vec<TraceFrame> {
+        self.buffer.lock().unwrap().frames.iter().cloned().collect()
+    }
+
+    /// 最後に記録し終えたフレーム
+    pub fn last_frame(&self) -> Option<TraceFrame> {
+        self.buffer.lock().unwrap().frames.back().cloned()
+    }
+
+    pub fn frame(&self, index: u64) -> Option<TraceFrame> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer
+            .frames
+            .iter()
+            .find(|frame| frame.index == index)
+            .cloned()
+    }
+
+    pub fn clear(&self) {
+        self.buffer.lock().unwrap().frames.clear();
+    }
+}
+
+struct TraceBuffer {
+    capacity: usize,
+    frames: VecDeque<TraceFrame>,
+    current: Option<TraceFrame>,
+    next_index: u64,
+    threads: HashMap<usize, String>,
+}
+
+impl TraceBuffer {
+    fn begin_frame(&mut self, start: f64, thread: usize) {
+        self.current = Some(TraceFrame {
+            index: self.next_index,
+            start,
+            duration: 0.0,
+            thread,
+            events: Vec::new(),
+            threads: Vec::new(),
+        });
+        self.next_index += 1;
+    }
+
+    fn end_frame(&mut self, end: f64) {
+        let mut frame = match self.current.take() {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.duration = end - frame.start;
+        frame.events.sort_by(|a, b| a.start.total_cmp(&b.start));
+        frame.threads = self
+            .threads
+            .iter()
+            .map(|(thread, name)| (*thread, name.clone()))
+            .collect();
+        frame.threads.sort();
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    fn record(&mut self, event: TraceEvent) {
+        if let Some(frame) = &mut self.current {
+            frame.events.push(event);
+        }
+    }
+}
+
+///
+/// span を `SystemTrace` に記録する `tracing` のレイヤー
+///
+/// 独自のサブスクライバーを使う場合は、`SystemTracePlugin` の代わりにこれを追加し、
+/// 返された `SystemTrace` をリソースとして追加します。
+///
+pub struct SystemTraceLayer {
+    epoch: Instant,
+    buffer: Arc<Mutex<TraceBuffer>>,
+}
+
+impl SystemTraceLayer {
+    pub fn new(frames: usize) -> (Self, SystemTrace) {
+        let buffer = Arc::new(Mutex::new(TraceBuffer {
+            capacity: frames,
+            frames: VecDeque::with_capacity(frames),
+            current: None,
+            next_index: 0,
+            threads: HashMap::default(),
+        }));
+        let layer = Self {
+            epoch: Instant::now(),
+            buffer: buffer.clone(),
+        };
+        (layer, SystemTrace { buffer })
+    }
+
+    /// トレースを始めてからの時間（マイクロ秒）
+    fn now(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64() * 1_000_000.0
+    }
+
+    /// 今のスレッドの番号（初めての場合は名前を記録する）
+    fn thread(&self) -> usize {
+        static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        }
+        let thread = THREAD.with(|thread| *thread);
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.threads.entry(thread).or_insert_with(|| {
+            let current = std::thread::current();
+            current
+                .name()
+                .map_or_else(|| format!("thread {}", thread), str::to_string)
+        });
+        thread
+    }
+}
+
+/// span に付けておく情報
+struct SpanInfo {
+    kind: SpanKind,
+    name: String,
+    entered: Option<f64>,
+}
+
+/// span の `name` フィールドを読む
+#[derive(Default)]
+struct NameVisitor(String);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "name" {
+            // ステージのラベルが文字列の場合は引用符を外す
+            self.0 = format!("{:?}", value).trim_matches('"').to_string();
+        }
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for SystemTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let kind = match SpanKind::from_name(attrs.metadata().name()) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let mut name = NameVisitor::default();
+        attrs.record(&mut name);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanInfo {
+                kind,
+                name: name.0,
+                entered: None,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(info) = extensions.get_mut::<SpanInfo>() {
+            let now = self.now();
+            info.entered = Some(now);
+            if info.kind == SpanKind::Frame {
+                let thread = self.thread();
+                self.buffer.lock().unwrap().begin_frame(now, thread);
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        let info = match extensions.get_mut::<SpanInfo>() {
+            Some(info) => info,
+            None => return,
+        };
+        let start = match info.entered.take() {
+            Some(start) => start,
+            None => return,
+        };
+        let now = self.now();
+        match info.kind {
+            SpanKind::Frame => self.buffer.lock().unwrap().end_frame(now),
+            SpanKind::Trace(kind) => {
+                let event = TraceEvent {
+                    kind,
+                    name: info.name.clone(),
+                    start,
+                    duration: now - start,
+                    thread: self.thread(),
+                };
+                self.buffer.lock().unwrap().record(event);
+            }
+        }
+    }
+}
+
+struct TraceDump {
+    key: KeyCode,
+    output: String,
+}
+
+fn trace_dump_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    dump: Res<TraceDump>,
+    trace: Res<SystemTrace>,
+) {
+    if !keyboard_input.just_pressed(dump.key) {
+        return;
+    }
+    let frame = match trace.last_frame() {
+        Some(frame) => frame,
+        None => {
+            warn!("no frame has been traced (build with `--features trace`)");
+            return;
+        }
+    };
+    let path = Path::new(&dump.output).join(format!("frame-{}.json", frame.index));
+    let saved = fs::create_dir_all(&dump.output)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| frame.save_chrome_trace(&path));
+    match saved {
+        Ok(()) => info!(
+            "saved the trace of frame {} to {}",
+            frame.index,
+            path.display()
+        ),
+        Err(e) => warn!("failed to save the trace: {}", e),
+    }
+}
+
+///
+/// スケジュールのステージ・システム・before/after の関係を DOT 形式で出力する
+///
+/// ステージは実行順に太い矢印でつなぎ、ステージ内の矢印は before/after で決まる順番です。
+/// 排他システムは灰色で表示します。入れ子のスケジュール（起動時のステージ）も含みます。
+///
+pub fn schedule_to_dot(schedule: &Schedule) -> String {
+    let mut dot = DotWriter::default();
+    dot.line("digraph schedule {");
+    dot.line("    compound = true;");
+    dot.line("    node [shape = box, fontname = \"sans-serif\"];");
+    dot.schedule(schedule, 1);
+    dot.line("}");
+    dot.out
+}
+
+pub fn save_schedule_dot(schedule: &Schedule, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    fs::write(path, schedule_to_dot(schedule))
+        .map_err(|e| anyhow::anyhow!("failed to write {}: {}", path.display(), e))
+}
+
+#[derive(Default)]
+struct DotWriter {
+    out: String,
+    next_id: usize,
+}
+
+/// DOT に出力するシステム
+struct DotSystem<'a> {
+    id: String,
+    name: String,
+    labels: &'a [Box<dyn SystemLabel>],
+    before: &'a [Box<dyn SystemLabel>],
+    after: &'a [Box<dyn SystemLabel>],
+    exclusive: bool,
+}
+
+impl<'a> DotSystem<'a> {
+    fn collect<C: SystemContainer>(
+        systems: &'a [C],
+        exclusive: bool,
+        dot: &mut DotWriter,
+        out: &mut Vec<Self>,
+    ) {
+        out.extend(systems.iter().map(|system| DotSystem {
+            id: dot.id("system"),
+            name: short_type_name(&system.name()),
+            labels: system.labels(),
+            before: system.before(),
+            after: system.after(),
+            exclusive,
+        }));
+    }
+}
+
+impl DotWriter {
+    fn line(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}_{}", prefix, self.next_id)
+    }
+
+    ///
+    /// ステージを実行順に出力し、先頭のステージの見出しの ID を返す
+    ///
+    fn schedule(&mut self, schedule: &Schedule, depth: usize) -> Option<String> {
+        let indent = "    ".repeat(depth);
+        let mut first = None;
+        let mut previous: Option<String> = None;
+        for (label, stage) in schedule.iter_stages() {
+            let cluster = self.id("cluster");
+            let header = self.id("stage");
+            let name = escape(format!("{:?}", label).trim_matches('"'));
+            let _ = writeln!(self.out, "{}subgraph {} {{", indent, cluster);
+            let _ = writeln!(
+                self.out,
+                "{}    {} [label = \"{}\", shape = plaintext];",
+                indent, header, name
+            );
+            if let Some(stage) = stage.downcast_ref::<SystemStage>() {
+                self.stage(stage, depth + 1, &header);
+            } else if let Some(schedule) = stage.downcast_ref::<Schedule>() {
+                if let Some(nested) = self.schedule(schedule, depth + 1) {
+                    let _ = writeln!(
+                        self.out,
+                        "{}    {} -> {} [style = invis];",
+                        indent, header, nested
+                    );
+                }
+            }
+            let _ = writeln!(self.out, "{}}}", indent);
+
+            if let Some(previous) = &previous {
+                let _ = writeln!(
+                    self.out,
+                    "{}{} -> {} [style = bold, color = gray40];",
+                    indent, previous, header
+                );
+            }
+            first.get_or_insert_with(|| header.clone());
+            previous = Some(header);
+        }
+        first
+    }
+
+    fn stage(&mut self, stage: &SystemStage, depth: usize, header: &str) {
+        let indent = "    ".repeat(depth);
+        let mut systems = Vec::new();
+        DotSystem::collect(stage.exclusive_at_start_systems(), true, self, &mut systems);
+        DotSystem::collect(stage.parallel_systems(), false, self, &mut systems);
+        DotSystem::collect(
+            stage.exclusive_before_commands_systems(),
+            true,
+            self,
+            &mut systems,
+        );
+        DotSystem::collect(stage.exclusive_at_end_systems(), true, self, &mut systems);
+
+        for system in &systems {
+            let mut label = escape(&system.name);
+            for system_label in system.labels.iter().filter_map(|l| label_name(l.as_ref())) {
+                let _ = write!(label, "\\n[{}]", escape(&system_label));
+            }
+            let style = if system.exclusive {
+                ", style = filled, fillcolor = gray85"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                self.out,
+                "{}{} [label = \"{}\"{}];",
+                indent, system.id, label, style
+            );
+            // 見出しの下に並べる
+            let _ = writeln!(
+                self.out,
+                "{}{} -> {} [style = invis];",
+                indent, header, system.id
+            );
+        }
+
+        for system in &systems {
+            let mut edge = |from: &DotSystem, to: &DotSystem, label: &dyn SystemLabel| {
+                let text = label_name(label)
+                    .map(|name| format!(" [label = \"{}\"]", escape(&name)))
+                    .unwrap_or_default();
+                let _ = writeln!(self.out, "{}{} -> {}{};", indent, from.id, to.id, text);
+            };
+            for other in systems.iter().filter(|other| other.id != system.id) {
+                for label in system.after {
+                    if other.labels.contains(label) {
+                        edge(other, system, label.as_ref());
+                    }
+                }
+                for label in system.before {
+                    if other.labels.contains(label) {
+                        edge(system, other, label.as_ref());
+                    }
+                }
+            }
+        }
+    }
+}
+
+///
+/// ラベルの表示名（関数をそのままラベルにした場合は `None`）
+///
+fn label_name(label: &dyn SystemLabel) -> Option<String> {
+    let name = format!("{:?}", label);
+    if name.starts_with("SystemTypeIdLabel") {
+        None
+    } else {
+        Some(short_type_name(name.trim_matches('"')))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::tracing::{self, info_span};
+
+    use super::*;
+
+    /// `frames` フレーム分の span を `SystemTraceLayer` に記録する
+    fn trace_frames(capacity: usize, frames: usize) -> SystemTrace {
+        let (layer, trace) = SystemTraceLayer::new(capacity);
+        let subscriber = Registry::default().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..frames {
+                let _frame = info_span!("frame").entered();
+                let stage = info_span!("stage", name = ?"update").entered();
+                info_span!("system", name = "input").in_scope(|| {});
+                info_span!("system", name = "movement").in_scope(|| {});
+                info_span!("system_commands", name = "movement").in_scope(|| {});
+                drop(stage);
+                info_span!("exclusive_system", name = "cleanup").in_scope(|| {});
+                // 記録しない span
+                info_span!("other", name = "ignored").in_scope(|| {});
+            }
+        });
+        trace
+    }
+
+    #[test]
+    fn records_spans_of_each_frame() {
+        let trace = trace_frames(4, 1);
+        let frame = trace.last_frame().unwrap();
+        assert_eq!(frame.index, 0);
+
+        let events: Vec<_> = frame
+            .events
+            .iter()
+            .map(|event| (event.kind, event.name.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (TraceKind::Stage, "update"),
+                (TraceKind::System, "input"),
+                (TraceKind::System, "movement"),
+                (TraceKind::Commands, "movement"),
+                (TraceKind::ExclusiveSystem, "cleanup"),
+            ]
+        );
+        let systems: Vec<_> = frame.systems().map(|event| event.name.as_str()).collect();
+        assert_eq!(systems, ["input", "movement", "cleanup"]);
+        assert_eq!(frame.stages().count(), 1);
+
+        let stage = frame.stages().next().unwrap();
+        assert!(frame.start <= stage.start);
+        assert!(stage.start + stage.duration <= frame.start + frame.duration);
+        assert_eq!(frame.threads.len(), 1);
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest_frames() {
+        let trace = trace_frames(3, 5);
+        let indices: Vec<_> = trace.frames().iter().map(|frame| frame.index).collect();
+        assert_eq!(indices, [2, 3, 4]);
+        assert_eq!(trace.last_frame().unwrap().index, 4);
+        assert!(trace.frame(1).is_none());
+        assert_eq!(trace.frame(3).unwrap().systems().count(), 3);
+
+        trace.clear();
+        assert!(trace.frames().is_empty());
+
+        // 0 の場合は何も残さない
+        assert!(trace_frames(0, 2).frames().is_empty());
+    }
+
+    #[test]
+    fn chrome_trace_json() {
+        let frame = TraceFrame {
+            index: 7,
+            start: 100.0,
+            duration: 50.0,
+            thread: 0,
+            events: vec![
+                TraceEvent {
+                    kind: TraceKind::Stage,
+                    name: "Update".to_string(),
+                    start: 110.0,
+                    duration: 30.0,
+                    thread: 0,
+                },
+                TraceEvent {
+                    kind: TraceKind::System,
+                    name: "movement".to_string(),
+                    start: 115.0,
+                    duration: 10.0,
+                    thread: 1,
+                },
+            ],
+            threads: vec![
+                (0, "main".to_string()),
+                (1, "Compute Task Pool (0)".to_string()),
+            ],
+        };
+
+        let json: serde_json::Value =
+            serde_json::from_str(&frame.to_chrome_trace().unwrap()).unwrap();
+        assert_eq!(json["displayTimeUnit"], "ms");
+        let events = json["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 5);
+
+        assert_eq!(
+            events[0],
+            serde_json::json!({
+                "name": "frame 7", "cat": "frame", "ph": "X",
+                "ts": 100.0, "dur": 50.0, "pid": 1, "tid": 0,
+            })
+        );
+        assert_eq!(
+            events[2],
+            serde_json::json!({
+                "name": "movement", "cat": "system", "ph": "X",
+                "ts": 115.0, "dur": 10.0, "pid": 1, "tid": 1,
+            })
+        );
+        assert_eq!(events[1]["cat"], "stage");
+        assert_eq!(
+            events[4],
+            serde_json::json!({
+                "name": "thread_name", "cat": "__metadata", "ph": "M",
+                "ts": 0.0, "pid": 1, "tid": 1,
+                "args": { "name": "Compute Task Pool (0)" },
+            })
+        );
+    }
+
+    #[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+    enum TestSystem {
+        Input,
+        Movement,
+    }
+
+    fn input() {}
+    fn movement() {}
+    fn collision() {}
+    fn cleanup(_world: &mut World) {}
+    fn render() {}
+
+    /// `name` を表示するノードの ID
+    fn node_id<'a>(dot: &'a str, name: &str) -> &'a str {
+        let label = format!("[label = \"{}", name);
+        dot.lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("system_") && line.contains(&label))
+            .and_then(|line| line.split(' ').next())
+            .unwrap_or_else(|| panic!("{} is not in\n{}", name, dot))
+    }
+
+    #[test]
+    fn schedule_dot_shows_stages_and_ordering() {
+        let mut schedule = Schedule::default()
+            .with_stage(
+                "update",
+                SystemStage::parallel()
+                    .with_system(collision.after(TestSystem::Movement))
+                    .with_system(
+                        movement
+                            .label(TestSystem::Movement)
+                            .after(TestSystem::Input),
+                    )
+                    .with_system(input.label(TestSystem::Input))
+                    .with_system(cleanup.exclusive_system().at_end()),
+            )
+            .with_stage("render", SystemStage::single_threaded().with_system(render));
+        // 入れ子のスケジュール
+        schedule.add_stage_before(
+            "update",
+            "startup",
+            Schedule::default().with_stage("startup_inner", SystemStage::parallel()),
+        );
+
+        let dot = schedule_to_dot(&schedule);
+        assert!(dot.starts_with("digraph schedule {\n"), "{}", dot);
+        assert!(dot.ends_with("}\n"), "{}", dot);
+
+        let input = node_id(&dot, "input\\n[Input]");
+        let movement = node_id(&dot, "movement\\n[Movement]");
+        let collision = node_id(&dot, "collision\"");
+        let lines: Vec<_> = dot.lines().map(str::trim).collect();
+        let has = |line: String| lines.contains(&line.as_str());
+        assert!(
+            has(format!("{} -> {} [label = \"Input\"];", input, movement)),
+            "{}",
+            dot
+        );
+        assert!(
+            has(format!(
+                "{} -> {} [label = \"Movement\"];",
+                movement, collision
+            )),
+            "{}",
+            dot
+        );
+        assert!(
+            !has(format!("{} -> {} [label = \"Input\"];", input, collision)),
+            "{}",
+            dot
+        );
+
+        // 排他システムは塗りつぶす
+        assert!(
+            dot.contains("[label = \"cleanup\", style = filled, fillcolor = gray85];"),
+            "{}",
+            dot
+        );
+
+        // ステージは実行順に太い矢印でつなぐ
+        let header = |name: &str| {
+            let label = format!("[label = \"{}\", shape = plaintext];", name);
+            lines
+                .iter()
+                .find(|line| line.ends_with(&label))
+                .and_then(|line| line.split(' ').next())
+                .unwrap_or_else(|| panic!("{} is not in\n{}", name, dot))
+        };
+        let (startup, update, render) = (header("startup"), header("update"), header("render"));
+        assert!(header("startup_inner").starts_with("stage_"));
+        let bold = |from: &str, to: &str| {
+            has(format!(
+                "{} -> {} [style = bold, color = gray40];",
+                from, to
+            ))
+        };
+        assert!(bold(startup, update), "{}", dot);
+        assert!(bold(update, render), "{}", dot);
+        assert!(!bold(startup, render), "{}", dot);
+    }
+}
@@ -0,0 +1,370 @@
+//
+// タッチ操作用の仮想ジョイスティックとタップ入力
+//
+
+use bevy::prelude::*;
+
+use crate::cursor::{CursorSystem, TouchPositions};
+
+/// タップと判定する最大の押下時間（秒）
+const TAP_MAX_DURATION: f64 = 0.25;
+/// タップと判定する最大の移動距離（ピクセル）
+const TAP_MAX_DISTANCE: f32 = 20.0;
+
+///
+/// 仮想ジョイスティックとタップ判定を行うプラグイン
+///
+/// `CursorPlugin` と一緒に追加してください。
+///
+pub struct TouchControlPlugin;
+
+impl Plugin for TouchControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TapEvent>().add_system_set_to_stage(
+            CoreStage::PreUpdate,
+            SystemSet::new()
+                .after(CursorSystem::Touch)
+                .before(CursorSystem::Cursor)
+                .with_system(joystick_system)
+                .with_system(tap_system.after(joystick_system)),
+        );
+    }
+}
+
+///
+/// 画面をタップした時に送られるイベント
+///
+#[derive(Debug, Clone, Copy)]
+pub struct TapEvent {
+    pub id: u64,
+    pub world_pos: Vec2,
+}
+
+///
+/// 仮想ジョイスティック
+///
+/// 円形のUIノードに付けると、その範囲内で開始したタッチを占有して
+/// `axis` に -1.0 ~ 1.0 の入力値を書き込みます。
+///
+#[derive(Component, Debug)]
+pub struct VirtualJoystick {
+    /// ノブを動かせる半径（ピクセル）
+    pub radius: f32,
+    /// 入力を無視する割合 0.0 ~ 1.0
+    pub dead_zone: f32,
+    /// 現在の入力値
+    pub axis: Vec2,
+    touch: Option<u64>,
+}
+
+impl VirtualJoystick {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            dead_zone: 0.1,
+            axis: Vec2::ZERO,
+            touch: None,
+        }
+    }
+}
+
+///
+/// ジョイスティックのノブ（ジョイスティックの子として配置）
+///
+#[derive(Component)]
+pub struct JoystickKnob;
+
+///
+/// 画面左下に仮想ジョイスティックを配置する
+///
+pub fn spawn_joystick(commands: &mut Commands, radius: f32, knob_radius: f32) -> Entity {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(40.0),
+                    bottom: Val::Px(40.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(radius * 2.0), Val::Px(radius * 2.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(1.0, 1.0, 1.0, 0.15).into(),
+            ..Default::default()
+        })
+        .insert(VirtualJoystick::new(radius))
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: knob_position(Vec2::ZERO, radius, knob_radius),
+                        size: Size::new(Val::Px(knob_radius * 2.0), Val::Px(knob_radius * 2.0)),
+                        ..Default::default()
+                    },
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.5).into(),
+                    ..Default::default()
+                })
+                .insert(JoystickKnob);
+        })
+        .id()
+}
+
+fn knob_position(axis: Vec2, radius: f32, knob_radius: f32) -> Rect<Val> {
+    Rect {
+        left: Val::Px(radius - knob_radius + axis.x * radius),
+        bottom: Val::Px(radius - knob_radius + axis.y * radius),
+        ..Default::default()
+    }
+}
+
+fn joystick_system(
+    mut positions: ResMut<TouchPositions>,
+    mut joystick_query: Query<(&mut VirtualJoystick, &Node, &GlobalTransform, &Children)>,
+    mut knob_query: Query<(&mut Style, &Node), With<JoystickKnob>>,
+) {
+    for (mut joystick, node, global_transform, children) in joystick_query.iter_mut() {
+        let center = global_transform.translation.truncate();
+
+        // 指が離れたら入力を解除
+        let active = joystick
+            .touch
+            .and_then(|id| positions.get(id))
+            .filter(|touch| !touch.released)
+            .is_some();
+        if !active {
+            joystick.touch = None;
+        }
+
+        // 範囲内で新しく触れた指を占有する
+        if joystick.touch.is_none() {
+            let half = node.size / 2.0;
+            let new_touch = positions.iter().find_map(|(id, touch)| {
+                let offset = touch.start_screen - center;
+                let inside = offset.x.abs() <= half.x && offset.y.abs() <= half.y;
+                (inside && !touch.claimed && !touch.released).then_some(id)
+            });
+            joystick.touch = new_touch;
+        }
+
+        let axis = match joystick.touch {
+            Some(id) => {
+                positions.claim(id);
+                let screen = positions.get(id).map_or(center, |touch| touch.screen);
+                let axis = ((screen - center) / joystick.radius).clamp_length_max(1.0);
+                if axis.length() < joystick.dead_zone {
+                    Vec2::ZERO
+                } else {
+                    axis
+                }
+            }
+            None => Vec2::ZERO,
+        };
+        joystick.axis = axis;
+
+        for child in children.iter() {
+            if let Ok((mut style, knob)) = knob_query.get_mut(*child) {
+                style.position = knob_position(axis, joystick.radius, knob.size.x / 2.0);
+            }
+        }
+    }
+}
+
+fn tap_system(positions: Res<TouchPositions>, time: Res<Time>, mut taps: EventWriter<TapEvent>) {
+    for (id, touch) in positions.iter() {
+        if !touch.released || touch.claimed {
+            continue;
+        }
+
+        let duration = time.seconds_since_startup() - touch.start_time;
+        let distance = touch.screen.distance(touch.start_screen);
+
+        if duration <= TAP_MAX_DURATION && distance <= TAP_MAX_DISTANCE {
+            taps.send(TapEvent {
+                id,
+                world_pos: touch.world,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::event::{Events, ManualEventReader},
+        input::{
+            touch::{TouchInput, TouchPhase},
+            InputPlugin,
+        },
+    };
+
+    use super::*;
+    use crate::cursor::{CursorPlugin, MousePos};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .init_resource::<Windows>()
+            .add_plugin(CursorPlugin)
+            .add_plugin(TouchControlPlugin);
+        app
+    }
+
+    /// 中心 (100, 100)、半径 50 のジョイスティック
+    fn spawn_test_joystick(app: &mut App) -> Entity {
+        app.world
+            .spawn()
+            .insert(VirtualJoystick::new(50.0))
+            .insert(Node {
+                size: Vec2::new(100.0, 100.0),
+            })
+            .insert(GlobalTransform::from_translation(Vec3::new(
+                100.0, 100.0, 0.0,
+            )))
+            .with_children(|parent| {
+                parent
+                    .spawn()
+                    .insert(Style::default())
+                    .insert(Node {
+                        size: Vec2::new(20.0, 20.0),
+                    })
+                    .insert(JoystickKnob);
+            })
+            .id()
+    }
+
+    fn touch(app: &mut App, id: u64, phase: TouchPhase, x: f32, y: f32) {
+        app.world
+            .resource_mut::<Events<TouchInput>>()
+            .send(TouchInput {
+                phase,
+                position: Vec2::new(x, y),
+                force: None,
+                id,
+            });
+    }
+
+    fn taps(app: &App, reader: &mut ManualEventReader<TapEvent>) -> Vec<(u64, Vec2)> {
+        reader
+            .iter(app.world.resource::<Events<TapEvent>>())
+            .map(|tap| (tap.id, tap.world_pos))
+            .collect()
+    }
+
+    #[test]
+    fn joystick_claims_touch_started_inside() {
+        let mut app = app();
+        let joystick = spawn_test_joystick(&mut app);
+
+        touch(&mut app, 1, TouchPhase::Started, 110.0, 100.0);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Moved, 175.0, 100.0);
+        app.update();
+
+        let axis = app.world.get::<VirtualJoystick>(joystick).unwrap().axis;
+        assert_eq!(axis, Vec2::X);
+        let positions = app.world.resource::<TouchPositions>();
+        assert!(positions.get(1).unwrap().claimed);
+        assert!(positions.pointer().is_none());
+
+        // 指を離すと入力が解除される
+        touch(&mut app, 1, TouchPhase::Ended, 175.0, 100.0);
+        app.update();
+        let axis = app.world.get::<VirtualJoystick>(joystick).unwrap().axis;
+        assert_eq!(axis, Vec2::ZERO);
+    }
+
+    #[test]
+    fn joystick_ignores_touch_started_outside() {
+        let mut app = app();
+        let joystick = spawn_test_joystick(&mut app);
+
+        touch(&mut app, 1, TouchPhase::Started, 300.0, 100.0);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Moved, 100.0, 100.0);
+        app.update();
+
+        let axis = app.world.get::<VirtualJoystick>(joystick).unwrap().axis;
+        assert_eq!(axis, Vec2::ZERO);
+        assert!(
+            !app.world
+                .resource::<TouchPositions>()
+                .get(1)
+                .unwrap()
+                .claimed
+        );
+    }
+
+    #[test]
+    fn short_touch_is_tap() {
+        let mut app = app();
+        let mut reader = Default::default();
+
+        touch(&mut app, 3, TouchPhase::Started, 400.0, 300.0);
+        app.update();
+        assert!(taps(&app, &mut reader).is_empty());
+
+        touch(&mut app, 3, TouchPhase::Ended, 405.0, 300.0);
+        app.update();
+        assert_eq!(taps(&app, &mut reader), vec![(3, Vec2::new(405.0, 300.0))]);
+
+        // 離された指は次のフレームで消える
+        app.update();
+        assert!(app.world.resource::<TouchPositions>().get(3).is_none());
+        assert!(taps(&app, &mut reader).is_empty());
+    }
+
+    #[test]
+    fn dragged_or_claimed_touch_is_not_tap() {
+        let mut app = app();
+        spawn_test_joystick(&mut app);
+        let mut reader = Default::default();
+
+        // ドラッグ
+        touch(&mut app, 1, TouchPhase::Started, 400.0, 300.0);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Ended, 400.0, 400.0);
+        app.update();
+
+        // ジョイスティックの操作
+        touch(&mut app, 2, TouchPhase::Started, 100.0, 100.0);
+        app.update();
+        touch(&mut app, 2, TouchPhase::Ended, 100.0, 100.0);
+        app.update();
+
+        assert!(taps(&app, &mut reader).is_empty());
+    }
+
+    #[test]
+    fn fingers_are_tracked_separately() {
+        let mut app = app();
+        let joystick = spawn_test_joystick(&mut app);
+        let mut reader = Default::default();
+
+        // 1本目でジョイスティックを操作しながら、2本目で狙う
+        touch(&mut app, 1, TouchPhase::Started, 100.0, 100.0);
+        touch(&mut app, 2, TouchPhase::Started, 400.0, 300.0);
+        app.update();
+        touch(&mut app, 1, TouchPhase::Moved, 100.0, 150.0);
+        touch(&mut app, 2, TouchPhase::Moved, 410.0, 310.0);
+        app.update();
+
+        let axis = app.world.get::<VirtualJoystick>(joystick).unwrap().axis;
+        assert_eq!(axis, Vec2::Y);
+        let positions = app.world.resource::<TouchPositions>();
+        assert_eq!(positions.get(1).unwrap().screen, Vec2::new(100.0, 150.0));
+        assert_eq!(positions.get(2).unwrap().screen, Vec2::new(410.0, 310.0));
+        assert_eq!(positions.pointer().map(|(id, _)| id), Some(2));
+        // カメラが無いのでウィンドウ座標のまま
+        assert_eq!(app.world.resource::<MousePos>().0, Vec2::new(410.0, 310.0));
+
+        // 2本目を離してもジョイスティックの入力は続く
+        touch(&mut app, 2, TouchPhase::Ended, 410.0, 310.0);
+        app.update();
+        assert_eq!(taps(&app, &mut reader), vec![(2, Vec2::new(410.0, 310.0))]);
+        let axis = app.world.get::<VirtualJoystick>(joystick).unwrap().axis;
+        assert_eq!(axis, Vec2::Y);
+    }
+}
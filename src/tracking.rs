@@ -0,0 +1,474 @@
+//
+// ターゲットを追跡するミサイル
+//
+// ミサイルの数が多い場合に備えて、ターゲットの位置を移動の前に `TargetSnapshot` に集め、
+// 移動は `par_for_each_mut` で `batch_size` 個ずつ並列に行います（スレッドが1つの場合は順番に行います）。
+//
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    prelude::*,
+    tasks::ComputeTaskPool,
+};
+
+use crate::cursor::MousePos;
+use crate::diagnostics::DiagnosticsAppExt;
+use crate::look_at::LookTarget;
+use crate::pause::gameplay_timestep;
+use crate::TIME_STEP;
+
+///
+/// 追跡ミサイルを動かすプラグイン
+///
+pub struct TrackingPlugin {
+    /// 並列に処理する時の1タスクあたりのミサイルの数
+    pub batch_size: usize,
+}
+
+impl Default for TrackingPlugin {
+    fn default() -> Self {
+        Self { batch_size: 256 }
+    }
+}
+
+impl TrackingPlugin {
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// 存在している追跡ミサイルの数
+    pub const HOMING_PROJECTILES: DiagnosticId =
+        DiagnosticId::from_u128(0x8b2e_41d7_6c09_4a3f_b5e8_1f74_c9a2_0d61);
+    /// 1ステップで旋回した角度の平均（度）
+    pub const STEERING_ANGLE: DiagnosticId =
+        DiagnosticId::from_u128(0x8b2e_41d7_6c09_4a3f_b5e8_1f74_c9a2_0d62);
+}
+
+impl Plugin for TrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TrackingConfig {
+            batch_size: self.batch_size,
+        })
+        .init_resource::<TargetSnapshot>()
+        .add_component_count_diagnostic::<TrackingMissile>(
+            Self::HOMING_PROJECTILES,
+            "homing_projectiles",
+        )
+        .add_diagnostic(
+            Diagnostic::new(Self::STEERING_ANGLE, "steering_angle", 20).with_suffix("°"),
+        )
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(gameplay_timestep)
+                .with_system(
+                    target_snapshot_system
+                        .label(TargetSnapshotSystem)
+                        .before(TrackingSystem),
+                )
+                .with_system(tracking_missile_movement.label(TrackingSystem)),
+        );
+    }
+}
+
+///
+/// 実行中に変更できる追跡ミサイルの設定
+///
+#[derive(Debug, Clone)]
+pub struct TrackingConfig {
+    /// 並列に処理する時の1タスクあたりのミサイルの数（0 の場合は 1 として扱う）
+    pub batch_size: usize,
+}
+
+///
+/// ターゲットの位置を集めたもの
+///
+/// ミサイルごとにクエリで位置を引く代わりに、移動の前に一度だけ集めておきます。
+/// エンティティの番号で直接引けるように、位置は番号の位置に置きます。
+///
+#[derive(Debug, Default)]
+pub struct TargetSnapshot {
+    positions: Vec<Option<(Entity, Vec2)>>,
+    /// `positions` で値を入れた番号
+    filled: Vec<usize>,
+    cursor: Option<Vec2>,
+}
+
+impl TargetSnapshot {
+    ///
+    /// `targets` のうちエンティティのターゲットの位置を集め直す
+    ///
+    /// 位置が無い（消えた）エンティティは含まれません。
+    ///
+    pub fn rebuild(
+        &mut self,
+        targets: impl IntoIterator<Item = LookTarget>,
+        cursor: Option<Vec2>,
+        transforms: &Query<&GlobalTransform>,
+    ) {
+        for index in self.filled.drain(..) {
+            self.positions[index] = None;
+        }
+        self.cursor = cursor;
+
+        for target in targets {
+            let entity = match target {
+                LookTarget::Entity(entity) => entity,
+                _ => continue,
+            };
+            let index = entity.id() as usize;
+            if matches!(self.positions.get(index), Some(Some((filled, _))) if *filled == entity) {
+                continue;
+            }
+            if let Ok(transform) = transforms.get(entity) {
+                if index >= self.positions.len() {
+                    self.positions.resize(index + 1, None);
+                }
+                self.positions[index] = Some((entity, transform.translation.truncate()));
+                self.filled.push(index);
+            }
+        }
+    }
+
+    /// ターゲットのワールド座標
+    pub fn position(&self, target: LookTarget) -> Option<Vec2> {
+        match target {
+            LookTarget::Entity(entity) => match self.positions.get(entity.id() as usize) {
+                Some(Some((filled, position))) if *filled == entity => Some(*position),
+                _ => None,
+            },
+            LookTarget::Point(point) => Some(point),
+            LookTarget::Cursor => self.cursor,
+        }
+    }
+
+    /// 集めたエンティティの数
+    pub fn len(&self) -> usize {
+        self.filled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filled.is_empty()
+    }
+}
+
+///
+/// `tracking_missile_movement` のラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackingSystem;
+
+///
+/// `TargetSnapshot` を集めるシステムのラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetSnapshotSystem;
+
+///
+/// ターゲットに向かって旋回しながら進むミサイル
+///
+#[derive(Component, Debug, Clone)]
+pub struct TrackingMissile {
+    /// 回転速度 (rad/s)
+    pub rotation_speed: f32,
+    pub speed: f32,
+    pub target: LookTarget,
+}
+
+impl TrackingMissile {
+    pub fn new(target: LookTarget) -> Self {
+        Self {
+            rotation_speed: 90_f32.to_radians(),
+            speed: 180.0,
+            target,
+        }
+    }
+}
+
+///
+/// `target` の方向へ最大 `max_angle` だけ旋回させ、旋回した角度を返す
+///
+/// 前方向は +Y です。
+///
+pub fn steer_towards(tf: &mut Transform, target: Vec2, max_angle: f32) -> f32 {
+    let missile_translation = tf.translation.truncate();
+    // 進んでいる座標方向
+    let missile_forward = (tf.rotation * Vec3::Y).truncate();
+
+    // ミサイルからターゲットへのベクトル
+    let to_target = (target - missile_translation).normalize_or_zero();
+    if to_target == Vec2::ZERO {
+        return 0.0;
+    }
+
+    // 前方ベクトルとターゲットへの方向の間の内積を取得
+    let forward_dot_target = missile_forward.dot(to_target);
+
+    // 内積が約1.0の場合、すでにターゲットに直面しているため、早期に終了できます。
+    if (forward_dot_target - 1.0).abs() < f32::EPSILON {
+        return 0.0;
+    }
+
+    // ミサイルの右ベクトルを2Dで取得（すでに単位長）
+    let missile_right = (tf.rotation * Vec3::X).truncate();
+
+    // ミサイルの右ベクトルとターゲットへの方向の内積を取得します。
+    // 内積が負の場合は反時計回りに回転する必要があり、正の場合は時計回りに回転する必要があります。
+    // ドット積が0.0の場合でも、 `copysign`は1.0を返すことに注意してください
+    // （ターゲットが真後ろにいるため、右のベクトルに垂直であるため）。
+    let right_dot_target = missile_right.dot(to_target);
+
+    // 2D bevy座標系は、画面の外を指している+ Zを中心に回転するため、ここで符号を無効にする必要があります。
+    // 右手の法則により、+ Zを中心とした正の回転は反時計回りで、負の回転は時計回りです。
+    let rotation_sign = -f32::copysign(1.0, right_dot_target);
+
+    // ターゲットをオーバーシュートしないように回転を制限します。
+    // ここで内積を角度に変換して、回転角を固定できるようにする必要があります。
+    let overshoot_angle = forward_dot_target.clamp(-1.0, 1.0).acos();
+
+    // 制限付きの回転角を計算して回転させる
+    let rotation_angle = rotation_sign * max_angle.min(overshoot_angle);
+    tf.rotation *= Quat::from_rotation_z(rotation_angle);
+    rotation_angle
+}
+
+///
+/// 前方向（+Y）に `distance` だけ進める
+///
+pub fn move_forward(tf: &mut Transform, distance: f32) {
+    let movement_direction = tf.rotation * Vec3::Y;
+    tf.translation += movement_direction * distance;
+}
+
+fn target_snapshot_system(
+    mouse_pos: Option<Res<MousePos>>,
+    mut snapshot: ResMut<TargetSnapshot>,
+    missiles: Query<&TrackingMissile>,
+    transforms: Query<&GlobalTransform>,
+) {
+    snapshot.rebuild(
+        missiles
+            .iter()
+            .map(|tracking_missile| tracking_missile.target),
+        mouse_pos.map(|mouse_pos| mouse_pos.0),
+        &transforms,
+    );
+}
+
+fn tracking_missile_movement(
+    pool: Res<ComputeTaskPool>,
+    config: Res<TrackingConfig>,
+    snapshot: Res<TargetSnapshot>,
+    mut diagnostics: ResMut<Diagnostics>,
+    mut query: Query<(&mut Transform, &TrackingMissile)>,
+) {
+    // 旋回角度の合計（マイクロ度）と旋回したミサイルの数
+    let total_angle = AtomicU64::new(0);
+    let steered = AtomicUsize::new(0);
+
+    let update = |(mut tf, tracking_missile): (Mut<Transform>, &TrackingMissile)| {
+        // ターゲットが消えた場合は直進する
+        if let Some(target) = snapshot.position(tracking_missile.target) {
+            let angle = steer_towards(&mut tf, target, tracking_missile.rotation_speed * TIME_STEP);
+            let micro_degrees = (angle.abs().to_degrees() * 1e6) as u64;
+            total_angle.fetch_add(micro_degrees, Ordering::Relaxed);
+            steered.fetch_add(1, Ordering::Relaxed);
+        }
+
+        move_forward(&mut tf, tracking_missile.speed * TIME_STEP);
+    };
+
+    // スレッドが1つしか無い場合はタスクを分ける分だけ遅くなる
+    if pool.thread_num() > 1 {
+        query.par_for_each_mut(&pool, config.batch_size.max(1), update);
+    } else {
+        query.for_each_mut(update);
+    }
+
+    let steered = steered.into_inner();
+    if steered > 0 {
+        let average = total_angle.into_inner() as f64 / 1e6 / steered as f64;
+        diagnostics.add_measurement(TrackingPlugin::STEERING_ANGLE, average);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::TaskPoolBuilder;
+    use bevy::transform::TransformPlugin;
+
+    use super::*;
+    use crate::pause::{GameTime, Pause};
+
+    #[derive(Component)]
+    struct Target;
+
+    /// `threads` 個のスレッドで動かすアプリ
+    fn app(threads: usize) -> App {
+        let mut app = App::new();
+        app.insert_resource(ComputeTaskPool(
+            TaskPoolBuilder::new().num_threads(threads).build(),
+        ))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .init_resource::<Pause>()
+        .init_resource::<GameTime>()
+        .add_plugin(TrackingPlugin::default().with_batch_size(8));
+        app
+    }
+
+    fn spawn_target(app: &mut App, position: Vec2) -> Entity {
+        app.world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(
+                Transform::from_translation(position.extend(0.0)),
+            ))
+            .insert(Target)
+            .id()
+    }
+
+    fn spawn_missile(app: &mut App, position: Vec2, angle: f32, target: LookTarget) -> Entity {
+        app.world
+            .spawn()
+            .insert_bundle(TransformBundle::from_transform(Transform {
+                translation: position.extend(0.0),
+                rotation: Quat::from_rotation_z(angle),
+                ..Default::default()
+            }))
+            .insert(TrackingMissile::new(target))
+            .id()
+    }
+
+    /// 固定タイムステップ1回分だけ進める
+    fn step(app: &mut App) {
+        app.world
+            .resource_mut::<GameTime>()
+            .advance(TIME_STEP, false);
+        app.update();
+    }
+
+    /// ターゲットを原点の周りで円を描くように動かす
+    fn move_targets(app: &mut App, time: f32) {
+        let mut query = app.world.query_filtered::<&mut Transform, With<Target>>();
+        for mut tf in query.iter_mut(&mut app.world) {
+            tf.translation = Vec3::new(time.cos(), time.sin(), 0.0) * 200.0;
+        }
+    }
+
+    fn transform(app: &App, entity: Entity) -> Transform {
+        *app.world.get::<Transform>(entity).unwrap()
+    }
+
+    /// 64 発のミサイルを動くターゲットに向けて飛ばし、最後の位置を返す
+    fn run_swarm(threads: usize) -> (Vec<Transform>, Option<f64>) {
+        let mut app = app(threads);
+        let target = spawn_target(&mut app, Vec2::new(200.0, 0.0));
+        let missiles: Vec<_> = (0..64)
+            .map(|i| {
+                let angle = i as f32 * 0.37;
+                let position = Vec2::new(angle.cos(), angle.sin()) * (400.0 + i as f32 * 5.0);
+                spawn_missile(&mut app, position, angle, LookTarget::Entity(target))
+            })
+            .collect();
+
+        for frame in 0..60 {
+            move_targets(&mut app, frame as f32 * TIME_STEP);
+            step(&mut app);
+        }
+
+        let angle = app
+            .world
+            .resource::<Diagnostics>()
+            .get(TrackingPlugin::STEERING_ANGLE)
+            .and_then(|diagnostic| diagnostic.value());
+        (
+            missiles
+                .iter()
+                .map(|missile| transform(&app, *missile))
+                .collect(),
+            angle,
+        )
+    }
+
+    #[test]
+    fn parallel_update_matches_single_thread() {
+        let (single, single_angle) = run_swarm(1);
+        let (parallel, parallel_angle) = run_swarm(4);
+        assert_eq!(single, parallel);
+        assert_eq!(single_angle, parallel_angle);
+        assert!(single_angle.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn missile_closes_in_on_moving_target() {
+        let mut app = app(1);
+        let target = spawn_target(&mut app, Vec2::new(200.0, 0.0));
+        // 後ろ向きに発射しても旋回して追いつく
+        let missile = spawn_missile(
+            &mut app,
+            Vec2::new(-600.0, 0.0),
+            std::f32::consts::FRAC_PI_2,
+            LookTarget::Entity(target),
+        );
+        let distance = |app: &App| {
+            transform(app, missile)
+                .translation
+                .distance(transform(app, target).translation)
+        };
+
+        let start = distance(&app);
+        for frame in 0..240 {
+            move_targets(&mut app, frame as f32 * TIME_STEP * 0.5);
+            step(&mut app);
+        }
+        assert!(
+            distance(&app) < start * 0.5,
+            "{} -> {}",
+            start,
+            distance(&app)
+        );
+    }
+
+    #[test]
+    fn missile_flies_straight_after_target_is_despawned() {
+        let mut app = app(1);
+        let target = spawn_target(&mut app, Vec2::new(300.0, 300.0));
+        let missile = spawn_missile(&mut app, Vec2::ZERO, 0.0, LookTarget::Entity(target));
+        step(&mut app);
+        step(&mut app);
+
+        app.world.despawn(target);
+        let before = transform(&app, missile);
+        let speed = app.world.get::<TrackingMissile>(missile).unwrap().speed;
+        for _ in 0..10 {
+            step(&mut app);
+        }
+        let after = transform(&app, missile);
+        assert_eq!(after.rotation, before.rotation);
+        let expected = before.translation + before.rotation * Vec3::Y * speed * TIME_STEP * 10.0;
+        assert!(after.translation.distance(expected) < 1e-3);
+    }
+
+    #[test]
+    fn snapshot_drops_despawned_targets() {
+        let mut app = app(1);
+        let target = spawn_target(&mut app, Vec2::new(10.0, 20.0));
+        spawn_missile(&mut app, Vec2::ZERO, 0.0, LookTarget::Entity(target));
+        step(&mut app);
+        step(&mut app);
+        let snapshot = app.world.resource::<TargetSnapshot>();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(
+            snapshot.position(LookTarget::Entity(target)),
+            Some(Vec2::new(10.0, 20.0))
+        );
+
+        app.world.despawn(target);
+        step(&mut app);
+        let snapshot = app.world.resource::<TargetSnapshot>();
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.position(LookTarget::Entity(target)), None);
+    }
+}
@@ -0,0 +1,236 @@
+//
+// 船体に取り付けて独立して照準を合わせる砲塔
+//
+// 砲塔は船体の子エンティティとして配置し、`LookAt2d` で旋回させます。
+// 移動と回転は船体から引き継ぎ、照準はワールド座標の角度で判定します。
+//
+
+use bevy::prelude::*;
+
+use crate::cursor::MousePos;
+use crate::look_at::{
+    look_angle, target_position, wrap_angle, z_angle, LookAt2d, LookAtSystem, LookTarget,
+};
+
+///
+/// 砲塔の照準判定を行うプラグイン
+///
+/// `LookAtPlugin` と一緒に追加してください。
+///
+pub struct TurretPlugin;
+
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(turret_aim_system.after(LookAtSystem));
+    }
+}
+
+///
+/// 砲塔
+///
+/// 照準の誤差が `tolerance` 以内の間は `OnTarget` が付与されます。
+///
+#[derive(Component, Debug)]
+pub struct Turret {
+    /// 照準が合っているとみなす角度の誤差 (rad)
+    pub tolerance: f32,
+}
+
+///
+/// 照準が合っている砲塔に付与されるマーカー
+///
+#[derive(Component, Debug)]
+pub struct OnTarget;
+
+impl Turret {
+    ///
+    /// 砲塔の生成に必要なコンポーネント
+    ///
+    /// `arc` は船体に対する旋回範囲、`slew_rate` は旋回速度 (rad/s) です。
+    /// スプライトは `spawn_bundle(SpriteBundle { .. })` で別に追加します。
+    ///
+    pub fn bundle(target: LookTarget, arc: (f32, f32), slew_rate: f32) -> (Turret, LookAt2d) {
+        (
+            Turret {
+                tolerance: 3_f32.to_radians(),
+            },
+            LookAt2d::new(target)
+                .with_arc(arc.0, arc.1)
+                .with_max_angular_speed(slew_rate),
+        )
+    }
+}
+
+fn turret_aim_system(
+    mut commands: Commands,
+    mouse_pos: Option<Res<MousePos>>,
+    query: Query<(Entity, &Turret, &LookAt2d, &GlobalTransform, Option<&OnTarget>)>,
+    targets: Query<&GlobalTransform>,
+) {
+    for (entity, turret, look_at, global_tf, on_target) in query.iter() {
+        let aimed = target_position(look_at.target, mouse_pos.as_deref(), &targets)
+            .map(|target| {
+                // 船体の回転を含んだワールド座標での向きと比較する
+                let desired = look_angle(global_tf.translation.truncate(), target, look_at.forward);
+                let current = z_angle(global_tf.rotation);
+                wrap_angle(desired - current).abs() <= turret.tolerance
+            })
+            .unwrap_or(false);
+
+        match (aimed, on_target.is_some()) {
+            (true, false) => {
+                commands.entity(entity).insert(OnTarget);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<OnTarget>();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    use bevy::transform::TransformPlugin;
+
+    use super::*;
+    use crate::look_at::LookAtPlugin;
+    use crate::pause::GameTime;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<GameTime>()
+            .add_plugin(TransformPlugin)
+            .add_plugin(LookAtPlugin)
+            .add_plugin(TurretPlugin);
+        app
+    }
+
+    /// ゲーム内時間を `delta` 秒進めて1フレーム実行する
+    fn tick(app: &mut App, delta: f32) {
+        app.world.resource_mut::<GameTime>().advance(delta, false);
+        app.update();
+    }
+
+    /// 原点の船体（`hull_angle` 回転）に取り付けた前方 +Y の砲塔
+    fn spawn_turret(
+        app: &mut App,
+        hull_angle: f32,
+        turret: (Turret, LookAt2d),
+    ) -> (Entity, Entity) {
+        let turret = app
+            .world
+            .spawn()
+            .insert_bundle(turret)
+            .insert_bundle(TransformBundle::identity())
+            .id();
+        let hull = app
+            .world
+            .spawn()
+            .insert(Transform::from_rotation(Quat::from_rotation_z(hull_angle)))
+            .insert(GlobalTransform::identity())
+            .push_children(&[turret])
+            .id();
+        (hull, turret)
+    }
+
+    fn turret(target: Vec2, slew_rate: f32) -> (Turret, LookAt2d) {
+        Turret::bundle(
+            LookTarget::Point(target),
+            (-FRAC_PI_2, FRAC_PI_2),
+            slew_rate,
+        )
+    }
+
+    fn on_target(app: &App, turret: Entity) -> bool {
+        app.world.get::<OnTarget>(turret).is_some()
+    }
+
+    #[test]
+    fn marks_turret_on_target_after_slewing() {
+        let mut app = app();
+        let (_, turret) = spawn_turret(&mut app, 0.0, turret(Vec2::new(100.0, 0.0), FRAC_PI_2));
+
+        // 90° 旋回するのに1秒かかる
+        tick(&mut app, 0.5);
+        tick(&mut app, 0.0);
+        assert!(!on_target(&app, turret));
+
+        tick(&mut app, 0.5);
+        tick(&mut app, 0.0);
+        assert!(on_target(&app, turret));
+        let angle = z_angle(app.world.get::<Transform>(turret).unwrap().rotation);
+        assert!((angle + FRAC_PI_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn aims_in_world_space_on_rotated_hull() {
+        let mut app = app();
+        // 船体が 90° 回転しているので、砲塔は回転しなくても -X を向いている
+        let (_, turret) = spawn_turret(
+            &mut app,
+            FRAC_PI_2,
+            turret(Vec2::new(-100.0, 0.0), FRAC_PI_2),
+        );
+
+        for _ in 0..3 {
+            tick(&mut app, 0.1);
+        }
+        assert!(on_target(&app, turret));
+        let angle = z_angle(app.world.get::<Transform>(turret).unwrap().rotation);
+        assert!(angle.abs() < 1e-4);
+    }
+
+    #[test]
+    fn loses_target_when_hull_turns() {
+        let mut app = app();
+        let (hull, turret) = spawn_turret(&mut app, 0.0, turret(Vec2::new(0.0, 100.0), 0.0));
+        tick(&mut app, 0.1);
+        tick(&mut app, 0.1);
+        assert!(on_target(&app, turret));
+
+        // 旋回速度が 0 なので砲塔は追従できない
+        app.world.get_mut::<Transform>(hull).unwrap().rotation = Quat::from_rotation_z(0.5);
+        tick(&mut app, 0.1);
+        tick(&mut app, 0.1);
+        assert!(!on_target(&app, turret));
+    }
+
+    #[test]
+    fn uses_tolerance() {
+        let mut app = app();
+        let (_, turret) = spawn_turret(&mut app, 0.0, turret(Vec2::new(0.0, 100.0), 0.0));
+        let set_angle = |app: &mut App, degrees: f32| {
+            app.world.get_mut::<Transform>(turret).unwrap().rotation =
+                Quat::from_rotation_z(degrees.to_radians());
+            tick(app, 0.1);
+            tick(app, 0.1);
+        };
+
+        set_angle(&mut app, 2.0);
+        assert!(on_target(&app, turret));
+        set_angle(&mut app, -2.9);
+        assert!(on_target(&app, turret));
+        set_angle(&mut app, 5.0);
+        assert!(!on_target(&app, turret));
+        set_angle(&mut app, -3.1);
+        assert!(!on_target(&app, turret));
+    }
+
+    #[test]
+    fn never_on_target_outside_arc() {
+        let mut app = app();
+        let (_, turret) = spawn_turret(&mut app, 0.0, turret(Vec2::new(-10.0, -100.0), PI));
+
+        for _ in 0..20 {
+            tick(&mut app, 0.1);
+        }
+        assert!(!on_target(&app, turret));
+        // 近い方の端で止まる
+        let angle = z_angle(app.world.get::<Transform>(turret).unwrap().rotation);
+        assert!((angle - FRAC_PI_2).abs() < 1e-4);
+    }
+}
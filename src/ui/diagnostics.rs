@@ -0,0 +1,431 @@
+//
+// 診断情報のオーバーレイ
+//
+// キー（既定は F3）で表示を切り替え、フレーム時間のグラフと
+// `Diagnostics` に登録された全ての値、エンティティ数の多いアーキタイプを表示します。
+//
+// ```
+// app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+//     .add_plugin(GameDiagnosticsPlugin)
+//     .add_plugin(DiagnosticsOverlayPlugin::default());
+// ```
+//
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    ecs::{archetype::Archetypes, component::Components},
+    prelude::*,
+    transform::TransformSystem,
+};
+
+use super::DEFAULT_FONT;
+use crate::state_scoped::KeepOnExit;
+
+/// オーバーレイのZ座標（切り替え効果よりは奥）
+const OVERLAY_Z: f32 = 800.0;
+
+/// グラフに表示するフレーム数
+const GRAPH_FRAMES: usize = 60;
+
+/// グラフの高さに相当するフレーム時間（ミリ秒）
+const GRAPH_MAX_MS: f32 = 1000.0 / 30.0;
+
+/// 表示するアーキタイプの数
+const ARCHETYPE_ROWS: usize = 6;
+
+///
+/// 診断情報のオーバーレイを追加するプラグイン
+///
+pub struct DiagnosticsOverlayPlugin {
+    /// 表示を切り替えるキー
+    pub key: KeyCode,
+    pub font: String,
+    /// 最初から表示するか
+    pub visible: bool,
+}
+
+impl Default for DiagnosticsOverlayPlugin {
+    fn default() -> Self {
+        Self {
+            key: KeyCode::F3,
+            font: DEFAULT_FONT.to_owned(),
+            visible: false,
+        }
+    }
+}
+
+impl DiagnosticsOverlayPlugin {
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_font(mut self, font: impl Into<String>) -> Self {
+        self.font = font.into();
+        self
+    }
+
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+}
+
+impl Plugin for DiagnosticsOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Diagnostics>()
+            .insert_resource(DiagnosticsOverlay {
+                visible: self.visible,
+                key: self.key,
+                font: self.font.clone(),
+                graph: FrameGraph::new(GRAPH_FRAMES),
+            })
+            .add_system(overlay_toggle_system)
+            .add_system(overlay_spawn_system.after(overlay_toggle_system))
+            .add_system(overlay_text_system)
+            .add_system(overlay_graph_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                overlay_z_system.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+///
+/// オーバーレイの表示状態
+///
+pub struct DiagnosticsOverlay {
+    pub visible: bool,
+    key: KeyCode,
+    font: String,
+    graph: FrameGraph,
+}
+
+impl DiagnosticsOverlay {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// 最近のフレーム時間
+    pub fn graph(&self) -> &FrameGraph {
+        &self.graph
+    }
+}
+
+///
+/// 最近のフレーム時間（ミリ秒）
+///
+#[derive(Debug, Clone)]
+pub struct FrameGraph {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl FrameGraph {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, ms: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
+    }
+
+    /// 古い順のフレーム時間（足りない分は先頭を 0 で埋める）
+    pub fn bars(&self) -> impl Iterator<Item = f32> + '_ {
+        std::iter::repeat_n(0.0, self.capacity - self.samples.len())
+            .chain(self.samples.iter().copied())
+    }
+
+    ///
+    /// グラフの高さの割合（0.0〜1.0）
+    ///
+    pub fn height(ms: f32) -> f32 {
+        (ms / GRAPH_MAX_MS).clamp(0.0, 1.0)
+    }
+
+    ///
+    /// フレーム時間に応じた色（60FPS 以内は緑、30FPS 以内は黄、それより遅いと赤）
+    ///
+    pub fn color(ms: f32) -> Color {
+        if ms <= 1000.0 / 59.0 {
+            Color::GREEN
+        } else if ms <= 1000.0 / 29.0 {
+            Color::YELLOW
+        } else {
+            Color::RED
+        }
+    }
+}
+
+///
+/// 型名からモジュールのパスを取り除く
+///
+/// `alloc::vec::Vec<bevy_ecs::entity::Entity>` は `Vec<Entity>` に、
+/// `bevy_ecs::event::Events<T>::update_system` は `Events<T>::update_system` になります。
+///
+pub fn short_type_name(name: &str) -> String {
+    let push_segment = |short: &mut String, segment: &str| {
+        // 型の後に続く関数名の `::` は残す
+        if segment.starts_with("::") {
+            short.push_str("::");
+        }
+        short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    };
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            push_segment(&mut short, &segment);
+            segment.clear();
+            short.push(c);
+        }
+    }
+    push_segment(&mut short, &segment);
+    short
+}
+
+///
+/// エンティティ数の多いアーキタイプ（エンティティ数とコンポーネント名）
+///
+pub fn largest_archetypes(
+    archetypes: &Archetypes,
+    components: &Components,
+    limit: usize,
+) -> Vec<(usize, String)> {
+    let mut rows: Vec<(usize, String)> = archetypes
+        .iter()
+        .filter(|archetype| !archetype.is_empty())
+        .map(|archetype| {
+            let names: Vec<String> = archetype
+                .components()
+                .filter_map(|id| components.get_info(id))
+                .map(|info| short_type_name(info.name()))
+                .collect();
+            (archetype.len(), names.join(", "))
+        })
+        .collect();
+    rows.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    rows.truncate(limit);
+    rows
+}
+
+///
+/// 診断情報の一覧（FPS とフレーム時間を先頭に、残りは名前順）
+///
+pub fn diagnostics_text(diagnostics: &Diagnostics) -> String {
+    let mut text = String::new();
+    if let Some(fps) = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.average())
+    {
+        let _ = writeln!(text, "FPS {:.1}", fps);
+    }
+    if let Some(frame_time) = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|frame_time| frame_time.average())
+    {
+        let _ = writeln!(text, "frame {:.2} ms", frame_time * 1000.0);
+    }
+
+    let skip = [
+        FrameTimeDiagnosticsPlugin::FPS,
+        FrameTimeDiagnosticsPlugin::FRAME_TIME,
+        FrameTimeDiagnosticsPlugin::FRAME_COUNT,
+    ];
+    let mut others: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| !skip.contains(&diagnostic.id))
+        .collect();
+    others.sort_by(|a, b| a.name.cmp(&b.name));
+    for diagnostic in others {
+        let value = match diagnostic.value() {
+            Some(value) => value,
+            None => continue,
+        };
+        // 数は整数で、それ以外は小数点以下2桁で表示する
+        let precision = if value.fract() == 0.0 { 0 } else { 2 };
+        let _ = writeln!(
+            text,
+            "{} {:.*}{}",
+            diagnostic.name, precision, value, diagnostic.suffix
+        );
+    }
+    text
+}
+
+#[derive(Component)]
+struct OverlayRoot;
+
+#[derive(Component)]
+struct OverlayText;
+
+#[derive(Component)]
+struct OverlayBar(usize);
+
+fn overlay_toggle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut overlay: ResMut<DiagnosticsOverlay>,
+) {
+    if keyboard_input.just_pressed(overlay.key) {
+        overlay.toggle();
+    }
+    // 非表示の間も記録し、表示した時にすぐグラフが埋まるようにする
+    overlay.graph.push(time.delta_seconds() * 1000.0);
+}
+
+fn overlay_spawn_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    overlay: Res<DiagnosticsOverlay>,
+    roots: Query<Entity, With<OverlayRoot>>,
+) {
+    match (overlay.visible, roots.get_single()) {
+        (true, Err(_)) => {
+            let font = asset_server.load(overlay.font.as_str());
+            spawn_overlay(&mut commands, font);
+        }
+        (false, Ok(entity)) => commands.entity(entity).despawn_recursive(),
+        _ => {}
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands, font: Handle<Font>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(5.0),
+                    top: Val::Px(5.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                padding: Rect::all(Val::Px(6.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..Default::default()
+        })
+        .insert(OverlayRoot)
+        .insert(KeepOnExit)
+        .with_children(|parent| {
+            // フレーム時間のグラフ
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(GRAPH_FRAMES as f32 * 4.0), Val::Px(48.0)),
+                        align_items: AlignItems::FlexStart,
+                        margin: Rect {
+                            bottom: Val::Px(6.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: Color::rgba(1.0, 1.0, 1.0, 0.1).into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for i in 0..GRAPH_FRAMES {
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(3.0), Val::Percent(0.0)),
+                                    margin: Rect {
+                                        right: Val::Px(1.0),
+                                        ..Default::default()
+                                    },
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            })
+                            .insert(OverlayBar(i));
+                    }
+                });
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font,
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(OverlayText);
+        });
+}
+
+fn overlay_text_system(
+    diagnostics: Res<Diagnostics>,
+    archetypes: &Archetypes,
+    components: &Components,
+    mut texts: Query<&mut Text, With<OverlayText>>,
+) {
+    for mut text in texts.iter_mut() {
+        let mut value = diagnostics_text(&diagnostics);
+        value.push_str("\narchetypes\n");
+        for (count, names) in largest_archetypes(archetypes, components, ARCHETYPE_ROWS) {
+            let _ = writeln!(value, "{:>6} {}", count, names);
+        }
+        text.sections[0].value = value;
+    }
+}
+
+fn overlay_graph_system(
+    overlay: Res<DiagnosticsOverlay>,
+    mut bars: Query<(&OverlayBar, &mut Style, &mut UiColor)>,
+) {
+    if bars.is_empty() {
+        return;
+    }
+    let samples: Vec<f32> = overlay.graph.bars().collect();
+    for (bar, mut style, mut color) in bars.iter_mut() {
+        let ms = samples[bar.0];
+        style.size.height = Val::Percent(FrameGraph::height(ms) * 100.0);
+        color.0 = FrameGraph::color(ms);
+    }
+}
+
+///
+/// オーバーレイを他のUIより手前に表示する
+///
+/// 子のノードも同じだけ手前に移します。
+///
+fn overlay_z_system(
+    roots: Query<Entity, With<OverlayRoot>>,
+    children: Query<&Children>,
+    mut transforms: Query<&mut GlobalTransform>,
+) {
+    for root in roots.iter() {
+        let offset = match transforms.get(root) {
+            Ok(transform) => OVERLAY_Z - transform.translation.z,
+            Err(_) => continue,
+        };
+        let mut stack = vec![root];
+        while let Some(entity) = stack.pop() {
+            if let Ok(mut transform) = transforms.get_mut(entity) {
+                transform.translation.z += offset;
+            }
+            if let Ok(entity_children) = children.get(entity) {
+                stack.extend(entity_children.iter().copied());
+            }
+        }
+    }
+}
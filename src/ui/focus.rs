@@ -0,0 +1,439 @@
+//
+// キーボード・ゲームパッドによるUIのフォーカス移動
+//
+// 矢印キー / 十字キーで画面上の位置が近いボタンにフォーカスを移し、
+// Enter / Aボタンで決定します。マウスのクリックも同じ `ButtonActivated` に変換されるので、
+// 画面側はイベントを1つ読むだけで済みます。
+//
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+
+///
+/// フォーカス移動と決定を行うプラグイン
+///
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ButtonActivated>()
+            .init_resource::<FocusMemory>()
+            // レイアウトで決まった位置を見てから最初のフォーカスを選ぶ
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                focus_restore_system
+                    .label(FocusSystem::Restore)
+                    .after(TransformSystem::TransformPropagate),
+            )
+            .add_system(focus_navigation_system.label(FocusSystem::Navigate))
+            .add_system(focus_memory_system.after(FocusSystem::Navigate));
+    }
+}
+
+///
+/// `FocusPlugin` が追加するシステムのラベル
+///
+/// `ButtonActivated` を読むシステムは `Navigate` の後に実行してください。
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FocusSystem {
+    /// 画面が開かれた時にフォーカスを復元する（`PostUpdate` で位置の伝播の後に実行）
+    Restore,
+    Navigate,
+}
+
+///
+/// フォーカスできるUIノード
+///
+/// キーは画面を開き直した時にフォーカスを復元するために使います。
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Focusable(pub &'static str);
+
+///
+/// フォーカスの範囲となる画面のルートノード
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct FocusScope(pub &'static str);
+
+///
+/// フォーカスされているノードに付与されるマーカー
+///
+#[derive(Component, Debug)]
+pub struct Focused;
+
+///
+/// ボタンがクリック、または Enter / Aボタンで決定された時に送られるイベント
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ButtonActivated(pub Entity);
+
+///
+/// 画面ごとに最後にフォーカスしていたノードのキー
+///
+#[derive(Debug, Default)]
+pub struct FocusMemory(pub HashMap<&'static str, &'static str>);
+
+///
+/// `from` から `direction` の方向で最も近い候補を選ぶ
+///
+/// 進行方向からずれている候補ほど遠いものとして扱います。
+///
+pub fn navigate(
+    from: Vec2,
+    direction: Vec2,
+    candidates: impl IntoIterator<Item = (Entity, Vec2)>,
+) -> Option<Entity> {
+    candidates
+        .into_iter()
+        .filter_map(|(entity, position)| {
+            let offset = position - from;
+            let forward = offset.dot(direction);
+            if forward <= 0.0 {
+                return None;
+            }
+            let side = offset.perp_dot(direction).abs();
+            Some((entity, forward + side * 2.0))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+///
+/// 最初にフォーカスするノード（画面の左上）を選ぶ
+///
+pub fn first_focus(candidates: impl IntoIterator<Item = (Entity, Vec2)>) -> Option<Entity> {
+    candidates
+        .into_iter()
+        .min_by(|(_, a), (_, b)| {
+            // UI座標は上が +Y
+            b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x))
+        })
+        .map(|(entity, _)| entity)
+}
+
+/// `entity` が属する `FocusScope` を親をたどって探す
+fn scope_of(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    scopes: &Query<&FocusScope>,
+) -> Option<&'static str> {
+    let mut current = entity;
+    loop {
+        if let Ok(scope) = scopes.get(current) {
+            return Some(scope.0);
+        }
+        current = parents.get(current).ok()?.0;
+    }
+}
+
+pub(crate) fn navigation_input(
+    keyboard_input: &Input<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_input: &Input<GamepadButton>,
+) -> (Option<Vec2>, bool) {
+    let pressed = |key: KeyCode, button: GamepadButtonType| {
+        keyboard_input.just_pressed(key)
+            || gamepads
+                .iter()
+                .any(|gamepad| gamepad_input.just_pressed(GamepadButton(*gamepad, button)))
+    };
+
+    let direction = if pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        Some(Vec2::Y)
+    } else if pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        Some(-Vec2::Y)
+    } else if pressed(KeyCode::Left, GamepadButtonType::DPadLeft) {
+        Some(-Vec2::X)
+    } else if pressed(KeyCode::Right, GamepadButtonType::DPadRight) {
+        Some(Vec2::X)
+    } else {
+        None
+    };
+
+    let activate = pressed(KeyCode::Return, GamepadButtonType::South)
+        || keyboard_input.just_pressed(KeyCode::NumpadEnter);
+
+    (direction, activate)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn focus_navigation_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut activated: EventWriter<ButtonActivated>,
+    focusables: Query<(Entity, &GlobalTransform, Option<&Focused>), With<Focusable>>,
+    interactions: Query<(Entity, &Interaction), Changed<Interaction>>,
+    parents: Query<&Parent>,
+    scopes: Query<&FocusScope>,
+) {
+    let focused = focusables
+        .iter()
+        .find(|(_, _, focused)| focused.is_some())
+        .map(|(entity, global_tf, _)| (entity, global_tf.translation.truncate()));
+
+    let mut set_focus = |entity: Entity| {
+        if let Some((old, _)) = focused {
+            if old == entity {
+                return;
+            }
+            commands.entity(old).remove::<Focused>();
+        }
+        commands.entity(entity).insert(Focused);
+    };
+
+    // マウスでクリックしたボタンはフォーカスも移る
+    for (entity, interaction) in interactions.iter() {
+        if *interaction == Interaction::Clicked {
+            if focusables.get(entity).is_ok() {
+                set_focus(entity);
+            }
+            activated.send(ButtonActivated(entity));
+        }
+    }
+
+    let (direction, activate) = navigation_input(&keyboard_input, &gamepads, &gamepad_input);
+
+    match focused {
+        Some((entity, position)) => {
+            if activate {
+                activated.send(ButtonActivated(entity));
+            }
+
+            if let Some(direction) = direction {
+                // 同じ画面内のノードだけを候補にする
+                let scope = scope_of(entity, &parents, &scopes);
+                let candidates = focusables
+                    .iter()
+                    .filter(|(candidate, _, _)| {
+                        *candidate != entity && scope_of(*candidate, &parents, &scopes) == scope
+                    })
+                    .map(|(candidate, global_tf, _)| {
+                        (candidate, global_tf.translation.truncate())
+                    });
+
+                if let Some(next) = navigate(position, direction, candidates) {
+                    set_focus(next);
+                }
+            }
+        }
+        None => {
+            if direction.is_some() {
+                let candidates = focusables
+                    .iter()
+                    .map(|(entity, global_tf, _)| (entity, global_tf.translation.truncate()));
+                if let Some(first) = first_focus(candidates) {
+                    set_focus(first);
+                }
+            }
+        }
+    }
+}
+
+///
+/// 画面が開かれた時に前回のフォーカスを復元する
+///
+fn focus_restore_system(
+    mut commands: Commands,
+    memory: Res<FocusMemory>,
+    new_scopes: Query<(Entity, &FocusScope), Added<FocusScope>>,
+    focusables: Query<(Entity, &Focusable, &GlobalTransform)>,
+    focused: Query<Entity, With<Focused>>,
+    parents: Query<&Parent>,
+    scopes: Query<&FocusScope>,
+) {
+    for (scope_entity, scope) in new_scopes.iter() {
+        let in_scope: Vec<_> = focusables
+            .iter()
+            .filter(|(entity, _, _)| scope_of(*entity, &parents, &scopes) == Some(scope.0))
+            .collect();
+
+        let remembered = memory.0.get(scope.0).and_then(|key| {
+            in_scope
+                .iter()
+                .find(|(_, focusable, _)| focusable.0 == *key)
+                .map(|(entity, _, _)| *entity)
+        });
+
+        // 初めて開いた画面は先頭のボタンにフォーカスする
+        let target = remembered.or_else(|| {
+            first_focus(
+                in_scope
+                    .iter()
+                    .map(|(entity, _, global_tf)| (*entity, global_tf.translation.truncate())),
+            )
+        });
+
+        if let Some(target) = target {
+            for entity in focused.iter() {
+                commands.entity(entity).remove::<Focused>();
+            }
+            commands.entity(target).insert(Focused);
+        } else {
+            debug!("focus scope {:?} ({:?}) has no focusable nodes", scope.0, scope_entity);
+        }
+    }
+}
+
+fn focus_memory_system(
+    mut memory: ResMut<FocusMemory>,
+    focused: Query<(Entity, &Focusable), Added<Focused>>,
+    parents: Query<&Parent>,
+    scopes: Query<&FocusScope>,
+) {
+    for (entity, focusable) in focused.iter() {
+        if let Some(scope) = scope_of(entity, &parents, &scopes) {
+            memory.0.insert(scope, focusable.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+    use bevy::input::{keyboard::KeyboardInput, ElementState, InputPlugin};
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(InputPlugin)
+            .add_plugin(TransformPlugin)
+            .add_plugin(FocusPlugin);
+        app
+    }
+
+    /// `scope` の画面に 2x2 に並べたボタン（右下から順に生成する）
+    fn spawn_grid(app: &mut App, scope: &'static str) -> [Entity; 4] {
+        let mut spawn = |key: &'static str, x: f32, y: f32| {
+            app.world
+                .spawn()
+                .insert_bundle((
+                    Focusable(key),
+                    Transform::from_xyz(x, y, 0.0),
+                    GlobalTransform::default(),
+                ))
+                .id()
+        };
+        let bottom_right = spawn("bottom_right", 50.0, -50.0);
+        let bottom_left = spawn("bottom_left", -50.0, -50.0);
+        let top_right = spawn("top_right", 50.0, 50.0);
+        let top_left = spawn("top_left", -50.0, 50.0);
+
+        app.world
+            .spawn()
+            .insert_bundle((
+                FocusScope(scope),
+                Transform::default(),
+                GlobalTransform::default(),
+            ))
+            .push_children(&[bottom_right, bottom_left, top_right, top_left]);
+        [top_left, top_right, bottom_left, bottom_right]
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        for state in [ElementState::Pressed, ElementState::Released] {
+            app.world
+                .resource_mut::<Events<KeyboardInput>>()
+                .send(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(key),
+                    state,
+                });
+            app.update();
+        }
+    }
+
+    fn focused(app: &mut App) -> Entity {
+        let focused: Vec<_> = app
+            .world
+            .query_filtered::<Entity, With<Focused>>()
+            .iter(&app.world)
+            .collect();
+        assert_eq!(focused.len(), 1, "exactly one node should be focused");
+        focused[0]
+    }
+
+    #[test]
+    fn first_focus_uses_propagated_positions() {
+        let mut app = app();
+        let [top_left, ..] = spawn_grid(&mut app, "grid");
+        app.update();
+        assert_eq!(focused(&mut app), top_left);
+    }
+
+    #[test]
+    fn arrow_keys_follow_screen_positions() {
+        let mut app = app();
+        let [top_left, top_right, bottom_left, bottom_right] = spawn_grid(&mut app, "grid");
+        app.update();
+
+        press(&mut app, KeyCode::Right);
+        assert_eq!(focused(&mut app), top_right);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(focused(&mut app), bottom_right);
+        press(&mut app, KeyCode::Left);
+        assert_eq!(focused(&mut app), bottom_left);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(focused(&mut app), top_left);
+        // 先に候補が無い方向には動かない
+        press(&mut app, KeyCode::Up);
+        assert_eq!(focused(&mut app), top_left);
+    }
+
+    /// 画面のルート（ボタンの親）を子ごと消す
+    fn close(app: &mut App, button: Entity) {
+        let root = app.world.get::<Parent>(button).unwrap().0;
+        despawn_with_children_recursive(&mut app.world, root);
+        app.update();
+    }
+
+    #[test]
+    fn focus_is_restored_when_screen_is_reopened() {
+        let mut app = app();
+        let [top_left, _, _, bottom_right] = spawn_grid(&mut app, "grid");
+        app.update();
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(focused(&mut app), bottom_right);
+        assert_eq!(
+            app.world.resource::<FocusMemory>().0["grid"],
+            "bottom_right"
+        );
+        close(&mut app, top_left);
+
+        // 別の画面は先頭から
+        let [other_top_left, other_top_right, ..] = spawn_grid(&mut app, "other");
+        app.update();
+        assert_eq!(focused(&mut app), other_top_left);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(focused(&mut app), other_top_right);
+        close(&mut app, other_top_left);
+
+        // 戻ると前回のボタンにフォーカスする
+        let [.., bottom_right] = spawn_grid(&mut app, "grid");
+        app.update();
+        assert_eq!(focused(&mut app), bottom_right);
+
+        let memory = &app.world.resource::<FocusMemory>().0;
+        assert_eq!(memory["grid"], "bottom_right");
+        assert_eq!(memory["other"], "top_right");
+    }
+
+    #[test]
+    fn nan_positions_do_not_panic() {
+        let mut world = World::new();
+        let valid = world.spawn().id();
+        let broken = world.spawn().id();
+        let candidates = [
+            (broken, Vec2::new(f32::NAN, 10.0)),
+            (valid, Vec2::new(0.0, 10.0)),
+        ];
+        assert_eq!(navigate(Vec2::ZERO, Vec2::Y, candidates), Some(valid));
+        first_focus(candidates);
+    }
+}
@@ -0,0 +1,789 @@
+//
+// RONファイルに書いた画面レイアウトからUIを作る
+//
+// `*.screen.ron` を AssetServer で読み込み、`LayoutRoot` を付けたエンティティの子として
+// ウィジェットを作ります。ファイルが変更されると作り直します（ホットリロード）。
+// ホットリロードには `AssetServerSettings { watch_for_changes: true, .. }` が必要です。
+//
+// ```ron
+// #![enable(implicit_some)]
+// (
+//     scope: "menu",
+//     children: [
+//         Title(text: "menu.title"),
+//         Button(id: "play", text: "menu.play", action: "play"),
+//         Slider(id: "volume", text: "settings.volume", format: Percent),
+//     ],
+// )
+// ```
+//
+
+use std::sync::Mutex;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::EntityCommands,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::Deserialize;
+
+use super::focus::FocusScope;
+use super::widgets::{
+    button, dropdown, label, slider, text_input, title, toggle, Slider, Widget, WidgetTheme,
+};
+
+///
+/// 画面レイアウトの読み込みと作成を行うプラグイン
+///
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ScreenLayout>()
+            .init_asset_loader::<ScreenLayoutLoader>()
+            .add_system(layout_system);
+    }
+}
+
+///
+/// 大きさの指定
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Length {
+    Auto,
+    Px(f32),
+    Percent(f32),
+}
+
+impl From<Length> for Val {
+    fn from(length: Length) -> Self {
+        match length {
+            Length::Auto => Val::Auto,
+            Length::Px(px) => Val::Px(px),
+            Length::Percent(percent) => Val::Percent(percent),
+        }
+    }
+}
+
+///
+/// テーマの `Style` を上書きする設定
+///
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StyleDef {
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    pub margin: Option<Length>,
+    pub padding: Option<Length>,
+    /// 背景色 (r, g, b, a)
+    pub color: Option<(f32, f32, f32, f32)>,
+}
+
+impl StyleDef {
+    pub fn apply(&self, style: &mut Style) {
+        if let Some(width) = self.width {
+            style.size.width = width.into();
+        }
+        if let Some(height) = self.height {
+            style.size.height = height.into();
+        }
+        if let Some(margin) = self.margin {
+            style.margin = Rect::all(margin.into());
+        }
+        if let Some(padding) = self.padding {
+            style.padding = Rect::all(padding.into());
+        }
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color.map(|(r, g, b, a)| Color::rgba(r, g, b, a))
+    }
+}
+
+///
+/// スライダーの値の表示方法
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SliderFormat {
+    Decimal,
+    Percent,
+    /// 倍率 (x1.25)
+    Scale,
+}
+
+fn default_max() -> f32 {
+    1.0
+}
+
+fn default_step() -> f32 {
+    0.05
+}
+
+fn default_max_len() -> usize {
+    32
+}
+
+fn default_format() -> SliderFormat {
+    SliderFormat::Decimal
+}
+
+///
+/// レイアウトのノード
+///
+/// `text` は表示する文字列（文字列表のキー）、`id` はフォーカスの復元と `LayoutId` に使います。
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum LayoutNode {
+    /// 子を縦に並べる
+    Column {
+        children: Vec<LayoutNode>,
+        #[serde(default)]
+        style: StyleDef,
+    },
+    /// 子を横に並べる
+    Row {
+        children: Vec<LayoutNode>,
+        #[serde(default)]
+        style: StyleDef,
+    },
+    Title {
+        text: String,
+    },
+    Label {
+        text: String,
+    },
+    Button {
+        id: String,
+        text: String,
+        /// 押した時のアクション (`LayoutAction`)
+        #[serde(default)]
+        action: Option<String>,
+        #[serde(default)]
+        style: StyleDef,
+    },
+    Slider {
+        id: String,
+        text: String,
+        #[serde(default)]
+        value: f32,
+        #[serde(default)]
+        min: f32,
+        #[serde(default = "default_max")]
+        max: f32,
+        #[serde(default = "default_step")]
+        step: f32,
+        #[serde(default = "default_format")]
+        format: SliderFormat,
+    },
+    Toggle {
+        id: String,
+        text: String,
+        #[serde(default)]
+        value: bool,
+    },
+    Dropdown {
+        id: String,
+        text: String,
+        options: Vec<String>,
+        #[serde(default)]
+        selected: usize,
+    },
+    TextInput {
+        id: String,
+        text: String,
+        #[serde(default)]
+        value: String,
+        #[serde(default = "default_max_len")]
+        max_len: usize,
+    },
+}
+
+impl LayoutNode {
+    /// エラーメッセージに使うノードの名前
+    fn name(&self) -> String {
+        match self {
+            LayoutNode::Column { .. } => "Column".to_owned(),
+            LayoutNode::Row { .. } => "Row".to_owned(),
+            LayoutNode::Title { text } => format!("Title({:?})", text),
+            LayoutNode::Label { text } => format!("Label({:?})", text),
+            LayoutNode::Button { id, .. } => format!("Button({:?})", id),
+            LayoutNode::Slider { id, .. } => format!("Slider({:?})", id),
+            LayoutNode::Toggle { id, .. } => format!("Toggle({:?})", id),
+            LayoutNode::Dropdown { id, .. } => format!("Dropdown({:?})", id),
+            LayoutNode::TextInput { id, .. } => format!("TextInput({:?})", id),
+        }
+    }
+
+    fn id(&self) -> Option<&str> {
+        match self {
+            LayoutNode::Button { id, .. }
+            | LayoutNode::Slider { id, .. }
+            | LayoutNode::Toggle { id, .. }
+            | LayoutNode::Dropdown { id, .. }
+            | LayoutNode::TextInput { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    ///
+    /// ノードの内容を検査する
+    ///
+    /// エラーは `path` を先頭に付けた文字列で返します。
+    ///
+    fn validate(&self, path: &str, ids: &mut HashSet<String>) -> Result<(), String> {
+        let path = format!("{} {}", path, self.name());
+
+        if let Some(id) = self.id() {
+            if id.is_empty() {
+                return Err(format!("{}: id must not be empty", path));
+            }
+            if !ids.insert(id.to_owned()) {
+                return Err(format!("{}: duplicate id {:?}", path, id));
+            }
+        }
+
+        match self {
+            LayoutNode::Column { children, .. } | LayoutNode::Row { children, .. } => {
+                validate_children(children, &format!("{} > ", path), ids)?;
+            }
+            LayoutNode::Slider {
+                value,
+                min,
+                max,
+                step,
+                ..
+            } => {
+                if min >= max {
+                    return Err(format!(
+                        "{}: min ({}) must be less than max ({})",
+                        path, min, max
+                    ));
+                }
+                if *step < 0.0 {
+                    return Err(format!("{}: step ({}) must not be negative", path, step));
+                }
+                if value < min || value > max {
+                    return Err(format!(
+                        "{}: value ({}) is out of range {}..={}",
+                        path, value, min, max
+                    ));
+                }
+            }
+            LayoutNode::Dropdown {
+                options, selected, ..
+            } => {
+                if options.is_empty() {
+                    return Err(format!("{}: options must not be empty", path));
+                }
+                if *selected >= options.len() {
+                    return Err(format!(
+                        "{}: selected ({}) is out of range (options: {})",
+                        path,
+                        selected,
+                        options.len()
+                    ));
+                }
+            }
+            LayoutNode::Button {
+                action: Some(action),
+                ..
+            } if action.is_empty() => {
+                return Err(format!("{}: action must not be empty", path));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_children(
+    children: &[LayoutNode],
+    prefix: &str,
+    ids: &mut HashSet<String>,
+) -> Result<(), String> {
+    for (index, child) in children.iter().enumerate() {
+        child.validate(&format!("{}children[{}]", prefix, index), ids)?;
+    }
+    Ok(())
+}
+
+///
+/// 画面のレイアウト
+///
+#[derive(Debug, Clone, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "0c6f1d2a-8e4b-4a7c-b3d9-5f2e1a6c8b47"]
+pub struct ScreenLayout {
+    /// フォーカスの範囲 (`FocusScope`)
+    pub scope: String,
+    #[serde(default)]
+    pub style: StyleDef,
+    pub children: Vec<LayoutNode>,
+}
+
+impl ScreenLayout {
+    ///
+    /// レイアウトの内容を検査する
+    ///
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scope.is_empty() {
+            return Err("scope must not be empty".to_owned());
+        }
+        validate_children(&self.children, "", &mut HashSet::default())
+    }
+}
+
+///
+/// レイアウトファイルを読んで検査する
+///
+/// エラーにはファイルのパスとノードの位置が入ります。
+///
+fn parse_layout(path: &str, bytes: &[u8]) -> Result<ScreenLayout, anyhow::Error> {
+    let layout: ScreenLayout = ron::de::from_bytes(bytes)
+        .map_err(|e| anyhow::anyhow!("invalid screen layout {}: {}", path, e))?;
+    layout
+        .validate()
+        .map_err(|e| anyhow::anyhow!("invalid screen layout {}: {}", path, e))?;
+    Ok(layout)
+}
+
+#[derive(Default)]
+pub struct ScreenLayoutLoader;
+
+impl AssetLoader for ScreenLayoutLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().display().to_string();
+            let layout = parse_layout(&path, bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(layout));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["screen.ron"]
+    }
+}
+
+///
+/// レイアウトから作る画面のルート
+///
+/// レイアウトが読み込まれると子にウィジェットが作られます。
+///
+#[derive(Component, Debug, Clone)]
+pub struct LayoutRoot {
+    pub layout: Handle<ScreenLayout>,
+    built: bool,
+}
+
+impl LayoutRoot {
+    pub fn new(layout: Handle<ScreenLayout>) -> Self {
+        Self {
+            layout,
+            built: false,
+        }
+    }
+}
+
+///
+/// レイアウトの `id` が付いたウィジェット
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct LayoutId(pub String);
+
+///
+/// レイアウトの `action` が付いたボタン
+///
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct LayoutAction(pub String);
+
+///
+/// レイアウトから作る画面を用意する
+///
+/// 返り値に画面を消す時のマーカーなどを追加してください。
+///
+pub fn spawn_layout<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    layout: Handle<ScreenLayout>,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut root = commands.spawn_bundle(NodeBundle {
+        color: Color::NONE.into(),
+        ..Default::default()
+    });
+    root.insert(LayoutRoot::new(layout));
+    root
+}
+
+///
+/// `Focusable` などに使う文字列を `'static` にする
+///
+/// 文字列ごとに1回だけメモリを確保し、プログラムの終了まで解放しません。
+/// 同じ文字列は使い回すので、ホットリロードを繰り返しても増え続けませんが、
+/// 確保する量はこれまでに渡した異なる文字列の数に比例します。
+/// レイアウトの `id` や `scope` のような決まった文字列だけに使ってください。
+///
+pub fn intern(value: &str) -> &'static str {
+    static INTERNED: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+    let mut interned = INTERNED.lock().unwrap();
+    let interned = interned.get_or_insert_with(HashSet::default);
+    match interned.get(value) {
+        Some(value) => value,
+        None => {
+            let value: &'static str = Box::leak(value.to_owned().into_boxed_str());
+            interned.insert(value);
+            value
+        }
+    }
+}
+
+fn spawn_node(parent: &mut ChildBuilder, node: &LayoutNode, theme: &WidgetTheme) {
+    let mut entity = match node {
+        LayoutNode::Column { children, style } | LayoutNode::Row { children, style } => {
+            let mut node_style = Style {
+                flex_direction: if matches!(node, LayoutNode::Row { .. }) {
+                    FlexDirection::Row
+                } else {
+                    FlexDirection::ColumnReverse
+                },
+                align_items: AlignItems::Center,
+                ..Default::default()
+            };
+            style.apply(&mut node_style);
+
+            let mut entity = parent.spawn_bundle(NodeBundle {
+                style: node_style,
+                color: style.color().unwrap_or(Color::NONE).into(),
+                ..Default::default()
+            });
+            entity.with_children(|parent| {
+                for child in children {
+                    spawn_node(parent, child, theme);
+                }
+            });
+            entity
+        }
+        LayoutNode::Title { text } => title(text).spawn(parent, theme),
+        LayoutNode::Label { text } => label(text).spawn(parent, theme),
+        LayoutNode::Button {
+            id,
+            text,
+            action,
+            style,
+        } => {
+            let mut button_style = theme.button.clone();
+            style.apply(&mut button_style);
+
+            let mut entity = button(intern(id), text).spawn(parent, theme);
+            entity.insert(button_style);
+            if let Some(action) = action {
+                entity.insert(LayoutAction(action.clone()));
+            }
+            entity
+        }
+        LayoutNode::Slider {
+            id,
+            text,
+            value,
+            min,
+            max,
+            step,
+            format,
+        } => {
+            let widget = Slider::new(*value, *min, *max, *step).with_format(match format {
+                SliderFormat::Decimal => |value| format!("{:.2}", value),
+                SliderFormat::Percent => |value| format!("{:.0}%", value * 100.0),
+                SliderFormat::Scale => |value| format!("x{:.2}", value),
+            });
+            slider(intern(id), text, widget).spawn(parent, theme)
+        }
+        LayoutNode::Toggle { id, text, value } => {
+            toggle(intern(id), text, *value).spawn(parent, theme)
+        }
+        LayoutNode::Dropdown {
+            id,
+            text,
+            options,
+            selected,
+        } => dropdown(intern(id), text, options.iter().cloned(), *selected).spawn(parent, theme),
+        LayoutNode::TextInput {
+            id,
+            text,
+            value,
+            max_len,
+        } => text_input(intern(id), text, value.clone())
+            .with_max_len(*max_len)
+            .spawn(parent, theme),
+    };
+
+    if let Some(id) = node.id() {
+        entity.insert(LayoutId(id.to_owned()));
+    }
+}
+
+///
+/// 読み込まれた・変更されたレイアウトから画面を作る
+///
+fn layout_system(
+    mut commands: Commands,
+    theme: Res<WidgetTheme>,
+    layouts: Res<Assets<ScreenLayout>>,
+    mut events: EventReader<AssetEvent<ScreenLayout>>,
+    mut roots: Query<(Entity, &mut LayoutRoot)>,
+) {
+    let modified: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, mut root) in roots.iter_mut() {
+        let reload = modified.contains(&root.layout);
+        if root.built && !reload {
+            continue;
+        }
+
+        let layout = match layouts.get(&root.layout) {
+            Some(layout) => layout,
+            None => continue,
+        };
+
+        if reload {
+            info!("reloading screen layout {:?}", layout.scope);
+        }
+        root.built = true;
+
+        let mut style = Style {
+            margin: Rect::all(Val::Auto),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        };
+        layout.style.apply(&mut style);
+        let color = layout.style.color().unwrap_or(theme.panel_color);
+
+        let mut root_commands = commands.entity(entity);
+        root_commands.despawn_descendants();
+        // FocusScope を追加し直してフォーカスを復元させる
+        root_commands
+            .remove::<FocusScope>()
+            .insert(style)
+            .insert(UiColor(color))
+            .insert(FocusScope(intern(&layout.scope)))
+            .with_children(|parent| {
+                for child in layout.children.iter() {
+                    spawn_node(parent, child, &theme);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetPlugin;
+
+    use super::*;
+
+    fn parse(ron: &str) -> Result<ScreenLayout, String> {
+        parse_layout("ui/test.screen.ron", ron.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn error_names_file_and_node() {
+        let error = parse(
+            r#"(
+                scope: "settings",
+                children: [
+                    Title(text: "settings.title"),
+                    Column(children: [
+                        Slider(id: "volume", text: "settings.volume", min: 1.0, max: 0.0),
+                    ]),
+                ],
+            )"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("ui/test.screen.ron"), "{}", error);
+        assert!(
+            error.contains(r#"children[1] Column > children[0] Slider("volume")"#),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("min (1) must be less than max (0)"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_ids_and_bad_options() {
+        let error = parse(
+            r#"(scope: "menu", children: [
+                Button(id: "play", text: "menu.play"),
+                Row(children: [Toggle(id: "play", text: "menu.play")]),
+            ])"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.contains(r#"children[1] Row > children[0] Toggle("play"): duplicate id "play""#),
+            "{}",
+            error
+        );
+
+        let error = parse(
+            r#"(scope: "menu", children: [
+                Dropdown(id: "language", text: "settings.language", options: ["ja"], selected: 1),
+            ])"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.contains(r#"children[0] Dropdown("language"): selected (1) is out of range"#),
+            "{}",
+            error
+        );
+
+        let error = parse(r#"(scope: "", children: [])"#).unwrap_err();
+        assert!(error.contains("scope must not be empty"), "{}", error);
+    }
+
+    #[test]
+    fn syntax_error_names_file() {
+        let error = parse(r#"(scope: "menu", children: [Button(id: "play")])"#).unwrap_err();
+        assert!(
+            error.starts_with("invalid screen layout ui/test.screen.ron:"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn bundled_layouts_are_valid() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/ui");
+        for name in ["menu", "pause", "settings"] {
+            let path = dir.join(format!("{}.screen.ron", name));
+            let bytes = std::fs::read(&path).unwrap();
+            let layout = parse_layout(&path.display().to_string(), &bytes).unwrap();
+            assert_eq!(layout.scope, name);
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .init_resource::<WidgetTheme>()
+            .add_plugin(LayoutPlugin);
+        app
+    }
+
+    /// ルートの子孫の `LayoutId` を作られた順に集める
+    fn layout_ids(app: &App, entity: Entity) -> Vec<String> {
+        let mut ids = Vec::new();
+        if let Some(id) = app.world.get::<LayoutId>(entity) {
+            ids.push(id.0.clone());
+        }
+        if let Some(children) = app.world.get::<Children>(entity) {
+            for child in children.iter() {
+                ids.extend(layout_ids(app, *child));
+            }
+        }
+        ids
+    }
+
+    #[test]
+    fn layout_round_trips_to_widgets() {
+        let layout = parse(
+            r#"#![enable(implicit_some)]
+            (
+                scope: "settings",
+                children: [
+                    Title(text: "settings.title"),
+                    Row(children: [
+                        Slider(id: "volume", text: "settings.volume", value: 0.5, format: Percent),
+                        Toggle(id: "fullscreen", text: "settings.fullscreen", value: true),
+                    ]),
+                    Dropdown(id: "language", text: "settings.language", options: ["ja", "en"]),
+                    TextInput(id: "name", text: "settings.name", max_len: 8),
+                    Button(id: "back", text: "settings.back", action: "back"),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(layout.scope, "settings");
+        assert_eq!(layout.children.len(), 5);
+        assert_eq!(
+            layout.children[1],
+            LayoutNode::Row {
+                children: vec![
+                    LayoutNode::Slider {
+                        id: "volume".to_owned(),
+                        text: "settings.volume".to_owned(),
+                        value: 0.5,
+                        min: 0.0,
+                        max: 1.0,
+                        step: 0.05,
+                        format: SliderFormat::Percent,
+                    },
+                    LayoutNode::Toggle {
+                        id: "fullscreen".to_owned(),
+                        text: "settings.fullscreen".to_owned(),
+                        value: true,
+                    },
+                ],
+                style: StyleDef::default(),
+            }
+        );
+
+        let mut app = app();
+        let handle = app.world.resource_mut::<Assets<ScreenLayout>>().add(layout);
+        let root = app
+            .world
+            .spawn()
+            .insert(LayoutRoot::new(handle.clone()))
+            .id();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<FocusScope>(root).map(|scope| scope.0),
+            Some("settings")
+        );
+        assert_eq!(
+            layout_ids(&app, root),
+            ["volume", "fullscreen", "language", "name", "back"]
+        );
+        let mut actions = app.world.query::<(&LayoutId, &LayoutAction)>();
+        let actions: Vec<_> = actions
+            .iter(&app.world)
+            .map(|(id, action)| (id.0.clone(), action.0.clone()))
+            .collect();
+        assert_eq!(actions, [("back".to_owned(), "back".to_owned())]);
+
+        // 変更されると作り直す
+        app.world
+            .resource_mut::<Assets<ScreenLayout>>()
+            .get_mut(&handle)
+            .unwrap()
+            .children
+            .truncate(1);
+        app.update();
+        app.update();
+        assert!(layout_ids(&app, root).is_empty());
+    }
+
+    #[test]
+    fn intern_reuses_strings() {
+        let a = intern(&String::from("layout-test-id"));
+        let b = intern(&String::from("layout-test-id"));
+        assert_eq!(a, "layout-test-id");
+        assert!(std::ptr::eq(a, b));
+        assert!(!std::ptr::eq(a, intern("layout-test-other")));
+    }
+}
@@ -0,0 +1,16 @@
+//
+// メニュー画面などのUI
+//
+// 既定のフォントはリポジトリに含まれていないので、`assets/fonts` に置いてください。
+// 見つからない場合は文字が表示されないだけで、読み込み画面などは先に進みます。
+//
+
+pub mod diagnostics;
+pub mod focus;
+pub mod layout;
+pub mod splash;
+pub mod transition;
+pub mod widgets;
+
+/// UIの既定のフォント
+pub const DEFAULT_FONT: &str = "fonts/NotoSansJP-Medium.otf";
@@ -0,0 +1,680 @@
+//
+// スプラッシュ画面（ロゴなどのスライドを順番に表示する）
+//
+// スライドは画像か文字で、表示時間・フェードイン / フェードアウトの時間と曲線・背景色を指定します。
+// キー・クリック・タッチ・ゲームパッドのボタンで現在のスライドを飛ばせます。
+// 画像の読み込みに失敗したスライドは飛ばすので、最後まで進まなくなることはありません。
+//
+// ```
+// app.add_plugin(SplashPlugin::new(
+//     GameState::Splash,
+//     GameState::Menu,
+//     vec![
+//         Slide::text("splash.presents", DEFAULT_FONT, 2.0),
+//         Slide::image("textures/logo.png", 2.5).with_background(Color::WHITE),
+//     ],
+// ));
+// ```
+//
+
+use bevy::{asset::LoadState, ecs::schedule::StateData, prelude::*};
+
+use super::transition::Transition;
+use crate::localization::LocalizedText;
+
+/// 画像の読み込みを待つ最大の時間（秒）
+const MAX_LOAD_WAIT: f32 = 3.0;
+
+///
+/// スプラッシュ画面を表示するプラグイン
+///
+/// `state` に入るとスライドを表示し、全て表示し終わると `next` に移ります。
+/// `TransitionPlugin` を追加している場合は切り替え効果を使って移ります。
+///
+/// 表示の進行はリソース1つで管理するので、追加できるのは1つだけです。
+///
+pub struct SplashPlugin<S> {
+    pub state: S,
+    pub next: S,
+    pub slides: Vec<Slide>,
+}
+
+impl<S: StateData> SplashPlugin<S> {
+    pub fn new(state: S, next: S, slides: Vec<Slide>) -> Self {
+        Self {
+            state,
+            next,
+            slides,
+        }
+    }
+}
+
+impl<S: StateData> Plugin for SplashPlugin<S> {
+    fn build(&self, app: &mut App) {
+        // 状態の型が違っても進行のリソースを共有してしまうので2つ目は受け付けない
+        assert!(
+            !app.world.contains_resource::<SplashAssets>(),
+            "SplashPlugin can only be added once"
+        );
+
+        app.insert_resource(SplashConfig {
+            next: self.next.clone(),
+            slides: self.slides.clone(),
+        })
+        .insert_resource(SplashSequence::new(Vec::new()))
+        .insert_resource(SplashAssets(Vec::new()))
+        .insert_resource(SplashDirty(false))
+        .add_system_set(SystemSet::on_enter(self.state.clone()).with_system(splash_setup::<S>))
+        .add_system_set(
+            SystemSet::on_update(self.state.clone())
+                .with_system(splash_skip_system.before(splash_system::<S>))
+                .with_system(splash_system::<S>),
+        )
+        .add_system_set(SystemSet::on_exit(self.state.clone()).with_system(splash_cleanup));
+    }
+}
+
+///
+/// フェードの曲線
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FadeCurve {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl FadeCurve {
+    /// 進み具合 `t` (0.0 ~ 1.0) に対する不透明度
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EaseIn => t * t,
+            FadeCurve::EaseOut => t * (2.0 - t),
+            FadeCurve::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    /// 不透明度が `alpha` になる進み具合（`apply` の逆）
+    pub fn inverse(&self, alpha: f32) -> f32 {
+        // どの曲線も単調増加なので二分法で求める
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..24 {
+            let mid = (low + high) * 0.5;
+            if self.apply(mid) < alpha {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (low + high) * 0.5
+    }
+}
+
+///
+/// スライドに表示するもの
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlideContent {
+    /// 画像のパス
+    Image(String),
+    /// 文字（文字列表のキー）とフォントのパス
+    Text {
+        text: String,
+        font: String,
+        font_size: f32,
+        color: Color,
+    },
+}
+
+///
+/// 1枚のスライド
+///
+/// `duration` はフェードの時間を含む表示時間（秒）です。
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slide {
+    pub content: SlideContent,
+    pub duration: f32,
+    pub fade_in: f32,
+    pub fade_out: f32,
+    pub fade_in_curve: FadeCurve,
+    pub fade_out_curve: FadeCurve,
+    pub background: Color,
+}
+
+impl Slide {
+    pub fn new(content: SlideContent, duration: f32) -> Self {
+        Self {
+            content,
+            duration,
+            fade_in: 0.5,
+            fade_out: 0.5,
+            fade_in_curve: FadeCurve::EaseOut,
+            fade_out_curve: FadeCurve::EaseIn,
+            background: Color::BLACK,
+        }
+    }
+
+    pub fn image(path: impl Into<String>, duration: f32) -> Self {
+        Self::new(SlideContent::Image(path.into()), duration)
+    }
+
+    pub fn text(text: impl Into<String>, font: impl Into<String>, duration: f32) -> Self {
+        Self::new(
+            SlideContent::Text {
+                text: text.into(),
+                font: font.into(),
+                font_size: 64.0,
+                color: Color::WHITE,
+            },
+            duration,
+        )
+    }
+
+    pub fn with_fade(mut self, fade_in: f32, fade_out: f32) -> Self {
+        self.fade_in = fade_in;
+        self.fade_out = fade_out;
+        self
+    }
+
+    pub fn with_curves(mut self, fade_in_curve: FadeCurve, fade_out_curve: FadeCurve) -> Self {
+        self.fade_in_curve = fade_in_curve;
+        self.fade_out_curve = fade_out_curve;
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// 表示を始めてから `time` 秒後の不透明度
+    pub fn alpha(&self, time: f32) -> f32 {
+        let fade_out_start = self.duration - self.fade_out;
+        if time < self.fade_in {
+            self.fade_in_curve.apply(time / self.fade_in)
+        } else if time > fade_out_start && self.fade_out > 0.0 {
+            self.fade_out_curve
+                .apply((self.duration - time) / self.fade_out)
+        } else {
+            1.0
+        }
+    }
+}
+
+///
+/// スライドの素材の読み込み状況
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlideStatus {
+    Loading,
+    Ready,
+    Failed,
+}
+
+///
+/// スライドの表示の進行
+///
+/// 時間を進める処理だけを持つので、ウィンドウが無くても動かせます。
+///
+#[derive(Debug, Clone)]
+pub struct SplashSequence {
+    slides: Vec<Slide>,
+    index: usize,
+    time: f32,
+    wait: f32,
+}
+
+impl SplashSequence {
+    pub fn new(slides: Vec<Slide>) -> Self {
+        Self {
+            slides,
+            index: 0,
+            time: 0.0,
+            wait: 0.0,
+        }
+    }
+
+    /// 表示中のスライドの番号
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&self) -> Option<&Slide> {
+        self.slides.get(self.index)
+    }
+
+    /// 表示中のスライドの経過時間（秒）
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.slides.len()
+    }
+
+    /// 表示中のスライドの不透明度
+    pub fn alpha(&self) -> f32 {
+        self.current().map_or(0.0, |slide| slide.alpha(self.time))
+    }
+
+    ///
+    /// 表示中のスライドを飛ばす
+    ///
+    /// 途中で消えないよう、今の不透明度からフェードアウトします。
+    ///
+    pub fn skip(&mut self) {
+        if let Some(slide) = self.current() {
+            let remaining = slide.fade_out_curve.inverse(self.alpha());
+            let fade_out_from = slide.duration - slide.fade_out * remaining;
+            self.time = self.time.max(fade_out_from);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.index += 1;
+        self.time = 0.0;
+        self.wait = 0.0;
+    }
+
+    ///
+    /// 時間を `delta` 秒進める
+    ///
+    /// `status` は表示中のスライドの素材の読み込み状況です。
+    /// 次のスライドに進んだ場合は `true` を返します。
+    ///
+    pub fn tick(&mut self, delta: f32, status: SlideStatus) -> bool {
+        let duration = match self.current() {
+            Some(slide) => slide.duration,
+            None => return false,
+        };
+
+        match status {
+            SlideStatus::Failed => {
+                self.advance();
+                return true;
+            }
+            SlideStatus::Loading if self.wait < MAX_LOAD_WAIT => {
+                // 読み込みが終わるまで時間を止める
+                self.wait += delta;
+                return false;
+            }
+            _ => {}
+        }
+
+        self.time += delta;
+        if self.time >= duration {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+struct SplashConfig<S> {
+    next: S,
+    slides: Vec<Slide>,
+}
+
+/// スライドの素材（画像スライドの場合のみ）
+struct SplashAssets(Vec<Option<Handle<Image>>>);
+
+#[derive(Component)]
+struct SplashRoot;
+
+#[derive(Component)]
+struct SplashContent;
+
+/// スライドが切り替わったので表示を作り直す
+struct SplashDirty(bool);
+
+fn splash_setup<S: StateData>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<SplashConfig<S>>,
+    mut assets: ResMut<SplashAssets>,
+    mut sequence: ResMut<SplashSequence>,
+    mut dirty: ResMut<SplashDirty>,
+) {
+    // 全ての画像を先に読み込み始める
+    assets.0 = config
+        .slides
+        .iter()
+        .map(|slide| match &slide.content {
+            SlideContent::Image(path) => Some(asset_server.load(path.as_str())),
+            SlideContent::Text { .. } => None,
+        })
+        .collect();
+
+    *sequence = SplashSequence::new(config.slides.clone());
+    dirty.0 = true;
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(SplashRoot);
+}
+
+fn splash_cleanup(
+    mut commands: Commands,
+    mut assets: ResMut<SplashAssets>,
+    roots: Query<Entity, With<SplashRoot>>,
+) {
+    for entity in roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    // 画像を解放する
+    assets.0.clear();
+}
+
+///
+/// 何かのキー・ボタンが押されたらスライドを飛ばす
+///
+fn splash_skip_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    touches: Res<Touches>,
+    mut sequence: ResMut<SplashSequence>,
+) {
+    let pressed = keyboard_input.get_just_pressed().len() > 0
+        || mouse_input.get_just_pressed().len() > 0
+        || gamepad_input.get_just_pressed().len() > 0
+        || touches.iter_just_pressed().next().is_some();
+
+    if pressed {
+        sequence.skip();
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn splash_system<S: StateData>(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    config: Res<SplashConfig<S>>,
+    assets: Res<SplashAssets>,
+    mut sequence: ResMut<SplashSequence>,
+    mut dirty: ResMut<SplashDirty>,
+    mut state: ResMut<State<S>>,
+    transition: Option<ResMut<Transition<S>>>,
+    mut roots: Query<(Entity, &mut UiColor), (With<SplashRoot>, Without<SplashContent>)>,
+    mut contents: Query<(Entity, Option<&mut UiColor>, Option<&mut Text>), With<SplashContent>>,
+) {
+    let status = match assets.0.get(sequence.index()) {
+        Some(Some(handle)) => match asset_server.get_load_state(handle) {
+            LoadState::Loaded => SlideStatus::Ready,
+            LoadState::Failed => {
+                warn!(
+                    "skipping splash slide {}: failed to load image",
+                    sequence.index()
+                );
+                SlideStatus::Failed
+            }
+            _ => SlideStatus::Loading,
+        },
+        _ => SlideStatus::Ready,
+    };
+
+    if sequence.tick(time.delta_seconds(), status) {
+        dirty.0 = true;
+    }
+
+    if sequence.is_finished() {
+        // TransitionPlugin がある場合は切り替え効果を使う
+        match transition {
+            Some(mut transition) => {
+                transition.request(config.next.clone());
+            }
+            None => {
+                // 既に遷移中の場合は何もしない
+                let _ = state.set(config.next.clone());
+            }
+        }
+        return;
+    }
+
+    let (root, mut background) = match roots.get_single_mut() {
+        Ok(root) => root,
+        Err(_) => return,
+    };
+    let slide = sequence.current().unwrap();
+    let alpha = sequence.alpha();
+
+    if dirty.0 {
+        dirty.0 = false;
+        for (entity, _, _) in contents.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        background.0 = slide.background;
+        spawn_content(
+            &mut commands,
+            root,
+            slide,
+            &assets.0[sequence.index()],
+            &asset_server,
+        );
+        return;
+    }
+
+    for (_, color, text) in contents.iter_mut() {
+        if let Some(mut color) = color {
+            color.0.set_a(alpha);
+        }
+        if let Some(mut text) = text {
+            for section in text.sections.iter_mut() {
+                section.style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+fn spawn_content(
+    commands: &mut Commands,
+    root: Entity,
+    slide: &Slide,
+    image: &Option<Handle<Image>>,
+    asset_server: &AssetServer,
+) {
+    commands
+        .entity(root)
+        .with_children(|parent| match (&slide.content, image) {
+            (SlideContent::Image(_), Some(image)) => {
+                parent
+                    .spawn_bundle(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(256.0), Val::Auto),
+                            ..Default::default()
+                        },
+                        image: UiImage(image.clone()),
+                        color: UiColor(Color::rgba(1.0, 1.0, 1.0, 0.0)),
+                        ..Default::default()
+                    })
+                    .insert(SplashContent);
+            }
+            (
+                SlideContent::Text {
+                    text,
+                    font,
+                    font_size,
+                    color,
+                },
+                _,
+            ) => {
+                let mut color = *color;
+                color.set_a(0.0);
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            text.clone(),
+                            TextStyle {
+                                font: asset_server.load(font.as_str()),
+                                font_size: *font_size,
+                                color,
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(LocalizedText(text.clone()))
+                    .insert(SplashContent);
+            }
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetPlugin;
+    use bevy::ecs::event::Events;
+    use bevy::input::{keyboard::KeyboardInput, ElementState, InputPlugin};
+
+    use super::*;
+
+    fn linear(duration: f32) -> Slide {
+        Slide::text("splash.test", "fonts/test.otf", duration)
+            .with_curves(FadeCurve::Linear, FadeCurve::Linear)
+    }
+
+    #[test]
+    fn slides_advance_after_their_duration() {
+        let mut sequence = SplashSequence::new(vec![linear(1.0), linear(0.5)]);
+        for _ in 0..3 {
+            assert!(!sequence.tick(0.25, SlideStatus::Ready));
+        }
+        assert!(sequence.tick(0.25, SlideStatus::Ready));
+        assert_eq!(sequence.index(), 1);
+        assert_eq!(sequence.time(), 0.0);
+
+        assert!(!sequence.tick(0.25, SlideStatus::Ready));
+        assert!(sequence.tick(0.25, SlideStatus::Ready));
+        assert!(sequence.is_finished());
+        assert!(!sequence.tick(0.25, SlideStatus::Ready));
+    }
+
+    #[test]
+    fn fade_follows_the_curves() {
+        let mut sequence = SplashSequence::new(vec![linear(2.0)]);
+        assert_eq!(sequence.alpha(), 0.0);
+        sequence.tick(0.25, SlideStatus::Ready);
+        assert_eq!(sequence.alpha(), 0.5);
+        sequence.tick(0.75, SlideStatus::Ready);
+        assert_eq!(sequence.alpha(), 1.0);
+        sequence.tick(0.75, SlideStatus::Ready);
+        assert_eq!(sequence.alpha(), 0.5);
+    }
+
+    #[test]
+    fn loading_slide_waits_until_timeout() {
+        let mut sequence = SplashSequence::new(vec![linear(1.0)]);
+        let mut waited = 0.0;
+        while waited < MAX_LOAD_WAIT {
+            sequence.tick(0.25, SlideStatus::Loading);
+            waited += 0.25;
+            assert_eq!(sequence.time(), 0.0);
+        }
+        // 待ちきったら読み込み中でも表示を進める
+        sequence.tick(0.25, SlideStatus::Loading);
+        assert_eq!(sequence.time(), 0.25);
+    }
+
+    #[test]
+    fn failed_slide_is_skipped() {
+        let mut sequence = SplashSequence::new(vec![linear(1.0), linear(1.0)]);
+        assert!(sequence.tick(0.25, SlideStatus::Failed));
+        assert_eq!(sequence.index(), 1);
+        assert_eq!(sequence.time(), 0.0);
+    }
+
+    #[test]
+    fn skip_fades_out_from_current_alpha() {
+        // 完全に表示されている時はフェードアウトの始めに飛ぶ
+        let mut sequence = SplashSequence::new(vec![linear(2.0)]);
+        sequence.tick(1.0, SlideStatus::Ready);
+        sequence.skip();
+        assert_eq!(sequence.time(), 1.5);
+
+        // フェードイン中は同じ不透明度から消えていく
+        let mut sequence = SplashSequence::new(vec![linear(2.0)]);
+        sequence.tick(0.25, SlideStatus::Ready);
+        sequence.skip();
+        assert!((sequence.alpha() - 0.5).abs() < 1e-4);
+        assert!((sequence.time() - 1.75).abs() < 1e-4);
+
+        // フェードアウト中は戻らない
+        let mut sequence = SplashSequence::new(vec![linear(2.0)]);
+        sequence.tick(1.75, SlideStatus::Ready);
+        sequence.skip();
+        assert_eq!(sequence.time(), 1.75);
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum TestState {
+        Splash,
+        Menu,
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_plugin(InputPlugin)
+            .add_state(TestState::Splash)
+            .add_plugin(SplashPlugin::new(
+                TestState::Splash,
+                TestState::Menu,
+                // フェードが無いので、飛ばすとすぐに終わる
+                vec![linear(10.0).with_fade(0.0, 0.0)],
+            ));
+        app
+    }
+
+    fn current(app: &App) -> &TestState {
+        app.world.resource::<State<TestState>>().current()
+    }
+
+    #[test]
+    fn key_press_skips_to_next_state() {
+        let mut app = app();
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(current(&app), &TestState::Splash);
+
+        app.world
+            .resource_mut::<Events<KeyboardInput>>()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::Space),
+                state: ElementState::Pressed,
+            });
+        // 飛ばした次のフレームでスライドが終わり、その次のフレームで状態が変わる
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(current(&app), &TestState::Menu);
+    }
+
+    #[test]
+    #[should_panic(expected = "SplashPlugin can only be added once")]
+    fn second_plugin_is_rejected() {
+        let mut app = app();
+        app.add_plugin(SplashPlugin::new(
+            TestState::Menu,
+            TestState::Splash,
+            vec![linear(1.0)],
+        ));
+    }
+}
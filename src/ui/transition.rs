@@ -0,0 +1,594 @@
+//
+// 画面の切り替え効果（フェード・ワイプ・スライド）
+//
+// `State::set` の代わりに `Transition::request` で次の状態を指定すると、
+// 画面を覆う効果を再生し、画面が完全に隠れた時点で状態を切り替えます。
+// `StateStackPlugin` がある場合は効果を始める前にガードで確認し、
+// 拒否された切り替えは効果を再生せずに `StateRejected` で知らせます。
+//
+// ```
+// app.add_plugin(TransitionPlugin::new(GameState::Splash).with_effect(TransitionEffect::Fade));
+//
+// fn system(mut transition: ResMut<Transition<GameState>>) {
+//     transition.request(GameState::Game);
+// }
+// ```
+//
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::schedule::StateData, prelude::*, transform::TransformSystem};
+
+use crate::state_scoped::KeepOnExit;
+use crate::state_stack::{check_state_change, report_rejected, StateOperation, StateStack};
+
+/// 切り替え効果を一番手前に表示するためのZ座標
+const OVERLAY_Z: f32 = 900.0;
+
+///
+/// 状態の切り替え効果を追加するプラグイン
+///
+/// 型引数 `S` は切り替える状態の型です。
+///
+pub struct TransitionPlugin<S> {
+    /// 型を決めるための値（使わない）
+    pub state: S,
+    pub effect: TransitionEffect,
+    /// 効果全体の時間（秒）
+    pub duration: f32,
+    pub color: Color,
+    pub policy: TransitionPolicy,
+}
+
+impl<S: StateData> TransitionPlugin<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            effect: TransitionEffect::Fade,
+            duration: 0.6,
+            color: Color::BLACK,
+            policy: TransitionPolicy::Queue,
+        }
+    }
+
+    pub fn with_effect(mut self, effect: TransitionEffect) -> Self {
+        self.effect = effect;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: TransitionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<S: StateData> Plugin for TransitionPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Transition::<S> {
+            effect: self.effect,
+            duration: self.duration,
+            color: self.color,
+            policy: self.policy,
+            active: None,
+            queue: VecDeque::new(),
+        })
+        .add_system(
+            transition_guard_system::<S>
+                .exclusive_system()
+                .at_start()
+                .label(TransitionSystem),
+        )
+        .add_system(transition_system::<S>.label(TransitionSystem))
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            overlay_z_system.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+///
+/// 状態を切り替えるシステムのラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransitionSystem;
+
+///
+/// 切り替え効果の種類
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionEffect {
+    /// 効果なしですぐに切り替える
+    Cut,
+    /// 色で覆ってから消す
+    Fade,
+    /// 左から覆い、右へ抜ける
+    Wipe,
+    /// 右から滑り込み、左へ抜ける
+    Slide,
+}
+
+///
+/// 切り替え中に別の切り替えを指定された時の扱い
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionPolicy {
+    /// 無視する
+    Reject,
+    /// 今の切り替えが終わってから行う
+    Queue,
+}
+
+///
+/// 覆い（オーバーレイ）の見た目
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayFrame {
+    /// 左端の位置（画面幅に対する%）
+    pub left: f32,
+    /// 幅（画面幅に対する%）
+    pub width: f32,
+    /// 不透明度
+    pub alpha: f32,
+}
+
+impl TransitionEffect {
+    ///
+    /// 進み具合 `t` (0.0 ~ 1.0) での覆いの見た目
+    ///
+    /// `t` が 0.5 の時に画面全体を覆います。
+    ///
+    pub fn frame(&self, t: f32) -> OverlayFrame {
+        let t = t.clamp(0.0, 1.0);
+        // 覆っていく途中は 0→1、抜けていく途中は 1→0
+        let cover = 1.0 - (1.0 - 2.0 * t).abs();
+        let full = OverlayFrame {
+            left: 0.0,
+            width: 100.0,
+            alpha: 1.0,
+        };
+        match self {
+            TransitionEffect::Cut => OverlayFrame { alpha: 0.0, ..full },
+            TransitionEffect::Fade => OverlayFrame {
+                alpha: cover,
+                ..full
+            },
+            TransitionEffect::Wipe if t < 0.5 => OverlayFrame {
+                width: cover * 100.0,
+                ..full
+            },
+            TransitionEffect::Wipe => OverlayFrame {
+                left: (1.0 - cover) * 100.0,
+                width: cover * 100.0,
+                ..full
+            },
+            TransitionEffect::Slide if t < 0.5 => OverlayFrame {
+                left: (1.0 - cover) * 100.0,
+                ..full
+            },
+            TransitionEffect::Slide => OverlayFrame {
+                left: (cover - 1.0) * 100.0,
+                ..full
+            },
+        }
+    }
+}
+
+struct ActiveTransition<S> {
+    next: S,
+    effect: TransitionEffect,
+    time: f32,
+    switched: bool,
+    /// ガードで許可されたか（許可されるまで効果を始めない）
+    accepted: bool,
+}
+
+///
+/// 状態の切り替えを管理するリソース
+///
+pub struct Transition<S> {
+    pub effect: TransitionEffect,
+    pub duration: f32,
+    pub color: Color,
+    pub policy: TransitionPolicy,
+    active: Option<ActiveTransition<S>>,
+    queue: VecDeque<(S, TransitionEffect)>,
+}
+
+impl<S: StateData> Transition<S> {
+    ///
+    /// 既定の効果で `next` に切り替える
+    ///
+    /// 受け付けた（または待ち行列に入れた）場合は `true` を返します。
+    ///
+    pub fn request(&mut self, next: S) -> bool {
+        let effect = self.effect;
+        self.request_with(next, effect)
+    }
+
+    ///
+    /// 効果を指定して `next` に切り替える
+    ///
+    /// 同じ状態への切り替えが既に進行中か待っている場合は何もしません。
+    ///
+    pub fn request_with(&mut self, next: S, effect: TransitionEffect) -> bool {
+        let pending = self
+            .active
+            .iter()
+            .map(|active| &active.next)
+            .chain(self.queue.iter().map(|(state, _)| state));
+        if pending.clone().any(|state| *state == next) {
+            return true;
+        }
+
+        if self.active.is_none() {
+            self.start(next, effect);
+            return true;
+        }
+        match self.policy {
+            TransitionPolicy::Reject => {
+                warn!(
+                    "transition to {:?} rejected: another transition is running",
+                    next
+                );
+                false
+            }
+            TransitionPolicy::Queue => {
+                self.queue.push_back((next, effect));
+                true
+            }
+        }
+    }
+
+    fn start(&mut self, next: S, effect: TransitionEffect) {
+        self.active = Some(ActiveTransition {
+            next,
+            effect,
+            time: 0.0,
+            switched: false,
+            accepted: false,
+        });
+    }
+
+    /// 待ち行列の次の切り替えを始める
+    fn start_next(&mut self) {
+        self.active = None;
+        if let Some((state, effect)) = self.queue.pop_front() {
+            self.start(state, effect);
+        }
+    }
+
+    /// 切り替え中か
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// 切り替え先（切り替え中の場合）
+    pub fn target(&self) -> Option<&S> {
+        self.active.as_ref().map(|active| &active.next)
+    }
+
+    /// 切り替えの進み具合 (0.0 ~ 1.0)
+    pub fn progress(&self) -> Option<f32> {
+        self.active
+            .as_ref()
+            .filter(|active| active.accepted)
+            .map(|active| {
+                if self.duration > 0.0 {
+                    (active.time / self.duration).min(1.0)
+                } else {
+                    1.0
+                }
+            })
+    }
+
+    /// 現在の覆いの見た目
+    pub fn frame(&self) -> Option<OverlayFrame> {
+        let effect = self.active.as_ref()?.effect;
+        self.progress().map(|t| effect.frame(t))
+    }
+
+    ///
+    /// 時間を `delta` 秒進める
+    ///
+    /// 画面が完全に隠れた時に、切り替える状態を返します。
+    /// ガードで許可されるまでは進みません。
+    ///
+    pub fn tick(&mut self, delta: f32) -> Option<S> {
+        let duration = self.duration;
+        let active = self.active.as_mut().filter(|active| active.accepted)?;
+        active.time += delta;
+
+        let half = duration * 0.5;
+        let mut next = None;
+        if !active.switched && (active.time >= half || active.effect == TransitionEffect::Cut) {
+            // 切り替えるフレームは必ず画面全体を覆う
+            active.time = half;
+            active.switched = true;
+            next = Some(active.next.clone());
+        }
+
+        if active.switched && (active.time >= duration || active.effect == TransitionEffect::Cut) {
+            self.start_next();
+        }
+        next
+    }
+}
+
+///
+/// 始める前の切り替えをガードで確認する
+///
+/// 拒否された場合は効果を再生せずに待ち行列の次の切り替えに進みます。
+///
+fn transition_guard_system<S: StateData>(world: &mut World) {
+    loop {
+        let next = match &world.resource::<Transition<S>>().active {
+            Some(active) if !active.accepted => active.next.clone(),
+            _ => return,
+        };
+        // 今の状態と同じなら切り替えないのでガードを通さない
+        let unchanged =
+            matches!(world.get_resource::<State<S>>(), Some(state) if state.current() == &next);
+        let checked = if unchanged {
+            Ok(())
+        } else {
+            check_state_change(world, StateOperation::Set(next))
+        };
+
+        let mut transition = world.resource_mut::<Transition<S>>();
+        match checked {
+            Ok(()) => {
+                if let Some(active) = &mut transition.active {
+                    active.accepted = true;
+                }
+                return;
+            }
+            Err(rejected) => {
+                transition.start_next();
+                report_rejected(world, rejected);
+            }
+        }
+    }
+}
+
+///
+/// 画面を覆うノード
+///
+#[derive(Component)]
+struct TransitionOverlay;
+
+fn transition_system<S: StateData>(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut transition: ResMut<Transition<S>>,
+    mut state: ResMut<State<S>>,
+    stack: Option<ResMut<StateStack<S>>>,
+    mut overlays: Query<(Entity, &mut Style, &mut UiColor), With<TransitionOverlay>>,
+) {
+    if !transition.is_active() && overlays.is_empty() {
+        return;
+    }
+
+    if let Some(next) = transition.tick(time.delta_seconds()) {
+        if state.current() != &next {
+            // StateStackPlugin がある場合はガードと履歴を通す
+            if let Some(mut stack) = stack {
+                stack.set(next);
+            } else if let Err(e) = state.set(next) {
+                warn!("failed to change state: {:?}", e);
+            }
+        }
+    }
+
+    let frame = match transition.frame() {
+        Some(frame) => frame,
+        None => {
+            for (entity, _, _) in overlays.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+    };
+
+    let mut color = transition.color;
+    color.set_a(color.a() * frame.alpha);
+    let style = Style {
+        position_type: PositionType::Absolute,
+        position: Rect {
+            left: Val::Percent(frame.left),
+            top: Val::Px(0.0),
+            ..Default::default()
+        },
+        size: Size::new(Val::Percent(frame.width), Val::Percent(100.0)),
+        ..Default::default()
+    };
+
+    match overlays.get_single_mut() {
+        Ok((_, mut overlay_style, mut overlay_color)) => {
+            *overlay_style = style;
+            overlay_color.0 = color;
+        }
+        Err(_) => {
+            commands
+                .spawn_bundle(NodeBundle {
+                    style,
+                    color: color.into(),
+                    ..Default::default()
+                })
+                .insert(TransitionOverlay)
+                .insert(KeepOnExit);
+        }
+    }
+}
+
+///
+/// 覆いを他のUIより手前に表示する
+///
+/// UIのZ座標は生成順で決まり、切り替え後の画面が後から作られるので
+/// 位置の伝播が終わった後に直接書き換えます。
+///
+fn overlay_z_system(mut overlays: Query<&mut GlobalTransform, With<TransitionOverlay>>) {
+    for mut transform in overlays.iter_mut() {
+        transform.translation.z = OVERLAY_Z;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+
+    use super::*;
+    use crate::state_stack::{StateChange, StateRejected, StateStackAppExt, StateStackPlugin};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum TestState {
+        Menu,
+        Game,
+        Locked,
+    }
+
+    fn transition(policy: TransitionPolicy) -> Transition<TestState> {
+        Transition {
+            effect: TransitionEffect::Fade,
+            duration: 1.0,
+            color: Color::BLACK,
+            policy,
+            active: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// ガードの確認を済ませたことにする
+    fn accept(transition: &mut Transition<TestState>) {
+        if let Some(active) = &mut transition.active {
+            active.accepted = true;
+        }
+    }
+
+    #[test]
+    fn reject_policy_ignores_requests_while_running() {
+        let mut transition = transition(TransitionPolicy::Reject);
+
+        assert!(transition.request(TestState::Game));
+        assert!(!transition.request(TestState::Locked));
+        // 進行中と同じ切り替えは受け付け済みとして扱う
+        assert!(transition.request(TestState::Game));
+        accept(&mut transition);
+
+        assert_eq!(transition.tick(0.5), Some(TestState::Game));
+        assert_eq!(transition.tick(0.5), None);
+        assert!(!transition.is_active());
+        assert!(transition.request(TestState::Locked));
+    }
+
+    #[test]
+    fn queue_policy_starts_next_after_current_finishes() {
+        let mut transition = transition(TransitionPolicy::Queue);
+
+        assert!(transition.request(TestState::Game));
+        assert!(transition.request(TestState::Menu));
+        assert!(transition.request(TestState::Menu));
+        accept(&mut transition);
+
+        assert_eq!(transition.tick(0.5), Some(TestState::Game));
+        assert_eq!(transition.tick(0.5), None);
+        assert_eq!(transition.target(), Some(&TestState::Menu));
+
+        // 次の切り替えもガードの確認までは進まない
+        assert_eq!(transition.tick(0.5), None);
+        assert_eq!(transition.progress(), None);
+        accept(&mut transition);
+        assert_eq!(transition.tick(0.5), Some(TestState::Menu));
+        assert_eq!(transition.tick(0.5), None);
+        assert!(!transition.is_active());
+    }
+
+    fn app(effect: TransitionEffect) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_state(TestState::Menu)
+            .add_plugin(StateStackPlugin::new(TestState::Menu))
+            .add_plugin(
+                TransitionPlugin::new(TestState::Menu)
+                    .with_effect(effect)
+                    .with_duration(60.0),
+            )
+            .add_state_guard(|change: &StateChange<TestState>, _: &World| {
+                if change.to == Some(TestState::Locked) {
+                    return Err("locked".to_string());
+                }
+                Ok(())
+            });
+        app.update();
+        app
+    }
+
+    fn overlays(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<TransitionOverlay>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    fn current(app: &App) -> TestState {
+        *app.world.resource::<State<TestState>>().current()
+    }
+
+    #[test]
+    fn guard_rejection_does_not_play_overlay() {
+        let mut app = app(TransitionEffect::Fade);
+        let mut reader = ManualEventReader::<StateRejected<TestState>>::default();
+
+        app.world
+            .resource_mut::<Transition<TestState>>()
+            .request(TestState::Locked);
+        app.update();
+
+        assert_eq!(overlays(&mut app), 0);
+        assert!(!app.world.resource::<Transition<TestState>>().is_active());
+        assert_eq!(current(&app), TestState::Menu);
+        let events = reader
+            .iter(app.world.resource::<Events<StateRejected<TestState>>>())
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reason, "locked");
+
+        // 許可される切り替えは覆いを表示する
+        app.world
+            .resource_mut::<Transition<TestState>>()
+            .request(TestState::Game);
+        app.update();
+        assert_eq!(overlays(&mut app), 1);
+    }
+
+    #[test]
+    fn rejected_change_is_skipped_in_queue() {
+        let mut app = app(TransitionEffect::Cut);
+        let mut reader = ManualEventReader::<StateRejected<TestState>>::default();
+
+        {
+            let mut transition = app.world.resource_mut::<Transition<TestState>>();
+            transition.request(TestState::Locked);
+            transition.request(TestState::Game);
+        }
+        app.update();
+        // 切り替えは StateStack の要求として次のフレームで反映される
+        app.update();
+
+        assert_eq!(current(&app), TestState::Game);
+        assert_eq!(overlays(&mut app), 0);
+        let events = reader
+            .iter(app.world.resource::<Events<StateRejected<TestState>>>())
+            .count();
+        assert_eq!(events, 1);
+    }
+}
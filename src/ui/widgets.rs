@@ -0,0 +1,1202 @@
+//
+// メニュー画面などで使うUIウィジェット
+//
+// ボタン・スライダー・トグル・ドロップダウン・テキスト入力をビルダーで作ります。
+// 表示する文字列は `LocalizationPlugin` の文字列表のキーとしても使います。
+// ウィジェットは `Focusable` なボタンで、ホバー・フォーカス時の色や入力の処理は
+// `WidgetsPlugin` が行い、値が変わると `ValueChanged<T>` イベントを送ります。
+//
+// ```
+// fn menu_setup(mut commands: Commands, theme: Res<WidgetTheme>) {
+//     spawn_panel(&mut commands, &theme, "menu").with_children(|parent| {
+//         title("メニュー").spawn(parent, &theme);
+//         button("play", "New Game").spawn(parent, &theme).insert(MenuButtonAction::Play);
+//         slider("volume", "音量", Slider::percent(0.8)).spawn(parent, &theme);
+//     });
+// }
+// ```
+//
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::localization::{Localization, LocalizedText};
+
+use super::focus::{
+    navigation_input, ButtonActivated, FocusScope, FocusSystem, Focusable, Focused,
+};
+use super::DEFAULT_FONT;
+
+/// テキスト入力のキャレットが点滅する間隔（秒）
+const CARET_BLINK: f32 = 0.5;
+
+///
+/// ウィジェットの入力と表示を行うプラグイン
+///
+pub struct WidgetsPlugin;
+
+impl Plugin for WidgetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WidgetTheme>()
+            .add_event::<ValueChanged<f32>>()
+            .add_event::<ValueChanged<bool>>()
+            .add_event::<ValueChanged<usize>>()
+            .add_event::<ValueChanged<String>>()
+            .add_system(widget_color_system)
+            .add_system_set(
+                SystemSet::new()
+                    .label(WidgetSystem)
+                    .after(FocusSystem::Navigate)
+                    .with_system(slider_system)
+                    .with_system(toggle_system)
+                    .with_system(dropdown_system)
+                    .with_system(text_input_system),
+            )
+            // 作った直後の表示も同じフレームで更新する
+            .add_system_to_stage(CoreStage::PostUpdate, widget_text_system::<Slider>)
+            .add_system_to_stage(CoreStage::PostUpdate, widget_text_system::<Toggle>)
+            .add_system_to_stage(CoreStage::PostUpdate, widget_text_system::<Dropdown>)
+            .add_system_to_stage(CoreStage::PostUpdate, widget_text_system::<TextInput>);
+    }
+}
+
+///
+/// ウィジェットの値を変更するシステムのラベル
+///
+/// `ValueChanged` を読むシステムはこの後に実行してください。
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WidgetSystem;
+
+///
+/// ウィジェットの値が変わった時に送られるイベント
+///
+/// | ウィジェット | T |
+/// |---|---|
+/// | `Slider` | `f32` |
+/// | `Toggle` | `bool` |
+/// | `Dropdown` | `usize`（選択肢の番号） |
+/// | `TextInput` | `String` |
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueChanged<T> {
+    pub entity: Entity,
+    pub value: T,
+}
+
+///
+/// ウィジェットの見た目
+///
+#[derive(Debug, Clone)]
+pub struct WidgetTheme {
+    pub font: Handle<Font>,
+    pub font_size: f32,
+    pub title_font_size: f32,
+    pub text_color: Color,
+    /// ボタンのレイアウト
+    pub button: Style,
+    /// ラベル付きウィジェットのレイアウト
+    pub field: Style,
+    pub panel_color: Color,
+    pub normal: Color,
+    pub hovered: Color,
+    pub focused: Color,
+    pub hovered_focused: Color,
+    pub pressed: Color,
+}
+
+impl FromWorld for WidgetTheme {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+
+        Self {
+            font: asset_server.load(DEFAULT_FONT),
+            font_size: 32.0,
+            title_font_size: 64.0,
+            text_color: Color::rgb(0.9, 0.9, 0.9),
+            button: Style {
+                size: Size::new(Val::Px(300.0), Val::Px(60.0)),
+                margin: Rect::all(Val::Px(12.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            field: Style {
+                size: Size::new(Val::Px(480.0), Val::Px(50.0)),
+                margin: Rect::all(Val::Px(8.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            panel_color: Color::CRIMSON,
+            normal: Color::rgb(0.15, 0.15, 0.15),
+            hovered: Color::rgb(0.25, 0.25, 0.25),
+            focused: Color::rgb(0.2, 0.35, 0.55),
+            hovered_focused: Color::rgb(0.25, 0.45, 0.65),
+            pressed: Color::rgb(0.35, 0.75, 0.35),
+        }
+    }
+}
+
+impl WidgetTheme {
+    pub fn text_style(&self) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: self.font_size,
+            color: self.text_color,
+        }
+    }
+}
+
+///
+/// `parent` の子としてウィジェットを作るビルダー
+///
+pub trait Widget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a>;
+}
+
+///
+/// 値を文字列で表示できるウィジェット
+///
+pub trait WidgetValue: Component {
+    fn display(&self, localization: Option<&Localization>) -> String;
+}
+
+/// `LocalizationPlugin` があればキーを現在の言語の文字列にする
+fn localize(localization: Option<&Localization>, key: &str) -> String {
+    localization.map_or_else(|| key.to_owned(), |localization| localization.text(key))
+}
+
+///
+/// ウィジェットのラベル
+///
+#[derive(Component, Debug, Clone)]
+pub struct WidgetLabel(pub String);
+
+///
+/// 画面のルートになるパネルを作る
+///
+/// `scope` はフォーカスの範囲 (`FocusScope`) です。
+///
+pub fn spawn_panel<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    theme: &WidgetTheme,
+    scope: &'static str,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut panel = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            margin: Rect::all(Val::Auto),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: theme.panel_color.into(),
+        ..Default::default()
+    });
+    panel.insert(FocusScope(scope));
+    panel
+}
+
+///
+/// ボタンと子のテキストを作る
+///
+/// `localized` の場合は `text` を文字列表のキーとして表示します。
+///
+fn spawn_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    theme: &WidgetTheme,
+    style: Style,
+    key: &'static str,
+    text: String,
+    localized: bool,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = parent.spawn_bundle(ButtonBundle {
+        style,
+        color: theme.normal.into(),
+        ..Default::default()
+    });
+    button.insert(Focusable(key)).with_children(|parent| {
+        let mut text_entity = parent.spawn_bundle(TextBundle {
+            text: Text::with_section(text.clone(), theme.text_style(), Default::default()),
+            ..Default::default()
+        });
+        if localized {
+            text_entity.insert(LocalizedText(text));
+        }
+    });
+    button
+}
+
+/// ラベル付きウィジェットを作る
+fn spawn_field<'w, 's, 'a, W: WidgetValue>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    theme: &WidgetTheme,
+    key: &'static str,
+    label: String,
+    widget: W,
+) -> EntityCommands<'w, 's, 'a> {
+    let text = field_text(&label, &widget.display(None));
+    let mut field = spawn_button(parent, theme, theme.field.clone(), key, text, false);
+    field.insert(WidgetLabel(label)).insert(widget);
+    field
+}
+
+fn field_text(label: &str, value: &str) -> String {
+    format!("{}: {}", label, value)
+}
+
+///
+/// 文字だけのラベル
+///
+/// 引数を埋め込む場合は `LocaleArgs` を追加してください。
+///
+pub struct LabelWidget {
+    pub text: String,
+    /// タイトルは大きい文字で表示する
+    pub title: bool,
+}
+
+pub fn label(text: impl Into<String>) -> LabelWidget {
+    LabelWidget {
+        text: text.into(),
+        title: false,
+    }
+}
+
+/// 画面のタイトル
+pub fn title(text: impl Into<String>) -> LabelWidget {
+    LabelWidget {
+        text: text.into(),
+        title: true,
+    }
+}
+
+impl Widget for LabelWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let (font_size, margin) = if self.title {
+            (theme.title_font_size, 40.0)
+        } else {
+            (theme.font_size, 8.0)
+        };
+
+        let mut label = parent.spawn_bundle(TextBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(margin)),
+                ..Default::default()
+            },
+            text: Text::with_section(
+                self.text.clone(),
+                TextStyle {
+                    font_size,
+                    ..theme.text_style()
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+        label.insert(LocalizedText(self.text));
+        label
+    }
+}
+
+///
+/// 押すと `ButtonActivated` を送るボタン
+///
+pub struct ButtonWidget {
+    pub key: &'static str,
+    pub text: String,
+}
+
+pub fn button(key: &'static str, text: impl Into<String>) -> ButtonWidget {
+    ButtonWidget {
+        key,
+        text: text.into(),
+    }
+}
+
+impl Widget for ButtonWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        spawn_button(
+            parent,
+            theme,
+            theme.button.clone(),
+            self.key,
+            self.text,
+            true,
+        )
+    }
+}
+
+///
+/// `min` ~ `max` の値を `step` 刻みで選ぶスライダー
+///
+/// フォーカス中は左右キーで変更でき、クリック・ドラッグした位置の値にもなります。
+///
+#[derive(Component, Debug, Clone)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    /// 値の表示方法
+    pub format: fn(f32) -> String,
+}
+
+impl Slider {
+    pub fn new(value: f32, min: f32, max: f32, step: f32) -> Self {
+        Self {
+            value,
+            min,
+            max,
+            step,
+            format: |value| format!("{:.2}", value),
+        }
+    }
+
+    /// 0.0 ~ 1.0 の値を百分率で表示するスライダー
+    pub fn percent(value: f32) -> Self {
+        Self {
+            format: |value| format!("{:.0}%", value * 100.0),
+            ..Self::new(value, 0.0, 1.0, 0.05)
+        }
+    }
+
+    pub fn with_format(mut self, format: fn(f32) -> String) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// `value` を範囲内に収めて `step` 刻みに丸める
+    pub fn snap(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.step > 0.0 {
+            let steps = ((value - self.min) / self.step).round();
+            (self.min + steps * self.step).min(self.max)
+        } else {
+            value
+        }
+    }
+
+    /// 範囲内の割合 (0.0 ~ 1.0) から値を求める
+    pub fn value_at(&self, fraction: f32) -> f32 {
+        self.snap(self.min + (self.max - self.min) * fraction)
+    }
+}
+
+impl WidgetValue for Slider {
+    fn display(&self, _localization: Option<&Localization>) -> String {
+        format!("< {} >", (self.format)(self.value))
+    }
+}
+
+///
+/// ラベル付きスライダー
+///
+pub struct SliderWidget {
+    pub key: &'static str,
+    pub label: String,
+    pub slider: Slider,
+}
+
+pub fn slider(key: &'static str, label: impl Into<String>, slider: Slider) -> SliderWidget {
+    SliderWidget {
+        key,
+        label: label.into(),
+        slider,
+    }
+}
+
+impl Widget for SliderWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        spawn_field(parent, theme, self.key, self.label, self.slider)
+    }
+}
+
+///
+/// ON / OFF を切り替えるトグル（チェックボックス）
+///
+#[derive(Component, Debug, Clone)]
+pub struct Toggle {
+    pub on: bool,
+}
+
+impl WidgetValue for Toggle {
+    fn display(&self, _localization: Option<&Localization>) -> String {
+        if self.on { "ON" } else { "OFF" }.to_owned()
+    }
+}
+
+///
+/// ラベル付きトグル
+///
+pub struct ToggleWidget {
+    pub key: &'static str,
+    pub label: String,
+    pub on: bool,
+}
+
+pub fn toggle(key: &'static str, label: impl Into<String>, on: bool) -> ToggleWidget {
+    ToggleWidget {
+        key,
+        label: label.into(),
+        on,
+    }
+}
+
+impl Widget for ToggleWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        spawn_field(parent, theme, self.key, self.label, Toggle { on: self.on })
+    }
+}
+
+///
+/// 決定で選択肢の一覧を開くドロップダウン
+///
+/// 閉じている間は左右キーで選択肢を切り替えられます。
+///
+#[derive(Component, Debug, Clone)]
+pub struct Dropdown {
+    pub options: Vec<String>,
+    pub selected: usize,
+    open: bool,
+}
+
+impl Dropdown {
+    pub fn new(options: impl IntoIterator<Item = impl Into<String>>, selected: usize) -> Self {
+        Self {
+            options: options.into_iter().map(Into::into).collect(),
+            selected,
+            open: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// `offset` だけ選択肢を進めた番号（端で折り返す）
+    pub fn offset_index(&self, offset: isize) -> usize {
+        let len = self.options.len().max(1) as isize;
+        (self.selected as isize + offset).rem_euclid(len) as usize
+    }
+}
+
+impl WidgetValue for Dropdown {
+    fn display(&self, localization: Option<&Localization>) -> String {
+        let option = self
+            .options
+            .get(self.selected)
+            .map_or_else(String::new, |option| localize(localization, option));
+        if self.open {
+            format!("{} ▲", option)
+        } else {
+            format!("< {} >", option)
+        }
+    }
+}
+
+///
+/// 開いたドロップダウンの選択肢
+///
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DropdownOption {
+    pub dropdown: Entity,
+    pub index: usize,
+}
+
+///
+/// ラベル付きドロップダウン
+///
+pub struct DropdownWidget {
+    pub key: &'static str,
+    pub label: String,
+    pub dropdown: Dropdown,
+}
+
+pub fn dropdown(
+    key: &'static str,
+    label: impl Into<String>,
+    options: impl IntoIterator<Item = impl Into<String>>,
+    selected: usize,
+) -> DropdownWidget {
+    DropdownWidget {
+        key,
+        label: label.into(),
+        dropdown: Dropdown::new(options, selected),
+    }
+}
+
+impl Widget for DropdownWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        // 開いた時に選択肢の分だけ縦に伸ばす
+        let mut style = theme.field.clone();
+        style.min_size.height = style.size.height;
+        style.size.height = Val::Auto;
+        style.flex_direction = FlexDirection::ColumnReverse;
+
+        let text = field_text(&self.label, &self.dropdown.display(None));
+        let mut field = spawn_button(parent, theme, style, self.key, text, false);
+        field.insert(WidgetLabel(self.label)).insert(self.dropdown);
+        field
+    }
+}
+
+///
+/// 1行のテキスト入力
+///
+/// フォーカス中は文字の入力・BackSpace / Delete での削除・
+/// 左右キー / Home / End でのキャレット移動ができます。
+///
+#[derive(Component, Debug, Clone)]
+pub struct TextInput {
+    pub value: String,
+    /// 入力できる最大の文字数
+    pub max_len: usize,
+    /// キャレットの位置（文字数）
+    caret: usize,
+    caret_visible: bool,
+}
+
+impl TextInput {
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        Self {
+            caret: value.chars().count(),
+            value,
+            max_len: 32,
+            caret_visible: false,
+        }
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// 値を変更してキャレットを末尾に移す
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+        self.caret = self.value.chars().count();
+    }
+
+    fn byte_index(&self, caret: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(caret)
+            .map_or(self.value.len(), |(index, _)| index)
+    }
+
+    /// キャレットの位置に文字を挿入する
+    pub fn insert(&mut self, c: char) -> bool {
+        if self.value.chars().count() >= self.max_len {
+            return false;
+        }
+        let index = self.byte_index(self.caret);
+        self.value.insert(index, c);
+        self.caret += 1;
+        true
+    }
+
+    /// キャレットの前の文字を削除する
+    pub fn backspace(&mut self) -> bool {
+        if self.caret == 0 {
+            return false;
+        }
+        self.caret -= 1;
+        let index = self.byte_index(self.caret);
+        self.value.remove(index);
+        true
+    }
+
+    /// キャレットの後ろの文字を削除する
+    pub fn delete(&mut self) -> bool {
+        if self.caret >= self.value.chars().count() {
+            return false;
+        }
+        let index = self.byte_index(self.caret);
+        self.value.remove(index);
+        true
+    }
+
+    pub fn move_caret(&mut self, offset: isize) {
+        let len = self.value.chars().count() as isize;
+        self.caret = (self.caret as isize + offset).clamp(0, len) as usize;
+    }
+}
+
+impl WidgetValue for TextInput {
+    fn display(&self, _localization: Option<&Localization>) -> String {
+        let mut text = self.value.clone();
+        text.insert(
+            self.byte_index(self.caret),
+            if self.caret_visible { '|' } else { ' ' },
+        );
+        text
+    }
+}
+
+///
+/// ラベル付きテキスト入力
+///
+pub struct TextInputWidget {
+    pub key: &'static str,
+    pub label: String,
+    pub input: TextInput,
+}
+
+pub fn text_input(
+    key: &'static str,
+    label: impl Into<String>,
+    value: impl Into<String>,
+) -> TextInputWidget {
+    TextInputWidget {
+        key,
+        label: label.into(),
+        input: TextInput::new(value),
+    }
+}
+
+impl TextInputWidget {
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.input.max_len = max_len;
+        self
+    }
+}
+
+impl Widget for TextInputWidget {
+    fn spawn<'w, 's, 'a>(
+        self,
+        parent: &'a mut ChildBuilder<'w, 's, '_>,
+        theme: &WidgetTheme,
+    ) -> EntityCommands<'w, 's, 'a> {
+        spawn_field(parent, theme, self.key, self.label, self.input)
+    }
+}
+
+///
+/// ボタンの状態（ホバー・フォーカス・クリック）に合わせて色を変える
+///
+fn widget_color_system(
+    theme: Res<WidgetTheme>,
+    mut interaction_query: Query<(&Interaction, &mut UiColor, Option<&Focused>), With<Button>>,
+) {
+    for (interaction, mut color, focused) in interaction_query.iter_mut() {
+        let new_color = match (*interaction, focused) {
+            (Interaction::Clicked, _) => theme.pressed,
+            (Interaction::Hovered, Some(_)) => theme.hovered_focused,
+            (Interaction::Hovered, None) => theme.hovered,
+            (Interaction::None, Some(_)) => theme.focused,
+            (Interaction::None, None) => theme.normal,
+        };
+
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
+
+/// 左右キー / 十字キーの入力 (-1.0, 0.0, 1.0)
+fn horizontal_input(
+    keyboard_input: &Input<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_input: &Input<GamepadButton>,
+) -> f32 {
+    let (direction, _) = navigation_input(keyboard_input, gamepads, gamepad_input);
+    direction.map_or(0.0, |direction| direction.x)
+}
+
+#[allow(clippy::type_complexity)]
+fn slider_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    windows: Res<Windows>,
+    mut changed: EventWriter<ValueChanged<f32>>,
+    mut sliders: Query<(
+        Entity,
+        &mut Slider,
+        &Interaction,
+        &Node,
+        &GlobalTransform,
+        Option<&Focused>,
+    )>,
+) {
+    let horizontal = horizontal_input(&keyboard_input, &gamepads, &gamepad_input);
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+
+    for (entity, mut slider, interaction, node, global_tf, focused) in sliders.iter_mut() {
+        let value = match (interaction, cursor) {
+            (Interaction::Clicked, Some(cursor)) => {
+                // ノードの左端からの割合
+                let left = global_tf.translation.x - node.size.x / 2.0;
+                let fraction = (cursor.x - left) / node.size.x.max(1.0);
+                slider.value_at(fraction.clamp(0.0, 1.0))
+            }
+            _ if focused.is_some() && horizontal != 0.0 => {
+                slider.snap(slider.value + slider.step * horizontal)
+            }
+            _ => continue,
+        };
+
+        if slider.value != value {
+            slider.value = value;
+            changed.send(ValueChanged { entity, value });
+        }
+    }
+}
+
+fn toggle_system(
+    mut activated: EventReader<ButtonActivated>,
+    mut changed: EventWriter<ValueChanged<bool>>,
+    mut toggles: Query<&mut Toggle>,
+) {
+    for ButtonActivated(entity) in activated.iter() {
+        if let Ok(mut toggle) = toggles.get_mut(*entity) {
+            toggle.on = !toggle.on;
+            changed.send(ValueChanged {
+                entity: *entity,
+                value: toggle.on,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dropdown_system(
+    mut commands: Commands,
+    theme: Res<WidgetTheme>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut activated: EventReader<ButtonActivated>,
+    mut changed: EventWriter<ValueChanged<usize>>,
+    mut dropdowns: Query<(Entity, &mut Dropdown, Option<&Focused>)>,
+    options: Query<(Entity, &DropdownOption)>,
+    focused: Query<Entity, With<Focused>>,
+) {
+    let set_focus = |commands: &mut Commands, entity: Entity| {
+        for old in focused.iter() {
+            commands.entity(old).remove::<Focused>();
+        }
+        commands.entity(entity).insert(Focused);
+    };
+
+    for ButtonActivated(entity) in activated.iter() {
+        // 選択肢が選ばれたら閉じてドロップダウンにフォーカスを戻す
+        if let Ok((_, option)) = options.get(*entity) {
+            if let Ok((dropdown_entity, mut dropdown, _)) = dropdowns.get_mut(option.dropdown) {
+                dropdown.open = false;
+                if dropdown.selected != option.index {
+                    dropdown.selected = option.index;
+                    changed.send(ValueChanged {
+                        entity: dropdown_entity,
+                        value: option.index,
+                    });
+                }
+                close_dropdown(&mut commands, dropdown_entity, &options);
+                set_focus(&mut commands, dropdown_entity);
+            }
+            continue;
+        }
+
+        if let Ok((dropdown_entity, mut dropdown, _)) = dropdowns.get_mut(*entity) {
+            dropdown.open = !dropdown.open;
+            if !dropdown.open {
+                close_dropdown(&mut commands, dropdown_entity, &options);
+                continue;
+            }
+
+            let mut selected = None;
+            commands.entity(dropdown_entity).with_children(|parent| {
+                for (index, option) in dropdown.options.iter().enumerate() {
+                    let entity = spawn_button(
+                        parent,
+                        &theme,
+                        theme.button.clone(),
+                        "dropdown_option",
+                        option.clone(),
+                        true,
+                    )
+                    .insert(DropdownOption {
+                        dropdown: dropdown_entity,
+                        index,
+                    })
+                    .id();
+                    if index == dropdown.selected {
+                        selected = Some(entity);
+                    }
+                }
+            });
+            if let Some(selected) = selected {
+                set_focus(&mut commands, selected);
+            }
+        }
+    }
+
+    let horizontal = horizontal_input(&keyboard_input, &gamepads, &gamepad_input);
+    if horizontal != 0.0 {
+        for (entity, mut dropdown, focused) in dropdowns.iter_mut() {
+            if focused.is_some() && !dropdown.open && !dropdown.options.is_empty() {
+                dropdown.selected = dropdown.offset_index(horizontal as isize);
+                changed.send(ValueChanged {
+                    entity,
+                    value: dropdown.selected,
+                });
+            }
+        }
+    }
+}
+
+fn close_dropdown(
+    commands: &mut Commands,
+    dropdown: Entity,
+    options: &Query<(Entity, &DropdownOption)>,
+) {
+    for (entity, option) in options.iter() {
+        if option.dropdown == dropdown {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn text_input_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut changed: EventWriter<ValueChanged<String>>,
+    mut blink: Local<f32>,
+    mut inputs: Query<(Entity, &mut TextInput, Option<&Focused>)>,
+) {
+    let typed: Vec<char> = characters
+        .iter()
+        .map(|event| event.char)
+        .filter(|c| !c.is_control())
+        .collect();
+
+    *blink = (*blink + time.delta_seconds()) % (CARET_BLINK * 2.0);
+    let caret_visible = *blink < CARET_BLINK;
+
+    for (entity, mut input, focused) in inputs.iter_mut() {
+        if focused.is_none() {
+            if input.caret_visible {
+                input.caret_visible = false;
+            }
+            continue;
+        }
+
+        let mut edited = false;
+        for c in typed.iter() {
+            edited |= input.insert(*c);
+        }
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            edited |= input.backspace();
+        }
+        if keyboard_input.just_pressed(KeyCode::Delete) {
+            edited |= input.delete();
+        }
+
+        if keyboard_input.just_pressed(KeyCode::Left) {
+            input.move_caret(-1);
+        }
+        if keyboard_input.just_pressed(KeyCode::Right) {
+            input.move_caret(1);
+        }
+        if keyboard_input.just_pressed(KeyCode::Home) {
+            input.caret = 0;
+        }
+        if keyboard_input.just_pressed(KeyCode::End) {
+            input.caret = input.value.chars().count();
+        }
+
+        if edited {
+            // 入力中はキャレットを表示し続ける
+            *blink = 0.0;
+            changed.send(ValueChanged {
+                entity,
+                value: input.value.clone(),
+            });
+        }
+
+        let visible = edited || caret_visible;
+        if input.caret_visible != visible {
+            input.caret_visible = visible;
+        }
+    }
+}
+
+///
+/// 値が変わったウィジェットの表示を更新する
+///
+/// 言語が変わった時は全てのウィジェットを更新します。
+///
+fn widget_text_system<W: WidgetValue>(
+    localization: Option<Res<Localization>>,
+    widgets: Query<(&W, ChangeTrackers<W>, &WidgetLabel, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    let all = matches!(&localization, Some(localization) if localization.is_changed());
+    let localization = localization.as_deref();
+
+    for (widget, tracker, label, children) in widgets.iter() {
+        if !all && !tracker.is_changed() {
+            continue;
+        }
+
+        let value = field_text(
+            &localize(localization, &label.0),
+            &widget.display(localization),
+        );
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = value.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetPlugin;
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bevy::ecs::system::CommandQueue;
+    use bevy::input::{keyboard::KeyboardInput, ElementState, InputPlugin};
+    use bevy::window::WindowId;
+
+    use super::super::focus::FocusPlugin;
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(TransformPlugin)
+            .init_resource::<Windows>()
+            .add_event::<ReceivedCharacter>()
+            .add_plugin(FocusPlugin)
+            .add_plugin(WidgetsPlugin);
+        app
+    }
+
+    /// 画面に1つだけウィジェットを作ってフォーカスする
+    fn spawn(app: &mut App, widget: impl Widget) -> Entity {
+        let theme = app.world.resource::<WidgetTheme>().clone();
+        let mut queue = CommandQueue::default();
+        let mut entity = None;
+        Commands::new(&mut queue, &app.world)
+            .spawn_bundle(NodeBundle::default())
+            .insert(FocusScope("test"))
+            .with_children(|parent| entity = Some(widget.spawn(parent, &theme).id()));
+        queue.apply(&mut app.world);
+        app.update();
+
+        let entity = entity.unwrap();
+        assert!(app.world.get::<Focused>(entity).is_some());
+        entity
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        for state in [ElementState::Pressed, ElementState::Released] {
+            app.world
+                .resource_mut::<Events<KeyboardInput>>()
+                .send(KeyboardInput {
+                    scan_code: 0,
+                    key_code: Some(key),
+                    state,
+                });
+            app.update();
+        }
+    }
+
+    fn changed<T: Clone + Send + Sync + 'static>(
+        app: &App,
+        reader: &mut ManualEventReader<ValueChanged<T>>,
+    ) -> Vec<ValueChanged<T>> {
+        reader
+            .iter(app.world.resource::<Events<ValueChanged<T>>>())
+            .cloned()
+            .collect()
+    }
+
+    /// ウィジェットに表示している文字列
+    fn text(app: &App, entity: Entity) -> String {
+        let children = app.world.get::<Children>(entity).unwrap();
+        app.world.get::<Text>(children[0]).unwrap().sections[0]
+            .value
+            .clone()
+    }
+
+    #[test]
+    fn slider_changes_with_arrow_keys() {
+        let mut app = app();
+        let entity = spawn(&mut app, slider("volume", "volume", Slider::percent(0.9)));
+        let mut reader = ManualEventReader::default();
+
+        press(&mut app, KeyCode::Right);
+        let events = changed::<f32>(&app, &mut reader);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].entity, entity);
+        assert!((events[0].value - 0.95).abs() < 1e-5, "{:?}", events);
+        assert_eq!(text(&app, entity), "volume: < 95% >");
+
+        // 上限では変わらない
+        press(&mut app, KeyCode::Right);
+        let events = changed::<f32>(&app, &mut reader);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].value, 1.0);
+        press(&mut app, KeyCode::Right);
+        assert!(changed::<f32>(&app, &mut reader).is_empty());
+
+        press(&mut app, KeyCode::Left);
+        let events = changed::<f32>(&app, &mut reader);
+        assert!((events[0].value - 0.95).abs() < 1e-5, "{:?}", events);
+        assert_eq!(
+            app.world.get::<Slider>(entity).unwrap().value,
+            events[0].value
+        );
+    }
+
+    #[test]
+    fn toggle_flips_on_activation() {
+        let mut app = app();
+        let entity = spawn(&mut app, toggle("fullscreen", "fullscreen", false));
+        let mut reader = ManualEventReader::default();
+
+        press(&mut app, KeyCode::Return);
+        assert_eq!(
+            changed::<bool>(&app, &mut reader),
+            [ValueChanged {
+                entity,
+                value: true
+            }]
+        );
+        assert_eq!(text(&app, entity), "fullscreen: ON");
+
+        press(&mut app, KeyCode::Return);
+        assert_eq!(
+            changed::<bool>(&app, &mut reader),
+            [ValueChanged {
+                entity,
+                value: false
+            }]
+        );
+        // 左右キーでは変わらない
+        press(&mut app, KeyCode::Right);
+        assert!(changed::<bool>(&app, &mut reader).is_empty());
+    }
+
+    #[test]
+    fn dropdown_selects_options() {
+        let mut app = app();
+        let entity = spawn(
+            &mut app,
+            dropdown("language", "language", ["ja", "en", "fr"], 0),
+        );
+        let mut reader = ManualEventReader::default();
+
+        // 閉じている間は左右キーで切り替える（端で折り返す）
+        press(&mut app, KeyCode::Left);
+        assert_eq!(
+            changed::<usize>(&app, &mut reader),
+            [ValueChanged { entity, value: 2 }]
+        );
+        assert_eq!(text(&app, entity), "language: < fr >");
+
+        // 開くと選択肢が作られ、選んでいるものにフォーカスする
+        press(&mut app, KeyCode::Return);
+        assert!(app.world.get::<Dropdown>(entity).unwrap().is_open());
+        let mut options = app.world.query::<(Entity, &DropdownOption)>();
+        let options: Vec<_> = options
+            .iter(&app.world)
+            .map(|(option, dropdown_option)| (dropdown_option.index, option))
+            .collect();
+        assert_eq!(options.len(), 3);
+        let option = |index: usize| options.iter().find(|(i, _)| *i == index).unwrap().1;
+        assert!(app.world.get::<Focused>(option(2)).is_some());
+
+        app.world
+            .resource_mut::<Events<ButtonActivated>>()
+            .send(ButtonActivated(option(1)));
+        app.update();
+        assert_eq!(
+            changed::<usize>(&app, &mut reader),
+            [ValueChanged { entity, value: 1 }]
+        );
+        assert!(!app.world.get::<Dropdown>(entity).unwrap().is_open());
+        assert!(app.world.get_entity(option(0)).is_none());
+        assert!(app.world.get::<Focused>(entity).is_some());
+        assert_eq!(text(&app, entity), "language: < en >");
+    }
+
+    #[test]
+    fn text_input_sends_edited_value() {
+        let mut app = app();
+        let entity = spawn(&mut app, text_input("name", "name", "ab").with_max_len(4));
+        let mut reader = ManualEventReader::default();
+
+        let mut values = |app: &App| {
+            changed::<String>(app, &mut reader)
+                .into_iter()
+                .map(|event| {
+                    assert_eq!(event.entity, entity);
+                    event.value
+                })
+                .collect::<Vec<_>>()
+        };
+        let type_chars = |app: &mut App, chars: &str| {
+            let mut events = app.world.resource_mut::<Events<ReceivedCharacter>>();
+            for char in chars.chars() {
+                events.send(ReceivedCharacter {
+                    id: WindowId::primary(),
+                    char,
+                });
+            }
+            app.update();
+        };
+
+        // 制御文字は無視し、最大の文字数で止まる
+        type_chars(&mut app, "c\u{8}de");
+        assert_eq!(values(&app), ["abcd"]);
+        type_chars(&mut app, "f");
+        assert!(values(&app).is_empty());
+
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Back);
+        assert_eq!(values(&app), ["abd"]);
+        press(&mut app, KeyCode::Home);
+        press(&mut app, KeyCode::Delete);
+        assert_eq!(values(&app), ["bd"]);
+        type_chars(&mut app, "x");
+        assert_eq!(values(&app), ["xbd"]);
+        // キャレットの移動だけでは送らない
+        press(&mut app, KeyCode::End);
+        assert!(values(&app).is_empty());
+        assert_eq!(app.world.get::<TextInput>(entity).unwrap().value, "xbd");
+    }
+}
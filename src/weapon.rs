@@ -0,0 +1,569 @@
+//
+// 弾を発射する武器
+//
+// `Weapon` を付けたエンティティは `trigger` が `true` の間、
+// 連射速度・バースト数・弾数に従って弾を生成します。
+//
+// 連射やリロードの時間はゲーム内の経過時間で進むので、一時停止中は止まります。
+//
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::look_at::LookTarget;
+use crate::pause::{gameplay_delta, GameTime, Pause};
+use crate::tracking::{move_forward, TrackingMissile};
+
+///
+/// 武器と弾を更新するプラグイン
+///
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(weapon_system.label(WeaponSystem))
+            .add_system(projectile_system.after(WeaponSystem));
+    }
+}
+
+///
+/// `weapon_system` のラベル
+///
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WeaponSystem;
+
+///
+/// 弾の種類
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileKind {
+    /// 直進する弾
+    Bullet,
+    /// ターゲットを追跡するミサイル（回転速度 rad/s）
+    TrackingMissile { rotation_speed: f32 },
+}
+
+///
+/// 生成する弾の設定
+///
+#[derive(Debug, Clone)]
+pub struct ProjectilePrefab {
+    pub kind: ProjectileKind,
+    pub texture: Handle<Image>,
+    pub scale: Vec3,
+    pub speed: f32,
+    /// 消えるまでの時間（秒）
+    pub lifetime: f32,
+}
+
+impl ProjectilePrefab {
+    pub fn bullet(texture: Handle<Image>, speed: f32) -> Self {
+        Self {
+            kind: ProjectileKind::Bullet,
+            texture,
+            scale: Vec3::ONE,
+            speed,
+            lifetime: 3.0,
+        }
+    }
+
+    pub fn tracking_missile(texture: Handle<Image>, speed: f32, rotation_speed: f32) -> Self {
+        Self {
+            kind: ProjectileKind::TrackingMissile { rotation_speed },
+            texture,
+            scale: Vec3::ONE,
+            speed,
+            lifetime: 5.0,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_lifetime(mut self, lifetime: f32) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+}
+
+///
+/// 弾数とリロード
+///
+#[derive(Debug, Clone)]
+pub struct Ammo {
+    pub capacity: u32,
+    pub remaining: u32,
+    /// リロードにかかる時間（秒）
+    pub reload_time: f32,
+    reload_timer: Option<f32>,
+}
+
+impl Ammo {
+    pub fn new(capacity: u32, reload_time: f32) -> Self {
+        Self {
+            capacity,
+            remaining: capacity,
+            reload_time,
+            reload_timer: None,
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload_timer.is_some()
+    }
+
+    /// 弾が減っていればリロードを開始する（リロード中は何もしない）
+    pub fn reload(&mut self) {
+        if !self.is_reloading() && self.remaining < self.capacity {
+            self.reload_timer = Some(self.reload_time);
+        }
+    }
+
+    /// 1発消費する、撃てない場合は `false`
+    fn consume(&mut self) -> bool {
+        if self.is_reloading() || self.remaining == 0 {
+            return false;
+        }
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.reload();
+        }
+        true
+    }
+
+    fn tick(&mut self, delta: f32) {
+        if let Some(timer) = &mut self.reload_timer {
+            *timer -= delta;
+            if *timer <= 0.0 {
+                self.remaining = self.capacity;
+                self.reload_timer = None;
+            }
+        }
+    }
+}
+
+///
+/// 武器
+///
+/// 弾は武器のエンティティのワールド座標・向き（+Y）から発射されます。
+///
+#[derive(Component, Debug, Clone)]
+pub struct Weapon {
+    pub prefab: ProjectilePrefab,
+    /// 1秒間に開始できるバーストの回数
+    pub fire_rate: f32,
+    /// 1回のバーストで撃つ回数
+    pub burst_count: u32,
+    /// バースト内の発射間隔（秒）
+    pub burst_interval: f32,
+    /// 1回の発射で扇状に撃つ弾の数
+    pub projectiles_per_shot: u32,
+    /// 扇の角度 (rad)
+    pub spread: f32,
+    /// 発射位置（武器のローカル座標の向きで回転）
+    pub muzzle_offset: Vec2,
+    pub ammo: Option<Ammo>,
+    /// 追跡ミサイルのターゲット
+    pub target: LookTarget,
+    /// 引き金を引いている間は `true`
+    pub trigger: bool,
+    cooldown: f32,
+    burst_remaining: u32,
+    burst_timer: f32,
+}
+
+impl Weapon {
+    ///
+    /// `fire_rate` は 1秒間に開始できるバーストの回数です。
+    ///
+    /// # Panics
+    ///
+    /// `fire_rate` が正の数でない場合
+    ///
+    pub fn new(prefab: ProjectilePrefab, fire_rate: f32) -> Self {
+        assert!(
+            fire_rate > 0.0,
+            "fire_rate must be positive, got {}",
+            fire_rate
+        );
+        Self {
+            prefab,
+            fire_rate,
+            burst_count: 1,
+            burst_interval: 0.0,
+            projectiles_per_shot: 1,
+            spread: 0.0,
+            muzzle_offset: Vec2::ZERO,
+            ammo: None,
+            target: LookTarget::Cursor,
+            trigger: false,
+            cooldown: 0.0,
+            burst_remaining: 0,
+            burst_timer: 0.0,
+        }
+    }
+
+    pub fn with_burst(mut self, burst_count: u32, burst_interval: f32) -> Self {
+        self.burst_count = burst_count;
+        self.burst_interval = burst_interval;
+        self
+    }
+
+    pub fn with_spread(mut self, projectiles_per_shot: u32, spread: f32) -> Self {
+        self.projectiles_per_shot = projectiles_per_shot;
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_muzzle_offset(mut self, muzzle_offset: Vec2) -> Self {
+        self.muzzle_offset = muzzle_offset;
+        self
+    }
+
+    pub fn with_ammo(mut self, ammo: Ammo) -> Self {
+        self.ammo = Some(ammo);
+        self
+    }
+
+    pub fn with_target(mut self, target: LookTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// 次のバーストを開始できる状態か
+    pub fn is_ready(&self) -> bool {
+        let reloading = matches!(&self.ammo, Some(ammo) if ammo.is_reloading());
+        self.cooldown <= 0.0 && self.burst_remaining == 0 && !reloading
+    }
+
+    ///
+    /// 時間を `delta` 秒進めて、このステップで発射する回数を返す
+    ///
+    /// `fire_rate` が正の数でない場合は発射しません。
+    ///
+    pub fn update(&mut self, delta: f32) -> u32 {
+        self.cooldown = (self.cooldown - delta).max(0.0);
+        if let Some(ammo) = &mut self.ammo {
+            ammo.tick(delta);
+        }
+        if self.burst_remaining > 0 {
+            self.burst_timer -= delta;
+        }
+
+        if self.trigger && self.fire_rate > 0.0 && self.is_ready() {
+            self.burst_remaining = self.burst_count;
+            self.burst_timer = 0.0;
+            self.cooldown = 1.0 / self.fire_rate;
+        }
+
+        let mut shots = 0;
+        while self.burst_remaining > 0 && self.burst_timer <= 0.0 {
+            let loaded = match &mut self.ammo {
+                Some(ammo) => ammo.consume(),
+                None => true,
+            };
+            if !loaded {
+                // 弾切れの場合はバーストを中断してリロードを待つ
+                self.burst_remaining = 0;
+                break;
+            }
+
+            shots += 1;
+            self.burst_remaining -= 1;
+            self.burst_timer += self.burst_interval;
+        }
+
+        if self.burst_remaining == 0 {
+            self.burst_timer = 0.0;
+        }
+
+        shots
+    }
+
+    ///
+    /// 1回の発射で撃つ弾それぞれの角度（武器の向きに対する角度）
+    ///
+    pub fn spread_angles(&self) -> impl Iterator<Item = f32> {
+        let count = self.projectiles_per_shot.max(1);
+        let spread = self.spread;
+        (0..count).map(move |i| {
+            if count == 1 {
+                0.0
+            } else {
+                -spread / 2.0 + spread * i as f32 / (count - 1) as f32
+            }
+        })
+    }
+}
+
+///
+/// 武器から発射された弾
+///
+#[derive(Component, Debug)]
+pub struct Projectile {
+    pub speed: f32,
+    /// 発射した武器のエンティティ
+    pub source: Entity,
+    pub lifetime: Timer,
+}
+
+fn weapon_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    pause: Option<Res<Pause>>,
+    game_time: Option<Res<GameTime>>,
+    mut query: Query<(Entity, &mut Weapon, &GlobalTransform)>,
+) {
+    let delta = gameplay_delta(&time, pause.as_deref(), game_time.as_deref());
+    // 一時停止中は発射しない
+    if delta <= 0.0 {
+        return;
+    }
+
+    for (entity, mut weapon, global_tf) in query.iter_mut() {
+        let shots = weapon.update(delta);
+        if shots == 0 {
+            continue;
+        }
+
+        let muzzle = global_tf.translation + global_tf.rotation * weapon.muzzle_offset.extend(0.0);
+
+        for _ in 0..shots {
+            for angle in weapon.spread_angles() {
+                let transform = Transform {
+                    translation: muzzle,
+                    rotation: global_tf.rotation * Quat::from_rotation_z(angle),
+                    scale: weapon.prefab.scale,
+                };
+                spawn_projectile(&mut commands, &weapon, entity, transform);
+            }
+        }
+    }
+}
+
+fn spawn_projectile(commands: &mut Commands, weapon: &Weapon, source: Entity, transform: Transform) {
+    let prefab = &weapon.prefab;
+    let mut projectile = commands.spawn_bundle(SpriteBundle {
+        transform,
+        texture: prefab.texture.clone(),
+        ..Default::default()
+    });
+
+    projectile.insert(Projectile {
+        speed: prefab.speed,
+        source,
+        lifetime: Timer::from_seconds(prefab.lifetime, false),
+    });
+
+    if let ProjectileKind::TrackingMissile { rotation_speed } = prefab.kind {
+        projectile.insert(TrackingMissile {
+            rotation_speed,
+            speed: prefab.speed,
+            target: weapon.target,
+        });
+    }
+}
+
+fn projectile_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    pause: Option<Res<Pause>>,
+    game_time: Option<Res<GameTime>>,
+    mut query: Query<(Entity, &mut Transform, &mut Projectile, Option<&TrackingMissile>)>,
+) {
+    let delta = gameplay_delta(&time, pause.as_deref(), game_time.as_deref());
+
+    for (entity, mut tf, mut projectile, tracking_missile) in query.iter_mut() {
+        // 追跡ミサイルの移動は TrackingPlugin が行う
+        if tracking_missile.is_none() {
+            move_forward(&mut tf, projectile.speed * delta);
+        }
+
+        if projectile
+            .lifetime
+            .tick(Duration::from_secs_f32(delta))
+            .finished()
+        {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::look_at::z_angle;
+
+    /// 2進数で割り切れるので、時間の計算に誤差が出ない
+    const DELTA: f32 = 0.125;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<GameTime>()
+            .init_resource::<Pause>()
+            .add_plugin(WeaponPlugin);
+        app
+    }
+
+    fn spawn_weapon(app: &mut App, weapon: Weapon) -> Entity {
+        app.world
+            .spawn()
+            .insert(Weapon {
+                trigger: true,
+                ..weapon
+            })
+            .insert(GlobalTransform::identity())
+            .id()
+    }
+
+    fn weapon(fire_rate: f32) -> Weapon {
+        Weapon::new(
+            ProjectilePrefab::bullet(Handle::default(), 100.0),
+            fire_rate,
+        )
+    }
+
+    /// ゲーム内時間を `DELTA` 秒進めて1フレーム実行する
+    fn tick(app: &mut App) {
+        let paused = app.world.resource::<Pause>().is_paused();
+        app.world.resource_mut::<GameTime>().advance(DELTA, paused);
+        app.update();
+    }
+
+    fn projectiles(app: &mut App) -> usize {
+        app.world.query::<&Projectile>().iter(&app.world).count()
+    }
+
+    /// 1フレームずつ進めて、それまでに発射された弾の数を返す
+    fn shots_per_frame(app: &mut App, frames: usize) -> Vec<usize> {
+        (0..frames)
+            .map(|_| {
+                tick(app);
+                projectiles(app)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fires_at_fire_rate() {
+        let mut app = app();
+        spawn_weapon(&mut app, weapon(2.0));
+
+        // 0.5 秒ごと（4フレームごと）に発射する
+        assert_eq!(
+            shots_per_frame(&mut app, 9),
+            vec![1, 1, 1, 1, 2, 2, 2, 2, 3]
+        );
+    }
+
+    #[test]
+    fn stops_when_trigger_released() {
+        let mut app = app();
+        let entity = spawn_weapon(&mut app, weapon(8.0));
+        assert_eq!(shots_per_frame(&mut app, 2), vec![1, 2]);
+
+        app.world.get_mut::<Weapon>(entity).unwrap().trigger = false;
+        assert_eq!(shots_per_frame(&mut app, 3), vec![2, 2, 2]);
+    }
+
+    #[test]
+    fn fires_burst_at_burst_interval() {
+        let mut app = app();
+        spawn_weapon(&mut app, weapon(1.0).with_burst(3, 0.25));
+
+        // バースト内は 0.25 秒ごと、次のバーストは開始から 1 秒後
+        assert_eq!(
+            shots_per_frame(&mut app, 9),
+            vec![1, 1, 2, 2, 3, 3, 3, 3, 4]
+        );
+    }
+
+    #[test]
+    fn fires_burst_within_single_frame() {
+        let mut app = app();
+        spawn_weapon(&mut app, weapon(1.0).with_burst(3, 0.0));
+
+        assert_eq!(shots_per_frame(&mut app, 2), vec![3, 3]);
+    }
+
+    #[test]
+    fn reloads_when_empty() {
+        let mut app = app();
+        let entity = spawn_weapon(&mut app, weapon(8.0).with_ammo(Ammo::new(2, 0.5)));
+
+        // 2発撃った後、0.5 秒のリロードを待つ
+        assert_eq!(shots_per_frame(&mut app, 7), vec![1, 2, 2, 2, 2, 3, 4]);
+        let weapon = app.world.get::<Weapon>(entity).unwrap();
+        assert!(weapon.ammo.as_ref().unwrap().is_reloading());
+    }
+
+    #[test]
+    fn reload_requires_spent_ammo() {
+        let mut ammo = Ammo::new(2, 0.5);
+        ammo.reload();
+        assert!(!ammo.is_reloading());
+
+        assert!(ammo.consume());
+        ammo.reload();
+        assert!(ammo.is_reloading());
+        assert!(!ammo.consume());
+
+        ammo.tick(0.5);
+        assert!(!ammo.is_reloading());
+        assert_eq!(ammo.remaining, 2);
+    }
+
+    #[test]
+    fn does_not_fire_or_cool_down_while_paused() {
+        let mut app = app();
+        spawn_weapon(&mut app, weapon(2.0));
+        assert_eq!(shots_per_frame(&mut app, 1), vec![1]);
+
+        app.world.resource_mut::<Pause>().set(true);
+        assert_eq!(shots_per_frame(&mut app, 8), vec![1; 8]);
+
+        // 一時停止前の経過時間から再開する
+        app.world.resource_mut::<Pause>().set(false);
+        assert_eq!(shots_per_frame(&mut app, 4), vec![1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn spreads_projectiles() {
+        let mut app = app();
+        spawn_weapon(&mut app, weapon(1.0).with_spread(3, 1.0));
+        tick(&mut app);
+
+        let mut angles: Vec<f32> = app
+            .world
+            .query_filtered::<&Transform, With<Projectile>>()
+            .iter(&app.world)
+            .map(|tf| z_angle(tf.rotation))
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        for (angle, expected) in angles.iter().zip([-0.5, 0.0, 0.5]) {
+            assert!((angle - expected).abs() < 1e-5);
+        }
+        assert_eq!(angles.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "fire_rate must be positive")]
+    fn rejects_zero_fire_rate() {
+        weapon(0.0);
+    }
+
+    #[test]
+    fn ignores_invalid_fire_rate_set_later() {
+        let mut weapon = weapon(1.0);
+        weapon.trigger = true;
+        weapon.fire_rate = -1.0;
+        assert_eq!(weapon.update(DELTA), 0);
+        weapon.fire_rate = f32::NAN;
+        assert_eq!(weapon.update(DELTA), 0);
+    }
+}